@@ -0,0 +1,108 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rendering HTML straight from the parser's events, against rendering a
+//! parsed document, against the old way of building an `Html` tree and
+//! formatting it. Run with `cargo bench --features bench` on nightly.
+
+#![feature(test)]
+
+extern crate rustdown;
+extern crate test;
+
+use test::Bencher;
+
+use rustdown::convert::parse_document;
+use rustdown::html::{Html, HtmlOptions, WriteHtml, write_events};
+use rustdown::parser::{Event, Parser, Tag};
+use rustdown::types::Document;
+
+fn big_document() -> Vec<String> {
+    let mut lines = vec![];
+    for i in 0..2000 {
+        lines.push(format!("## Section {}", i));
+        lines.push(String::new());
+        lines.push("Some text, with <markup> & entities &copy;  ".to_string());
+        lines.push("over a couple of lines.".to_string());
+        lines.push(String::new());
+        lines.push("- one".to_string());
+        lines.push("- two".to_string());
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// The tag a block or span of the generated document is written as.
+fn tag_name(tag: &Tag) -> String {
+    match tag {
+        &Tag::Heading(level) => format!("h{}", level),
+        &Tag::List(Some(_)) => "ol".to_string(),
+        &Tag::List(None) => "ul".to_string(),
+        &Tag::Item => "li".to_string(),
+        &Tag::Emphasis => "em".to_string(),
+        &Tag::Strong => "strong".to_string(),
+        _ => "p".to_string(),
+    }
+}
+
+/// Build one `Html` tree per top-level block, the way the renderer did
+/// before it wrote straight from events.
+fn html_tree(document: &Document) -> Vec<Html> {
+    let mut blocks = vec![];
+    let mut open: Vec<Html> = vec![];
+    for (event, _) in document.events() {
+        match event {
+            Event::Start(ref tag) => open.push(Html::new_empty(tag_name(tag))),
+            Event::End(_) => {
+                let html = open.pop().unwrap();
+                match open.last_mut() {
+                    Some(parent) => parent.add_tag(html),
+                    None => blocks.push(html),
+                }
+            }
+            Event::Text(text) => open.last_mut().unwrap().add_string(text),
+            Event::SoftBreak => open.last_mut().unwrap().add_string("\n".to_string()),
+            _ => {}
+        }
+    }
+    blocks
+}
+
+#[bench]
+fn bench_html_tree(b: &mut Bencher) {
+    let document = parse_document(&big_document()).unwrap();
+    b.iter(|| {
+        let blocks: Vec<String> = html_tree(&document).iter()
+                                                      .map(|html| format!("{}", html))
+                                                      .collect();
+        blocks.join("\n")
+    });
+}
+
+#[bench]
+fn bench_write_events(b: &mut Bencher) {
+    let lines = big_document();
+    let options = HtmlOptions::default();
+    b.iter(|| {
+        let mut out = String::new();
+        write_events(Parser::new(&lines).spanned(), &mut out, &options).unwrap();
+        out
+    });
+}
+
+#[bench]
+fn bench_write_html(b: &mut Bencher) {
+    let document = parse_document(&big_document()).unwrap();
+    let options = HtmlOptions::default();
+    b.iter(|| {
+        let mut out = String::new();
+        document.write_html(&mut out, &options).unwrap();
+        out
+    });
+}
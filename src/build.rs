@@ -0,0 +1,485 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converting a whole directory of markdown at once.
+//!
+//! The source directory is mirrored into the output directory: each
+//! markdown file becomes a standalone HTML page at the same path, with its
+//! links to other markdown files pointing at their pages, and every other
+//! file is copied across. Hidden files and directories are left out.
+//!
+//! With `BuildOptions::site` set, the pages are built into a static site
+//! instead, as described in `site`.
+//!
+//! The output directory keeps a manifest of what the last build wrote, so
+//! that files which haven't changed since can be skipped, and outputs
+//! whose sources have gone can be removed. Only files in the manifest are
+//! ever removed.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use convert;
+use encoding;
+use encoding::{DecoderTrap, Encoding};
+use feed::StripMore;
+use html::{HtmlOptions, SoftBreakStyle};
+use lines;
+use links;
+use links::RewriteLinks;
+use preprocessor;
+use site;
+use site::SiteOptions;
+use standalone;
+use standalone::StandaloneOptions;
+use types::{Document, VisitorMut};
+use types::visit::MapSpans;
+
+/// The manifest's name in the output directory.
+pub const MANIFEST: &'static str = ".rustdown-build";
+
+/// The manifest entry holding the hash of the settings of the last build.
+/// Hidden files are never sources, so no output can have this path.
+const SETTINGS: &'static str = ".settings";
+
+/// How to tell whether a file has changed since the last build.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Freshness {
+    /// The output is up to date if it's newer than its source, and the
+    /// build options are the same as last time.
+    Mtime,
+    /// The output is up to date if its source, and the build options,
+    /// hash the same as they did last time.
+    Hash,
+}
+
+impl FromStr for Freshness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Freshness, String> {
+        match s {
+            "mtime" => Ok(Freshness::Mtime),
+            "hash" => Ok(Freshness::Hash),
+            _ => Err(format!("unknown comparison `{}` (expected mtime or hash)", s)),
+        }
+    }
+}
+
+/// Options for building a directory.
+///
+#[derive(Clone, Debug)]
+pub struct BuildOptions {
+    pub html: HtmlOptions,
+    pub standalone: StandaloneOptions,
+    pub freshness: Freshness,
+    /// Rebuild everything, even files that look up to date.
+    pub force: bool,
+    pub site: Option<SiteOptions>,
+}
+
+impl Default for BuildOptions {
+    fn default() -> BuildOptions {
+        BuildOptions {
+            html: HtmlOptions::default(),
+            standalone: StandaloneOptions::default(),
+            freshness: Freshness::Mtime,
+            force: false,
+            site: None,
+        }
+    }
+}
+
+/// How many files a build did what to.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct BuildSummary {
+    pub converted: usize,
+    pub copied: usize,
+    /// Files made from the tree as a whole, like a site's index pages.
+    pub generated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+fn io_error(path: &Path, e: io::Error) -> String {
+    format!("Error with {}:\n{}", path.display(), e)
+}
+
+/// A relative path with `/` separators, as written in the manifest.
+pub fn manifest_path(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>().join("/")
+}
+
+/// Every file under `dir`, relative to `root`, in order, skipping hidden
+/// ones and the directory `skip`.
+fn walk(root: &Path, dir: &Path, skip: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let mut entries = vec![];
+    for entry in try!(fs::read_dir(dir).map_err(|e| io_error(dir, e))) {
+        entries.push(try!(entry.map_err(|e| io_error(dir, e))).path());
+    }
+    entries.sort();
+    for path in entries.into_iter() {
+        let hidden = path.file_name().map_or(true, |n| n.to_string_lossy().starts_with('.'));
+        if hidden || path.canonicalize().ok().map_or(false, |p| p == skip) {
+            continue;
+        }
+        if path.is_dir() {
+            try!(walk(root, &path, skip, files));
+        }
+        else {
+            files.push(path.strip_prefix(root).unwrap().to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// What a file in the output directory is made from. Source paths are
+/// relative to the source directory.
+pub enum Output {
+    Copy(PathBuf),
+    /// A page converted from a markdown file on its own.
+    Page(PathBuf),
+    /// Contents made from the whole tree, along with the source file
+    /// they're mostly made from, if there is one. These are up to date if
+    /// they're the same as what's already there.
+    Generated(Option<PathBuf>, Vec<u8>),
+}
+
+/// The relative path a source file is written to.
+pub fn output_path(source: &Path) -> PathBuf {
+    if links::is_markdown(source) { source.with_extension("html") } else { source.to_path_buf() }
+}
+
+fn read_manifest(out: &Path) -> HashMap<String, String> {
+    let mut manifest = HashMap::new();
+    let mut source = String::new();
+    if fs::File::open(out.join(MANIFEST)).and_then(|mut f| f.read_to_string(&mut source)).is_ok() {
+        for line in source.lines() {
+            let mut parts = line.splitn(2, ' ');
+            if let (Some(hash), Some(path)) = (parts.next(), parts.next()) {
+                manifest.insert(path.to_string(), hash.to_string());
+            }
+        }
+    }
+    manifest
+}
+
+pub fn read_bytes(path: &Path) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    try!(fs::File::open(path).and_then(|mut f| f.read_to_end(&mut bytes))
+         .map_err(|e| io_error(path, e)));
+    Ok(bytes)
+}
+
+/// The 64-bit FNV-1a hash of `settings` and `bytes`, in hex. Unlike the
+/// standard library's hashers, it's the same from one build of rustdown
+/// to the next, which the manifest needs.
+fn hash(bytes: &[u8], settings: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in settings.as_bytes().iter().chain(&[0xff]).chain(bytes.iter()) {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// The build options that change what's written, one per line. `force`
+/// and the freshness check only change which files are, so they're left
+/// out.
+fn settings(options: &BuildOptions) -> String {
+    let soft_break = match options.html.soft_break {
+        SoftBreakStyle::Newline => "newline",
+        SoftBreakStyle::Space => "space",
+        SoftBreakStyle::LineBreak => "br",
+    };
+    let standalone = &options.standalone;
+    let mut lines = vec![
+        format!("soft-break {}", soft_break),
+        format!("heading-ids {}", options.html.heading_ids),
+        format!("indent {}", options.html.pretty.map_or("none".to_string(), |n| n.to_string())),
+        format!("sourcepos {}", options.html.sourcepos),
+        format!("title {:?}", standalone.title.as_ref().map_or("", |title| &title[..])),
+        format!("template {:?}", standalone.template.source()),
+    ];
+    lines.extend(standalone.stylesheets.iter().map(|url| format!("css {:?}", url)));
+    lines.extend(standalone.inline_css.iter().map(|css| format!("inline-css {:?}", css)));
+    if let Some(ref site) = options.site {
+        lines.push(format!("site {}", site.base_url.as_ref().map_or(String::new(), |url| {
+            url.to_string()
+        })));
+        lines.extend(site.stop_words.iter().map(|word| format!("stop-word {}", word)));
+    }
+    lines.join("\n")
+}
+
+fn is_newer(output: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(output), modified(source)) {
+        (Some(output), Some(source)) => output >= source,
+        _ => false,
+    }
+}
+
+/// Parse the markdown in `source`, with its links pointing at the pages
+/// they'll be converted to.
+pub fn parse_page(source: &str) -> Result<Document, String> {
+    let (lines, mut map) = lines::split_lines_mapped(source);
+    let lines = preprocessor::replace_tabs_mapped(&lines, &mut map);
+    let mut transforms: Vec<Box<VisitorMut>> = vec![Box::new(MapSpans(map)),
+                                                    Box::new(RewriteLinks::new())];
+    convert::parse_document_with(&lines, &mut transforms).map_err(|e| e.to_string())
+}
+
+/// Read a markdown file to convert. Sources are read as UTF-8, unless
+/// they start with a byte order mark for UTF-16.
+pub fn read_page(path: &Path) -> Result<String, String> {
+    let bytes = try!(read_bytes(path));
+    encoding::decode(&bytes, Encoding::Utf8, DecoderTrap::Strict).map_err(|e| {
+        format!("Error with {}:\n{} (sources without a byte order mark must be UTF-8)",
+                path.display(), e)
+    })
+}
+
+/// Convert the markdown in `source` to a standalone page.
+pub fn convert_page(source: &str, options: &BuildOptions) -> Result<String, String> {
+    let mut document = try!(parse_page(source));
+    StripMore.visit_document_mut(&mut document);
+    standalone::render(&document, &options.html, &options.standalone)
+}
+
+/// Build the markdown files under `src` into `out`.
+pub fn build(src: &Path, out: &Path, options: &BuildOptions) -> Result<BuildSummary, String> {
+    try!(fs::create_dir_all(out).map_err(|e| io_error(out, e)));
+    let out_dir = try!(out.canonicalize().map_err(|e| io_error(out, e)));
+    let mut sources = vec![];
+    try!(walk(src, src, &out_dir, &mut sources));
+
+    let outputs = match options.site {
+        Some(ref site) => try!(site::plan(src, &sources, options, site)),
+        None => sources.into_iter().map(|source| {
+            let output = output_path(&source);
+            (output, if links::is_markdown(&source) { Output::Page(source) }
+                     else { Output::Copy(source) })
+        }).collect(),
+    };
+
+    let previous = read_manifest(out);
+    let settings = settings(options);
+    let stamp = hash(settings.as_bytes(), "");
+    let same_settings = previous.get(SETTINGS) == Some(&stamp);
+    let mut manifest = vec![format!("{} {}", stamp, SETTINGS)];
+    let mut written = HashSet::new();
+    written.insert(SETTINGS.to_string());
+    let mut summary = BuildSummary::default();
+    for (output_relative, what) in outputs.into_iter() {
+        let output = out.join(&output_relative);
+        let key = manifest_path(&output_relative);
+        written.insert(key.clone());
+
+        let source = match what {
+            Output::Copy(ref source) | Output::Page(ref source) => src.join(source),
+            Output::Generated(..) => PathBuf::new(),
+        };
+        // Copied files come out the same whatever the settings are.
+        let page_settings = match what {
+            Output::Page(_) => &settings[..],
+            _ => "",
+        };
+        let mut bytes = None;
+        let mut digest = "-".to_string();
+        if let Output::Generated(_, ref contents) = what {
+            digest = hash(contents, "");
+        }
+        else if options.freshness == Freshness::Hash {
+            let contents = try!(read_bytes(&source));
+            digest = hash(&contents, page_settings);
+            bytes = Some(contents);
+        }
+        let fresh = !options.force && output.is_file() && match what {
+            Output::Generated(_, ref contents) => read_bytes(&output).ok().as_ref() == Some(contents),
+            _ if options.freshness == Freshness::Mtime => {
+                (same_settings || page_settings.is_empty()) && is_newer(&output, &source)
+            }
+            _ => previous.get(&key) == Some(&digest),
+        };
+        manifest.push(format!("{} {}", digest, key));
+        if fresh {
+            summary.unchanged += 1;
+            continue;
+        }
+
+        let contents = match what {
+            Output::Generated(from, contents) => {
+                if from.is_some() { summary.converted += 1 } else { summary.generated += 1 }
+                contents
+            }
+            Output::Page(_) => {
+                summary.converted += 1;
+                let text = try!(read_page(&source));
+                try!(convert_page(&text, options).map_err(|e| {
+                    format!("Error parsing {}:\n{}", source.display(), e)
+                })).into_bytes()
+            }
+            Output::Copy(_) => {
+                summary.copied += 1;
+                match bytes {
+                    Some(bytes) => bytes,
+                    None => try!(read_bytes(&source)),
+                }
+            }
+        };
+        let parent = output.parent().unwrap();
+        try!(fs::create_dir_all(parent).map_err(|e| io_error(parent, e)));
+        try!(fs::File::create(&output).and_then(|mut f| f.write_all(&contents))
+             .map_err(|e| io_error(&output, e)));
+    }
+
+    for path in previous.keys() {
+        if written.contains(path) {
+            continue;
+        }
+        let orphan = out.join(path);
+        if orphan.is_file() {
+            try!(fs::remove_file(&orphan).map_err(|e| io_error(&orphan, e)));
+            summary.removed += 1;
+            // Tidy up directories left empty, stopping at the first that isn't.
+            let mut dir = orphan.parent();
+            while let Some(d) = dir {
+                if d == out || fs::remove_dir(d).is_err() {
+                    break;
+                }
+                dir = d.parent();
+            }
+        }
+    }
+    manifest.sort();
+    let manifest_file = out.join(MANIFEST);
+    try!(fs::File::create(&manifest_file)
+         .and_then(|mut f| f.write_all((manifest.join("\n") + "\n").as_bytes()))
+         .map_err(|e| io_error(&manifest_file, e)));
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{build, hash, read_page, BuildOptions, BuildSummary, Freshness};
+    use testing::{read, temp_dir, write, write_bytes};
+
+    fn summary(converted: usize, copied: usize, unchanged: usize, removed: usize)
+               -> BuildSummary {
+        BuildSummary {
+            converted: converted,
+            copied: copied,
+            generated: 0,
+            unchanged: unchanged,
+            removed: removed,
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let dir = temp_dir("build-test");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write(&src.join("index.md"), "# Home\n\nSee [the guide](guide/setup.md#install).\n");
+        write(&src.join("guide/setup.md"), "# Setup\n\n## Install\n");
+        write(&src.join("guide/img/logo.png"), "PNG");
+        write(&src.join(".git/config"), "");
+        write(&out.join("keep.txt"), "not ours");
+
+        let mut options = BuildOptions::default();
+        options.freshness = Freshness::Hash;
+        assert_eq!(build(&src, &out, &options), Ok(summary(2, 1, 0, 0)));
+        let index = read(&out.join("index.html"));
+        assert!(index.contains("<a href=\"guide/setup.html#install\">the guide</a>"));
+        assert!(read(&out.join("guide/setup.html")).contains("<h2 id=\"install\">Install</h2>"));
+        assert_eq!(read(&out.join("guide/img/logo.png")), "PNG");
+        assert!(!out.join(".git").exists());
+
+        assert_eq!(build(&src, &out, &options), Ok(summary(0, 0, 3, 0)));
+        write(&src.join("index.md"), "# Home again\n");
+        fs::remove_dir_all(src.join("guide")).unwrap();
+        assert_eq!(build(&src, &out, &options), Ok(summary(1, 0, 0, 2)));
+        assert!(read(&out.join("index.html")).contains("Home again"));
+        assert!(!out.join("guide").exists());
+        assert!(out.join("keep.txt").exists());
+
+        options.force = true;
+        assert_eq!(build(&src, &out, &options), Ok(summary(1, 0, 0, 0)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_inside_source() {
+        let dir = temp_dir("build-nested-test");
+        write(&dir.join("a.md"), "A\n");
+        let options = BuildOptions::default();
+        assert_eq!(build(&dir, &dir.join("site"), &options), Ok(summary(1, 0, 0, 0)));
+        assert_eq!(build(&dir, &dir.join("site"), &options), Ok(summary(0, 0, 1, 0)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mtime_builds_notice_new_settings() {
+        let dir = temp_dir("build-settings-test");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write(&src.join("a.md"), "A\nB\n");
+        let mut options = BuildOptions::default();
+        assert_eq!(build(&src, &out, &options), Ok(summary(1, 0, 0, 0)));
+        assert_eq!(build(&src, &out, &options), Ok(summary(0, 0, 1, 0)));
+        options.html.sourcepos = true;
+        assert_eq!(build(&src, &out, &options), Ok(summary(1, 0, 0, 0)));
+        assert!(read(&out.join("a.html")).contains("data-sourcepos"));
+        assert_eq!(build(&src, &out, &options), Ok(summary(0, 0, 1, 0)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_new_settings_only_rebuild_pages() {
+        let dir = temp_dir("build-copy-test");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write(&src.join("a.md"), "A\n");
+        write(&src.join("b.txt"), "B\n");
+        let mut options = BuildOptions::default();
+        for &freshness in [Freshness::Mtime, Freshness::Hash].iter() {
+            options.freshness = freshness;
+            options.html.sourcepos = !options.html.sourcepos;
+            build(&src, &out, &options).unwrap();
+            options.html.sourcepos = !options.html.sourcepos;
+            assert_eq!(build(&src, &out, &options), Ok(summary(1, 0, 1, 0)));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_page() {
+        let dir = temp_dir("build-read-test");
+        let utf16 = dir.join("utf16.md");
+        write_bytes(&utf16, b"\xff\xfeA\x00\n\x00");
+        assert_eq!(read_page(&utf16), Ok("A\n".to_string()));
+        let latin1 = dir.join("latin1.md");
+        write_bytes(&latin1, b"caf\xe9\n");
+        let error = read_page(&latin1).unwrap_err();
+        assert!(error.contains("must be UTF-8"), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hash_is_stable() {
+        assert_eq!(hash(b"abc", ""), "313d2062bea5b40a");
+        assert!(hash(b"c", "ab") != hash(b"bc", "a"));
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `rustdown build SRC OUT`: convert the markdown files under `SRC` into
+//! standalone HTML pages under `OUT`, as described in `build`, or with
+//! `--site`, into a static site, as described in `site`.
+
+use std::path::Path;
+
+use getopts::{Matches, Options};
+
+use url::Url;
+
+use build::{build, BuildOptions};
+use mdfile;
+use site::{SiteOptions, SITE_TEMPLATE};
+use template::Template;
+
+pub fn run(args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("", "compare", "how to tell whether a file has changed since the last \
+                                build: mtime (default) or hash", "METHOD");
+    opts.optflag("", "force", "rebuild every file, even ones that look up to date");
+    opts.optopt("", "template", "render each page into this template file", "FILE");
+    opts.optmulti("", "css", "link to this stylesheet from each page", "URL");
+    opts.optflag("", "site", "build a static site, with navigation, index pages and \
+                              links to the previous and next pages");
+    opts.optopt("", "base-url", "the URL the site will be served from, for its sitemap \
+                                 (implies --site)", "URL");
+    opts.optopt("", "stop-words", "leave the words in this file, separated by whitespace, \
+                                   out of the search index instead of common English \
+                                   ones (implies --site)", "FILE");
+    let matches = match super::parse_args("build", "SRC OUT", &mut opts, args) {
+        Ok(matches) => matches,
+        Err(status) => return status,
+    };
+    if matches.free.len() != 2 {
+        println!("Expected a source and an output directory");
+        return 2;
+    }
+    let result = build_options(&matches).and_then(|options| {
+        build(Path::new(&matches.free[0]), Path::new(&matches.free[1]), &options)
+    });
+    match result {
+        Ok(summary) => {
+            println!("{} converted, {} copied, {} generated, {} unchanged, {} removed",
+                     summary.converted, summary.copied, summary.generated, summary.unchanged,
+                     summary.removed);
+            0
+        }
+        Err(e) => {
+            println!("{}", e);
+            2
+        }
+    }
+}
+
+fn build_options(matches: &Matches) -> Result<BuildOptions, String> {
+    let mut options = BuildOptions::default();
+    if let Some(freshness) = matches.opt_str("compare") {
+        options.freshness = try!(freshness.parse());
+    }
+    options.force = matches.opt_present("force");
+    if let Some(path) = matches.opt_str("template") {
+        let source = try!(mdfile::read_text_file(Path::new(&path)).map_err(|e| {
+            format!("Error reading {}:\n{}", path, e)
+        }));
+        options.standalone.template = Template::new(source);
+    }
+    options.standalone.stylesheets = matches.opt_strs("css");
+    if matches.opt_present("site") || matches.opt_present("base-url") ||
+       matches.opt_present("stop-words") {
+        let mut site = SiteOptions::default();
+        if let Some(url) = matches.opt_str("base-url") {
+            // A base URL without a trailing slash would drop its last
+            // directory when pages are resolved against it.
+            let url = if url.ends_with('/') { url } else { url + "/" };
+            site.base_url = Some(try!(Url::parse(&url).map_err(|e| {
+                format!("invalid base URL `{}`: {}", url, e)
+            })));
+        }
+        if let Some(path) = matches.opt_str("stop-words") {
+            let words = try!(mdfile::read_text_file(Path::new(&path)).map_err(|e| {
+                format!("Error reading {}:\n{}", path, e)
+            }));
+            site.stop_words = words.split_whitespace().map(|w| w.to_lowercase()).collect();
+        }
+        if !matches.opt_present("template") {
+            options.standalone.template = Template::new(SITE_TEMPLATE.to_string());
+        }
+        options.site = Some(site);
+    }
+    Ok(options)
+}
@@ -0,0 +1,78 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `rustdown check-links`: check that the local links and images in
+//! markdown files point at files and headings that exist. External links
+//! are listed but never fetched. Fails if any links are broken.
+
+use std::path::Path;
+
+use getopts::Options;
+
+use convert;
+use lines;
+use links::LinkChecker;
+use mdfile;
+use preprocessor;
+use types::VisitorMut;
+use types::visit::MapSpans;
+
+pub fn run(args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("", "root", "resolve links starting with / from this directory \
+                             (default: the current directory)", "DIR");
+    let matches = match super::parse_args("check-links", "FILE...", &mut opts, args) {
+        Ok(matches) => matches,
+        Err(status) => return status,
+    };
+    if matches.free.is_empty() {
+        println!("No markdown files given");
+        return 2;
+    }
+    let root = matches.opt_str("root").unwrap_or(".".to_string());
+    let mut checker = LinkChecker::new(Path::new(&root));
+    let mut broken = 0;
+    let mut external = vec![];
+    for path in matches.free.iter() {
+        let source = match mdfile::read_text_file(Path::new(path)) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("Error reading {}:\n{}", path, e);
+                return 2;
+            }
+        };
+        let (lines, mut map) = lines::split_lines_mapped(&source);
+        let lines = preprocessor::replace_tabs_mapped(&lines, &mut map);
+        let mut document = match convert::parse_document(&lines) {
+            Ok(document) => document,
+            Err(e) => {
+                println!("Error parsing {}:\n{}", path, e);
+                return 2;
+            }
+        };
+        MapSpans(map).visit_document_mut(&mut document);
+        let report = checker.check(Path::new(path), &document);
+        for &(ref link, ref problem) in report.broken.iter() {
+            println!("{}:{}:{}: broken link `{}`: {}", path, link.span.start.line,
+                     link.span.start.column, link.url, problem);
+        }
+        broken += report.broken.len();
+        for link in report.external.iter() {
+            external.push(format!("{}:{}:{}: {}", path, link.span.start.line,
+                                  link.span.start.column, link.url));
+        }
+    }
+    if !external.is_empty() {
+        println!("External links (not checked):");
+        for link in external.iter() {
+            println!("    {}", link);
+        }
+    }
+    if broken > 0 { 1 } else { 0 }
+}
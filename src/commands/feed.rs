@@ -0,0 +1,132 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `rustdown feed`: write an Atom or RSS feed of the dated markdown
+//! files given, as described in `feed`. Each file's page is taken to be
+//! at its path from the root directory, with an `.html` extension, under
+//! the base URL.
+
+use std::path::Path;
+
+use getopts::{Matches, Options};
+use url::{Url, UrlParser};
+
+use build;
+use feed;
+use feed::{FeedEntry, FeedOptions};
+
+pub fn run(args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("", "format", "the kind of feed: atom (default) or rss", "FORMAT");
+    opts.optopt("", "base-url", "the URL of the site the pages are published on", "URL");
+    opts.optopt("", "title", "the title of the feed", "TITLE");
+    opts.optopt("", "author", "the author of entries that don't name one", "NAME");
+    opts.optopt("", "root", "the directory the base URL serves \
+                             (default: the current directory)", "DIR");
+    let matches = match super::parse_args("feed", "FILE...", &mut opts, args) {
+        Ok(matches) => matches,
+        Err(status) => return status,
+    };
+    match write_feed(&matches) {
+        Ok(xml) => {
+            print!("{}", xml);
+            0
+        }
+        Err(e) => {
+            println!("{}", e);
+            2
+        }
+    }
+}
+
+fn write_feed(matches: &Matches) -> Result<String, String> {
+    let base = match matches.opt_str("base-url") {
+        Some(url) => {
+            let url = if url.ends_with('/') { url } else { url + "/" };
+            try!(Url::parse(&url).map_err(|e| format!("invalid base URL `{}`: {}", url, e)))
+        }
+        None => return Err("A feed needs a --base-url".to_string()),
+    };
+    let title = try!(matches.opt_str("title").ok_or("A feed needs a --title".to_string()));
+    let root = matches.opt_str("root").unwrap_or(".".to_string());
+    let mut entries = vec![];
+    for path in matches.free.iter() {
+        let path = Path::new(path);
+        let text = try!(build::read_page(path));
+        let document = try!(build::parse_page(&text).map_err(|e| {
+            format!("Error parsing {}:\n{}", path.display(), e)
+        }));
+        let url = try!(page_url(&base, Path::new(&root), path));
+        let stem = path.file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned());
+        let entry = try!(FeedEntry::new(&document, &url, &stem).map_err(|e| {
+            format!("Error with {}:\n{}", path.display(), e)
+        }));
+        entries.extend(entry);
+    }
+    feed::sort_entries(&mut entries);
+    let options = FeedOptions {
+        title: title,
+        url: base.clone(),
+        author: matches.opt_str("author"),
+    };
+    match matches.opt_str("format").as_ref().map(|f| &f[..]) {
+        None | Some("atom") => {
+            let self_url = UrlParser::new().base_url(&base).parse("atom.xml")
+                .map(|url| url.serialize()).unwrap_or(base.serialize());
+            Ok(feed::atom(&options, &self_url, &entries))
+        }
+        Some("rss") => Ok(feed::rss(&options, &entries)),
+        Some(format) => Err(format!("unknown feed format `{}` (expected atom or rss)", format)),
+    }
+}
+
+/// The URL of the page converted from the file at `path`, which has to
+/// be somewhere under `root`, the directory `base` serves.
+fn page_url(base: &Url, root: &Path, path: &Path) -> Result<Url, String> {
+    let root = try!(root.canonicalize().map_err(|e| {
+        format!("Error with {}:\n{}", root.display(), e)
+    }));
+    let relative = try!(path.canonicalize().map_err(|e| {
+        format!("Error with {}:\n{}", path.display(), e)
+    }).and_then(|full| match full.strip_prefix(&root) {
+        Ok(relative) => Ok(relative.to_path_buf()),
+        Err(_) => Err(format!("Error with {}:\nit isn't under {}; give the directory the \
+                               base URL serves with --root", path.display(), root.display())),
+    }));
+    let page = build::manifest_path(&build::output_path(&relative));
+    UrlParser::new().base_url(base).parse(&page).map_err(|e| {
+        format!("invalid URL for {}: {}", page, e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use url::Url;
+
+    use super::page_url;
+    use testing::{temp_dir, write};
+
+    #[test]
+    fn test_page_url() {
+        let dir = temp_dir("feed-test");
+        write(&dir.join("site/blog/a.md"), "# A\n");
+        write(&dir.join("other/b.md"), "# B\n");
+        let base = Url::parse("https://e.com/blog/").unwrap();
+        let (root, blog) = (dir.join("site"), dir.join("site/blog"));
+        assert_eq!(page_url(&base, &root, &dir.join("site/blog/a.md")).unwrap().serialize(),
+                   "https://e.com/blog/blog/a.html");
+        assert_eq!(page_url(&base, &blog, &dir.join("site/blog/../blog/a.md")).unwrap()
+                   .serialize(), "https://e.com/blog/a.html");
+        let err = page_url(&base, &blog, &dir.join("other/b.md")).unwrap_err();
+        assert!(err.contains("isn't under"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
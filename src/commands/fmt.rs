@@ -0,0 +1,241 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `rustdown fmt`: rewrite markdown files in their normalised form, as
+//! written by `markdown`, in the style set in `.rustdown.toml`.
+//!
+//! With `--check`, files are left alone, and the command lists the ones
+//! that aren't formatted and fails if there are any.
+//!
+//! Files that can't be formatted without changing what they say are
+//! refused rather than rewritten: those with a list item, heading or rule
+//! in the middle of a paragraph, which the parser reads as text, and any
+//! whose formatted form wouldn't render the same.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use getopts::Options;
+
+use config::Config;
+use convert;
+use html::{HtmlOptions, SoftBreakStyle};
+use lines;
+use markdown::ToMarkdown;
+use mdfile;
+use parser::blocks;
+use preprocessor;
+use types::{Document, Paragraph, Visitor};
+
+pub fn run(args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optflag("", "check", "list the files that aren't formatted, without changing them");
+    opts.optopt("", "config", "use these settings instead of the nearest .rustdown.toml",
+                "FILE");
+    let matches = match super::parse_args("fmt", "FILE...", &mut opts, args) {
+        Ok(matches) => matches,
+        Err(status) => return status,
+    };
+    if matches.free.is_empty() {
+        println!("No markdown files given");
+        return 2;
+    }
+    let config = match matches.opt_str("config") {
+        Some(path) => match Config::load(Path::new(&path)) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                println!("{}", e);
+                return 2;
+            }
+        },
+        None => None,
+    };
+    let check = matches.opt_present("check");
+    // 2 if any file couldn't be formatted, else 1 if any needed it.
+    let mut status = 0;
+    for path in matches.free.iter() {
+        let path = Path::new(path);
+        let result = match config {
+            Some(ref config) => format_file(path, config, check),
+            None => Config::for_file(path).and_then(|config| format_file(path, &config, check)),
+        };
+        match result {
+            Ok(true) => {}
+            Ok(false) if !check => {}
+            Ok(false) => {
+                println!("{}", path.display());
+                if status == 0 {
+                    status = 1;
+                }
+            }
+            Err(e) => {
+                // Carry on with the other files, as `lint` does.
+                println!("{}", e);
+                status = 2;
+            }
+        }
+    }
+    status
+}
+
+/// Format one file, returning whether it was already formatted. Unless
+/// `check` is set, a file that wasn't is rewritten.
+fn format_file(path: &Path, config: &Config, check: bool) -> Result<bool, String> {
+    let source = try!(mdfile::read_text_file(path).map_err(|e| {
+        format!("Error reading {}:\n{}", path.display(), e)
+    }));
+    let formatted = try!(format(&source, config).map_err(|e| {
+        format!("Can't format {}:\n{}", path.display(), e)
+    }));
+    if formatted == source {
+        return Ok(true);
+    }
+    if !check {
+        try!(File::create(path).and_then(|mut f| f.write_all(formatted.as_bytes())).map_err(|e| {
+            format!("Error writing {}:\n{}", path.display(), e)
+        }));
+    }
+    Ok(false)
+}
+
+/// The formatted form of the markdown in `source`.
+pub fn format(source: &str, config: &Config) -> Result<String, String> {
+    let lines = preprocessor::replace_tabs(&lines::split_lines(source));
+    let document = try!(convert::parse_document(&lines).map_err(|e| e.to_string()));
+    let mut unmodelled = Unmodelled { lines: &lines, found: None };
+    unmodelled.visit_document(&document);
+    if let Some((line, what)) = unmodelled.found {
+        return Err(format!("line {} starts {} in the middle of a paragraph; \
+                            put a blank line before it", line, what));
+    }
+    let formatted = document.to_markdown_with(&config.fmt);
+    let lines = preprocessor::replace_tabs(&lines::split_lines(&formatted));
+    let reparsed = try!(convert::parse_document(&lines).map_err(|e| e.to_string()));
+    if rendered(&reparsed) != rendered(&document) {
+        return Err("formatting would change how the document renders".to_string());
+    }
+    Ok(formatted)
+}
+
+/// Finds the first paragraph line that CommonMark would start a block
+/// with, but which this parser takes as more of the paragraph's text.
+struct Unmodelled<'a> {
+    lines: &'a [String],
+    found: Option<(usize, &'static str)>,
+}
+
+impl<'a> Visitor for Unmodelled<'a> {
+    fn visit_paragraph(&mut self, paragraph: &Paragraph) {
+        let span = paragraph.span();
+        if self.found.is_some() || span.start.line == 0 {
+            return;
+        }
+        for number in span.start.line..span.end.line + 1 {
+            let mut line = &self.lines[number - 1][..];
+            while let Some(marker) = blocks::quote_marker(line) {
+                line = &line[marker..];
+            }
+            let first = number == span.start.line;
+            let what = match blocks::list_item(line) {
+                // Only a non-empty item, and if it's numbered, one
+                // numbered 1, can interrupt a paragraph.
+                Some(ref item) if first || !item.text.is_empty() &&
+                                           item.number.map_or(true, |n| n == 1) => {
+                    Some("a list item")
+                }
+                _ if is_atx_heading(line) => Some("a heading"),
+                _ if !first && blocks::is_rule(line) => Some("a rule"),
+                _ => None,
+            };
+            if let Some(what) = what {
+                self.found = Some((number, what));
+                return;
+            }
+        }
+    }
+}
+
+/// Whether `line` is a heading like `## Title`: one to six `#`s, then a
+/// space or the end of the line.
+fn is_atx_heading(line: &str) -> bool {
+    let rest = line.trim_left_matches(' ');
+    let text = rest.trim_left_matches('#');
+    let hashes = rest.len() - text.len();
+    line.len() - rest.len() < 4 && hashes >= 1 && hashes <= 6 &&
+        (text.is_empty() || text.starts_with(' '))
+}
+
+/// The HTML for `document`'s body, with runs of whitespace collapsed, so
+/// documents that only differ in how their paragraphs are wrapped render
+/// the same.
+fn rendered(document: &Document) -> String {
+    let mut options = HtmlOptions::default();
+    options.soft_break = SoftBreakStyle::Space;
+    document.body_html(&options).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{format, run};
+    use config::Config;
+    use testing::{read, temp_dir, write};
+
+    #[test]
+    fn test_format() {
+        let config = Config::parse("[fmt]\nbullet = \"*\"").unwrap();
+        let formatted = format("Title\n===\n\n- a\n- b\n", &config).unwrap();
+        assert_eq!(formatted, "# Title\n\n* a\n* b\n");
+        assert_eq!(format(&formatted, &config).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_run_goes_on_after_a_failure() {
+        let dir = temp_dir("fmt-test");
+        write(&dir.join("b.md"), "B\n=\n");
+        let args: Vec<String> = vec![dir.join("a.md"), dir.join("b.md")].iter().map(|path| {
+            path.to_string_lossy().into_owned()
+        }).collect();
+        assert_eq!(run(&args), 2);
+        assert_eq!(read(&dir.join("b.md")), "# B\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_keeps_code_and_html() {
+        let config = Config::parse("[fmt]\nwrap = 20").unwrap();
+        let source = "Some text that is long enough to wrap.\n\n\
+                      ~~~ rust\nfn main() { println!(\"a line longer than twenty\"); }\n~~~\n\n\
+                      \x20   indented   code\n\n\
+                      <div>\n  kept   as   is\n</div>\n\n\
+                      a|b\n-|:-:\nlonger|x\n";
+        let formatted = format(source, &config).unwrap();
+        assert_eq!(formatted,
+                   "Some text that is\nlong enough to wrap.\n\n\
+                    ```rust\nfn main() { println!(\"a line longer than twenty\"); }\n```\n\n\
+                    ```\nindented   code\n```\n\n\
+                    <div>\n  kept   as   is\n</div>\n\n\
+                    | a      |  b  |\n| ------ | :-: |\n| longer |  x  |\n");
+        assert_eq!(format(&formatted, &config).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_unmodelled_blocks_are_refused() {
+        let config = Config::default();
+        assert_eq!(format("Intro:\n- a\n- b\n", &config).unwrap_err(),
+                   "line 2 starts a list item in the middle of a paragraph; \
+                    put a blank line before it");
+        assert!(format("a\n## b\n", &config).unwrap_err().contains("line 2 starts a heading"));
+        assert!(format("1. a\n- b\n", &config).unwrap_err().contains("line 1"));
+        assert!(format("> a\n> * b\n", &config).is_err());
+        assert!(format("The year\n1986. was good\n#5 too\n", &config).is_ok());
+    }
+}
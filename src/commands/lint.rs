@@ -0,0 +1,81 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `rustdown lint`: check markdown files with the rules in `lint`, as
+//! configured in `.rustdown.toml`. Fails if any problems are found.
+
+use std::path::Path;
+
+use getopts::Options;
+
+use config::Config;
+use lines;
+use lint::Linter;
+use lint::report::{FileReport, ReportFormat, write_report};
+use mdfile;
+use preprocessor;
+
+pub fn run(args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("", "format", "how to report problems: human (default), json or sarif",
+                "FORMAT");
+    opts.optopt("", "config", "use these settings instead of the nearest .rustdown.toml",
+                "FILE");
+    let matches = match super::parse_args("lint", "FILE...", &mut opts, args) {
+        Ok(matches) => matches,
+        Err(status) => return status,
+    };
+    match lint_files(&matches) {
+        Ok(status) => status,
+        Err(e) => {
+            println!("{}", e);
+            2
+        }
+    }
+}
+
+fn lint_files(matches: &::getopts::Matches) -> Result<i32, String> {
+    if matches.free.is_empty() {
+        return Err("No markdown files given".to_string());
+    }
+    let format = match matches.opt_str("format") {
+        Some(format) => try!(format.parse()),
+        None => ReportFormat::Human,
+    };
+    let config = match matches.opt_str("config") {
+        Some(path) => Some(try!(Config::load(Path::new(&path)))),
+        None => None,
+    };
+    let mut reports = vec![];
+    for path in matches.free.iter() {
+        let config = match config {
+            Some(ref config) => config.clone(),
+            None => try!(Config::for_file(Path::new(path))),
+        };
+        let mut linter = Linter::default();
+        try!(linter.configure(&config.lint));
+        let source = try!(mdfile::read_text_file(Path::new(path)).map_err(|e| {
+            format!("Error reading {}:\n{}", path, e)
+        }));
+        let (lines, mut map) = lines::split_lines_mapped(&source);
+        let lines = preprocessor::replace_tabs_mapped(&lines, &mut map);
+        let mut diagnostics = try!(linter.lint(&lines).map_err(|e| {
+            format!("Error parsing {}:\n{}", path, e)
+        }));
+        for diagnostic in diagnostics.iter_mut() {
+            diagnostic.span = map.span(diagnostic.span);
+        }
+        reports.push(FileReport {
+            path: path.clone(),
+            diagnostics: diagnostics,
+        });
+    }
+    print!("{}", write_report(format, &reports, Linter::default().rules()));
+    Ok(if reports.iter().any(|r| !r.diagnostics.is_empty()) { 1 } else { 0 })
+}
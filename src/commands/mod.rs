@@ -0,0 +1,68 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Subcommands, run as `rustdown COMMAND ARGS...`.
+
+use getopts::Options;
+
+pub mod build;
+pub mod check_links;
+pub mod feed;
+pub mod fmt;
+pub mod lint;
+
+/// The name and description of each subcommand.
+pub const COMMANDS: &'static [(&'static str, &'static str)] = &[
+    ("build", "convert a directory of markdown into a directory of HTML"),
+    ("check-links", "check that local links and anchors go somewhere"),
+    ("feed", "write an Atom or RSS feed of dated markdown files"),
+    ("fmt", "rewrite markdown files in a consistent style"),
+    ("lint", "check markdown files for common mistakes"),
+];
+
+/// Run the subcommand named by the first argument, if there is one,
+/// returning the exit status.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = match args.first() {
+        Some(command) => command,
+        None => return None,
+    };
+    let args = &args[1..];
+    match &command[..] {
+        "build" => Some(build::run(args)),
+        "check-links" => Some(check_links::run(args)),
+        "feed" => Some(feed::run(args)),
+        "fmt" => Some(fmt::run(args)),
+        "lint" => Some(lint::run(args)),
+        _ => None,
+    }
+}
+
+/// Parse a subcommand's arguments, printing its usage for `--help` or on
+/// errors. `operands` describes the arguments after the options. `Err`
+/// holds the exit status to stop with.
+fn parse_args(command: &str, operands: &str, opts: &mut Options, args: &[String])
+              -> Result<::getopts::Matches, i32> {
+    opts.optflag("h", "help", "print this help menu");
+    let usage = |opts: &Options| {
+        let brief = format!("Usage: rustdown {} [options] {}", command, operands);
+        opts.usage(&brief)
+    };
+    match opts.parse(args) {
+        Ok(ref m) if m.opt_present("h") => {
+            println!("{}", usage(opts));
+            Err(0)
+        }
+        Ok(m) => Ok(m),
+        Err(f) => {
+            println!("{}\n\n{}", f, usage(opts));
+            Err(2)
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Project settings, read from a `.rustdown.toml` file.
+//!
+//! The file that applies to a markdown file is the nearest one in its
+//! directory or the directories above it. Each command has its own table:
+//!
+//! ```toml
+//! [fmt]
+//! heading-style = "atx"      # or "setext"
+//! bullet = "-"               # or "*" or "+"
+//! numbering = "consecutive"  # or "same"
+//! wrap = 80                  # columns; leave out to keep line breaks
+//! align-tables = true        # pad table cells so the columns line up
+//! ```
+//!
+//! The `[lint]` table configures the linter's rules, as described in
+//! `lint`.
+
+use std::path::{Path, PathBuf};
+
+use toml;
+
+use markdown::MarkdownOptions;
+use mdfile;
+
+pub const FILE_NAME: &'static str = ".rustdown.toml";
+
+/// The settings from a `.rustdown.toml` file.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub fmt: MarkdownOptions,
+    /// The `[lint]` table, which is checked against the rules when a
+    /// `Linter` is configured with it.
+    pub lint: toml::Table,
+}
+
+impl Config {
+    pub fn parse(source: &str) -> Result<Config, String> {
+        let mut parser = toml::Parser::new(source);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None => {
+                return Err(parser.errors.iter().map(|e| {
+                    let (line, col) = parser.to_linecol(e.lo);
+                    format!("{} at line {} column {}", e.desc, line + 1, col + 1)
+                }).collect::<Vec<_>>().join("; "));
+            }
+        };
+        let mut config = Config::default();
+        for (section, value) in table.iter() {
+            let value = try!(value.as_table().ok_or_else(|| {
+                format!("`{}` should be a table", section)
+            }));
+            match &section[..] {
+                "fmt" => try!(parse_fmt(value, &mut config.fmt)),
+                "lint" => config.lint = value.clone(),
+                _ => return Err(format!("unknown section `{}`", section)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let source = try!(mdfile::read_text_file(path).map_err(|e| {
+            format!("Error reading {}:\n{}", path.display(), e)
+        }));
+        Config::parse(&source).map_err(|e| format!("Error in {}:\n{}", path.display(), e))
+    }
+
+    /// The settings for the markdown file at `path`, or the defaults if
+    /// no `.rustdown.toml` applies to it.
+    pub fn for_file(path: &Path) -> Result<Config, String> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        match find(dir) {
+            Some(config) => Config::load(&config),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+/// The nearest `.rustdown.toml` in `dir` or above it.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let dir = dir.canonicalize().unwrap_or(dir.to_path_buf());
+    let mut dir = Some(dir.as_path());
+    while let Some(d) = dir {
+        let file = d.join(FILE_NAME);
+        if file.is_file() {
+            return Some(file);
+        }
+        dir = d.parent();
+    }
+    None
+}
+
+fn parse_fmt(table: &toml::Table, options: &mut MarkdownOptions) -> Result<(), String> {
+    for (key, value) in table.iter() {
+        match &key[..] {
+            "heading-style" => options.heading_style = try!(try!(string(key, value)).parse()),
+            "bullet" => {
+                options.bullet = match try!(string(key, value)) {
+                    "-" => '-',
+                    "*" => '*',
+                    "+" => '+',
+                    s => return Err(format!("unknown bullet `{}` (expected -, * or +)", s)),
+                }
+            }
+            "numbering" => options.numbering = try!(try!(string(key, value)).parse()),
+            "wrap" => {
+                options.wrap = match value.as_integer() {
+                    Some(0) => None,
+                    Some(n) if n > 0 => Some(n as usize),
+                    _ => return Err(format!("`fmt.{}` should be a number of columns", key)),
+                }
+            }
+            "align-tables" => {
+                options.align_tables = try!(value.as_bool().ok_or_else(|| {
+                    format!("`fmt.{}` should be true or false", key)
+                }))
+            }
+            _ => return Err(format!("unknown setting `fmt.{}`", key)),
+        }
+    }
+    Ok(())
+}
+
+fn string<'a>(key: &str, value: &'a toml::Value) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| format!("`{}` should be a string", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use markdown::{HeadingStyle, Numbering};
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse("[fmt]\nheading-style = \"setext\"\nbullet = \"*\"\n\
+                                    numbering = \"same\"\nwrap = 72\nalign-tables = false\n")
+                     .unwrap();
+        assert_eq!(config.fmt.heading_style, HeadingStyle::Setext);
+        assert_eq!(config.fmt.bullet, '*');
+        assert_eq!(config.fmt.numbering, Numbering::Same);
+        assert_eq!(config.fmt.wrap, Some(72));
+        assert!(!config.fmt.align_tables);
+        assert_eq!(Config::parse("").unwrap().fmt.wrap, None);
+        assert!(Config::parse("").unwrap().fmt.align_tables);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Config::parse("[fmt]\nbullet = \"x\"").is_err());
+        assert!(Config::parse("[fmt]\nwidth = 80").is_err());
+        assert!(Config::parse("[fmt]\nalign-tables = 1").is_err());
+        assert!(Config::parse("[fnt]").is_err());
+        assert!(Config::parse("fmt = 1").is_err());
+        assert!(Config::parse("[fmt").is_err());
+    }
+}
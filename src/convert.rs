@@ -7,20 +7,74 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use types::MarkdownStructure;
-use types::MarkdownStructure::{MDH, MDP, MDB};
-use types::parse_heading;
-use types::parse_paragraph;
-use types::parse_bulletlist;
-
-pub fn parse_block(block: &Vec<String>) -> MarkdownStructure {
-    match parse_heading(block) {
-        Some(heading) => return MDH(heading),
-        None => {}
+use metadata;
+use metadata::FrontMatterError;
+use parser::Parser;
+use types::Document;
+use types::VisitorMut;
+use types::build::build_blocks;
+
+/// Parse a whole document. Front matter, if there is any, becomes the
+/// document's metadata rather than part of its body, and its lines are
+/// kept so it can be written back as it was.
+pub fn parse_document(lines: &[String]) -> Result<Document, FrontMatterError> {
+    let (metadata, skip) = try!(metadata::parse_front_matter(lines));
+    let blocks = build_blocks(Parser::starting_at(lines, skip).spanned());
+    let mut document = Document::with_metadata(blocks, metadata);
+    if skip > 0 {
+        document.set_front_matter(lines[..skip].to_vec());
     }
-    match parse_bulletlist(block) {
-        Some(bullets) => return MDB(bullets),
-        None => {}
+    Ok(document)
+}
+
+/// Parse a whole document, then run each of `transforms` over it in
+/// turn, so they can rewrite it before it's rendered.
+pub fn parse_document_with(lines: &[String], transforms: &mut [Box<VisitorMut>])
+                           -> Result<Document, FrontMatterError> {
+    let mut document = try!(parse_document(lines));
+    transform(&mut document, transforms);
+    Ok(document)
+}
+
+/// Run each of `transforms` over `document` in turn.
+pub fn transform(document: &mut Document, transforms: &mut [Box<VisitorMut>]) {
+    for transform in transforms.iter_mut() {
+        transform.visit_document_mut(document);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_document, parse_document_with};
+    use types::VisitorMut;
+    use types::visit::ShiftHeadings;
+    use html::HtmlOptions;
+    use metadata::MetaValue;
+    use testing::lines;
+
+    #[test]
+    fn test_front_matter_is_not_body() {
+        let document = parse_document(&lines(&["---", "author: Ann", "---", "",
+                                               "Text."])).unwrap();
+        assert_eq!(document.metadata()["author"], MetaValue::String("Ann".to_string()));
+        assert_eq!(document.body_html(&HtmlOptions::default()), "<p>Text.</p>");
+        assert!(parse_document(&lines(&["+++", "= 1", "+++"])).is_err());
+    }
+
+    #[test]
+    fn test_thematic_breaks_are_not_front_matter() {
+        let document = parse_document(&lines(&["---", "", "Intro", "", "---", "",
+                                               "body"])).unwrap();
+        assert!(document.metadata().is_empty());
+        assert_eq!(document.body_html(&HtmlOptions::default()),
+                   "<hr />\n<p>Intro</p>\n<hr />\n<p>body</p>");
+    }
+
+    #[test]
+    fn test_transforms() {
+        let mut transforms: Vec<Box<VisitorMut>> = vec![Box::new(ShiftHeadings(1)),
+                                                        Box::new(ShiftHeadings(-3))];
+        let document = parse_document_with(&lines(&["### A"]), &mut transforms).unwrap();
+        assert_eq!(document.body_html(&HtmlOptions::default()), "<h1>A</h1>");
     }
-    MDP(parse_paragraph(block))
 }
@@ -0,0 +1,348 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Decoding of input files into UTF-8 text.
+
+use std::char;
+use std::error::Error;
+use std::fmt;
+use std::str;
+use std::str::FromStr;
+
+use span::OffsetMap;
+
+/// A character encoding rustdown can read input in.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Ascii,
+    /// ISO-8859-1.
+    Latin1,
+    /// ISO-8859-15, which is Latin-1 with the euro sign and a few
+    /// other letters swapped in.
+    Latin9,
+    Windows1252,
+}
+
+impl Encoding {
+    /// The canonical name of this encoding.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Ascii => "ascii",
+            Encoding::Latin1 => "iso-8859-1",
+            Encoding::Latin9 => "iso-8859-15",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Encoding, String> {
+        match &s.to_lowercase()[..] {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            // Without a byte order mark, UTF-16 is assumed to be
+            // little-endian.
+            "utf-16" | "utf16" | "utf-16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" => Ok(Encoding::Utf16Be),
+            "ascii" | "us-ascii" => Ok(Encoding::Ascii),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "latin9" | "latin-9" | "iso-8859-15" => Ok(Encoding::Latin9),
+            "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+            _ => Err(format!("unknown encoding `{}`", s)),
+        }
+    }
+}
+
+/// What to do when the input isn't valid in its encoding.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum DecoderTrap {
+    /// Replace each invalid sequence with U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+    /// Stop decoding and report a `DecodeError`.
+    Strict,
+}
+
+/// An invalid byte sequence found while decoding.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct DecodeError {
+    /// The encoding the input was being decoded as.
+    pub encoding: Encoding,
+    /// The byte offset of the invalid sequence in the input.
+    pub offset: usize,
+    /// The invalid sequence itself.
+    pub bytes: Vec<u8>,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "invalid {} sequence at byte {}:", self.encoding.name(), self.offset));
+        for b in self.bytes.iter() {
+            try!(write!(fmt, " {:#04x}", b));
+        }
+        Ok(())
+    }
+}
+
+impl Error for DecodeError {
+    fn description(&self) -> &str {
+        "invalid byte sequence"
+    }
+}
+
+/// Decode `bytes` as `encoding`.
+///
+/// A byte order mark at the start of the input takes precedence over
+/// `encoding`, and is removed from the result.
+pub fn decode(bytes: &[u8], encoding: Encoding, trap: DecoderTrap)
+              -> Result<String, DecodeError> {
+    decode_mapped(bytes, encoding, trap).map(|(s, _)| s)
+}
+
+/// Like `decode`, but also return where each byte of the result came
+/// from in `bytes`.
+pub fn decode_mapped(bytes: &[u8], encoding: Encoding, trap: DecoderTrap)
+                     -> Result<(String, OffsetMap), DecodeError> {
+    let (encoding, start) = if bytes.starts_with(b"\xef\xbb\xbf") {
+        (Encoding::Utf8, 3)
+    }
+    else if bytes.starts_with(b"\xff\xfe") {
+        (Encoding::Utf16Le, 2)
+    }
+    else if bytes.starts_with(b"\xfe\xff") {
+        (Encoding::Utf16Be, 2)
+    }
+    else {
+        (encoding, 0)
+    };
+    let bytes = &bytes[start..];
+    let mut decoder = Decoder {
+        result: String::with_capacity(bytes.len()),
+        encoding: encoding,
+        trap: trap,
+        start: start,
+        map: OffsetMap::new(),
+    };
+    decoder.map.record(0, start);
+    try!(match encoding {
+        Encoding::Utf8 => decoder.utf8(bytes),
+        Encoding::Utf16Le => decoder.utf16(bytes, u16::from_le_bytes),
+        Encoding::Utf16Be => decoder.utf16(bytes, u16::from_be_bytes),
+        Encoding::Ascii => decoder.single_byte(bytes, |b| {
+            if b < 0x80 { Some(b as char) } else { None }
+        }),
+        Encoding::Latin1 => decoder.single_byte(bytes, |b| Some(b as char)),
+        Encoding::Latin9 => decoder.single_byte(bytes, latin9),
+        Encoding::Windows1252 => decoder.single_byte(bytes, windows1252),
+    });
+    Ok((decoder.result, decoder.map))
+}
+
+struct Decoder {
+    result: String,
+    encoding: Encoding,
+    trap: DecoderTrap,
+    /// The offset of the decoded bytes in the original input.
+    start: usize,
+    /// Where each byte of the result came from in the original input.
+    map: OffsetMap,
+}
+
+impl Decoder {
+    /// Add `c`, decoded from the `len` bytes at `offset`.
+    fn push(&mut self, c: char, offset: usize, len: usize) {
+        self.map.record(self.result.len(), self.start + offset);
+        self.result.push(c);
+        self.map.record(self.result.len(), self.start + offset + len);
+    }
+
+    fn invalid(&mut self, offset: usize, bytes: &[u8]) -> Result<(), DecodeError> {
+        match self.trap {
+            DecoderTrap::Replace => {
+                self.push('\u{fffd}', offset, bytes.len());
+                Ok(())
+            }
+            DecoderTrap::Strict => Err(DecodeError {
+                encoding: self.encoding,
+                offset: self.start + offset,
+                bytes: bytes.to_vec(),
+            }),
+        }
+    }
+
+    fn utf8(&mut self, bytes: &[u8]) -> Result<(), DecodeError> {
+        let mut offset = 0;
+        loop {
+            match str::from_utf8(&bytes[offset..]) {
+                Ok(s) => {
+                    self.map.record(self.result.len(), self.start + offset);
+                    self.result.push_str(s);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let valid = offset + e.valid_up_to();
+                    self.map.record(self.result.len(), self.start + offset);
+                    self.result.push_str(str::from_utf8(&bytes[offset..valid]).unwrap());
+                    let end = valid + e.error_len().unwrap_or(bytes.len() - valid);
+                    try!(self.invalid(valid, &bytes[valid..end]));
+                    offset = end;
+                }
+            }
+        }
+    }
+
+    fn utf16(&mut self, bytes: &[u8], unit: fn([u8; 2]) -> u16)
+             -> Result<(), DecodeError> {
+        let units = bytes.chunks(2).filter(|c| c.len() == 2).map(|c| unit([c[0], c[1]]));
+        let mut offset = 0;
+        for c in char::decode_utf16(units) {
+            match c {
+                Ok(c) => {
+                    self.push(c, offset, 2 * c.len_utf16());
+                    offset += 2 * c.len_utf16();
+                }
+                Err(_) => {
+                    try!(self.invalid(offset, &bytes[offset..offset + 2]));
+                    offset += 2;
+                }
+            }
+        }
+        if bytes.len() % 2 == 1 {
+            try!(self.invalid(offset, &bytes[offset..]));
+        }
+        Ok(())
+    }
+
+    fn single_byte<F>(&mut self, bytes: &[u8], table: F) -> Result<(), DecodeError>
+        where F: Fn(u8) -> Option<char> {
+        for (offset, &b) in bytes.iter().enumerate() {
+            match table(b) {
+                Some(c) => self.push(c, offset, 1),
+                None => try!(self.invalid(offset, &bytes[offset..offset + 1])),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn latin9(b: u8) -> Option<char> {
+    Some(match b {
+        0xa4 => '\u{20ac}',
+        0xa6 => '\u{160}',
+        0xa8 => '\u{161}',
+        0xb4 => '\u{17d}',
+        0xb8 => '\u{17e}',
+        0xbc => '\u{152}',
+        0xbd => '\u{153}',
+        0xbe => '\u{178}',
+        b => b as char,
+    })
+}
+
+/// The characters Windows-1252 puts in place of the C1 control codes,
+/// starting at 0x80. Unassigned bytes are `None`.
+static WINDOWS_1252_C1: [Option<char>; 32] = [
+    Some('\u{20ac}'), None, Some('\u{201a}'), Some('\u{192}'),
+    Some('\u{201e}'), Some('\u{2026}'), Some('\u{2020}'), Some('\u{2021}'),
+    Some('\u{2c6}'), Some('\u{2030}'), Some('\u{160}'), Some('\u{2039}'),
+    Some('\u{152}'), None, Some('\u{17d}'), None,
+    None, Some('\u{2018}'), Some('\u{2019}'), Some('\u{201c}'),
+    Some('\u{201d}'), Some('\u{2022}'), Some('\u{2013}'), Some('\u{2014}'),
+    Some('\u{2dc}'), Some('\u{2122}'), Some('\u{161}'), Some('\u{203a}'),
+    Some('\u{153}'), None, Some('\u{17e}'), Some('\u{178}'),
+];
+
+fn windows1252(b: u8) -> Option<char> {
+    if b >= 0x80 && b < 0xa0 {
+        WINDOWS_1252_C1[(b - 0x80) as usize]
+    }
+    else {
+        Some(b as char)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lossy(bytes: &[u8], encoding: Encoding) -> String {
+        decode(bytes, encoding, DecoderTrap::Replace).unwrap()
+    }
+
+    #[test]
+    fn test_parse_encoding_names() {
+        assert_eq!("UTF-8".parse(), Ok(Encoding::Utf8));
+        assert_eq!("utf-16".parse(), Ok(Encoding::Utf16Le));
+        assert_eq!("Latin1".parse(), Ok(Encoding::Latin1));
+        assert_eq!("cp1252".parse(), Ok(Encoding::Windows1252));
+        assert!("ebcdic".parse::<Encoding>().is_err());
+    }
+
+    #[test]
+    fn test_single_byte_encodings() {
+        let bytes = b"caf\xe9 \x80\xa4";
+        assert_eq!(lossy(bytes, Encoding::Latin1), "caf\u{e9} \u{80}\u{a4}");
+        assert_eq!(lossy(bytes, Encoding::Latin9), "caf\u{e9} \u{80}\u{20ac}");
+        assert_eq!(lossy(bytes, Encoding::Windows1252), "caf\u{e9} \u{20ac}\u{a4}");
+        assert_eq!(lossy(bytes, Encoding::Ascii), "caf\u{fffd} \u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn test_utf8() {
+        assert_eq!(lossy("h\u{e9}".as_bytes(), Encoding::Utf8), "h\u{e9}");
+        assert_eq!(lossy(b"a\xffb\xe2\x82", Encoding::Utf8), "a\u{fffd}b\u{fffd}");
+    }
+
+    #[test]
+    fn test_utf16_byte_order_marks() {
+        assert_eq!(lossy(b"\xff\xfeh\x00\xe9\x00", Encoding::Utf8), "h\u{e9}");
+        assert_eq!(lossy(b"\xfe\xff\x00h\x00\xe9", Encoding::Latin1), "h\u{e9}");
+        assert_eq!(lossy(b"h\x00i\x00", Encoding::Utf16Le), "hi");
+        assert_eq!(lossy(b"\x00h\x00i", Encoding::Utf16Be), "hi");
+        assert_eq!(lossy(b"\x3d\xd8\x00\xde", Encoding::Utf16Le), "\u{1f600}");
+        assert_eq!(lossy(b"\xef\xbb\xbfhi", Encoding::Latin1), "hi");
+    }
+
+    #[test]
+    fn test_strict_errors() {
+        assert_eq!(decode(b"ok\x81", Encoding::Windows1252, DecoderTrap::Strict),
+                   Err(DecodeError {
+                       encoding: Encoding::Windows1252,
+                       offset: 2,
+                       bytes: vec![0x81],
+                   }));
+        let err = decode(b"\xff\xfea\x00\x00\xdc", Encoding::Utf8, DecoderTrap::Strict)
+                  .unwrap_err();
+        assert_eq!(err.encoding, Encoding::Utf16Le);
+        assert_eq!(err.offset, 4);
+        assert_eq!(format!("{}", err), "invalid utf-16le sequence at byte 4: 0x00 0xdc");
+        assert_eq!(lossy(b"a\x00\x00\xdcb", Encoding::Utf16Le), "a\u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn test_decode_mapped() {
+        let original = |bytes: &[u8], encoding| {
+            let (s, map) = decode_mapped(bytes, encoding, DecoderTrap::Replace).unwrap();
+            (0..s.len() + 1).map(|i| map.original(i)).collect::<Vec<_>>()
+        };
+        assert_eq!(original(b"\xff\xfea\x00\n\x00", Encoding::Utf8), vec![2, 4, 6]);
+        assert_eq!(original(b"caf\xe9x", Encoding::Latin1), vec![0, 1, 2, 3, 4, 4, 5]);
+        assert_eq!(original(b"\xef\xbb\xbfa\xffb", Encoding::Utf8),
+                   vec![3, 4, 5, 5, 5, 6]);
+    }
+}
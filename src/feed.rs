@@ -0,0 +1,408 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Atom and RSS feeds of dated documents.
+//!
+//! Every document with a `date` in its front matter is an entry, newest
+//! first. An entry's summary is everything before a `<!-- more -->`
+//! paragraph, or if there isn't one, the first paragraph. Its content is
+//! the whole document, with links made absolute so they still work from
+//! a feed reader.
+
+use rustdoc::html::escape::Escape;
+use url::Url;
+
+use html::HtmlOptions;
+use links::RewriteLinks;
+use metadata::MetaValue;
+use parser::Event;
+use types::{Document, MarkdownStructure, VisitorMut};
+
+// Like `try!`, for functions returning an `Option`.
+macro_rules! try_opt (
+    ($e:expr) => (match $e { Some(x) => x, None => return None })
+);
+
+/// The paragraph separating a summary from the rest of a document.
+pub const MORE: &'static str = "<!-- more -->";
+
+/// A date and time from front matter, such as `2015-03-14`,
+/// `2015-03-14 15:09` or `2015-03-14T15:09:26+01:00`. A date without a
+/// time is taken to be midnight, and a time without an offset to be UTC.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct FeedDate {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// Minutes east of UTC.
+    pub offset: i32,
+}
+
+fn number(s: &str) -> Option<u32> {
+    if s.is_empty() || !s.chars().all(|c| c.is_digit(10)) { None } else { s.parse().ok() }
+}
+
+/// The start of 1970, in UTC.
+const EPOCH: FeedDate = FeedDate {
+    year: 1970,
+    month: 1,
+    day: 1,
+    hour: 0,
+    minute: 0,
+    second: 0,
+    offset: 0,
+};
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 +
+                      day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+impl FeedDate {
+    pub fn parse(s: &str) -> Option<FeedDate> {
+        let s = s.trim();
+        if s.len() < 10 || !s.is_char_boundary(10) || &s[4..5] != "-" || &s[7..8] != "-" {
+            return None;
+        }
+        let mut date = FeedDate {
+            year: try_opt!(number(&s[..4])) as i64,
+            month: try_opt!(number(&s[5..7])),
+            day: try_opt!(number(&s[8..10])),
+            hour: 0,
+            minute: 0,
+            second: 0,
+            offset: 0,
+        };
+        let mut rest = &s[10..];
+        if let Some(c) = rest.chars().next() {
+            if c != 'T' && c != 't' && c != ' ' || rest.len() < 6 || &rest[3..4] != ":" {
+                return None;
+            }
+            date.hour = try_opt!(number(&rest[1..3]));
+            date.minute = try_opt!(number(&rest[4..6]));
+            rest = &rest[6..];
+            if rest.starts_with(':') && rest.len() >= 3 {
+                date.second = try_opt!(number(&rest[1..3]));
+                rest = &rest[3..];
+                if rest.starts_with('.') {
+                    rest = rest[1..].trim_left_matches(|c: char| c.is_digit(10));
+                }
+            }
+            date.offset = match rest {
+                "" | "Z" | "z" => 0,
+                _ if rest.len() == 6 && &rest[3..4] == ":" => {
+                    let minutes = try_opt!(number(&rest[1..3])) * 60 + try_opt!(number(&rest[4..]));
+                    match &rest[..1] {
+                        "+" => minutes as i32,
+                        "-" => -(minutes as i32),
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            };
+        }
+        let valid = date.month >= 1 && date.month <= 12 && date.day >= 1 && date.day <= 31 &&
+                    date.hour < 24 && date.minute < 60 && date.second < 61;
+        if valid { Some(date) } else { None }
+    }
+
+    /// Seconds since the Unix epoch.
+    pub fn timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400 +
+            (self.hour * 3600 + self.minute * 60 + self.second) as i64 - self.offset as i64 * 60
+    }
+
+    fn zone(&self, separator: &str) -> String {
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let offset = self.offset.abs();
+        format!("{}{:02}{}{:02}", sign, offset / 60, separator, offset % 60)
+    }
+
+    /// The date as Atom wants it, like `2015-03-14T15:09:26Z`.
+    pub fn to_rfc3339(&self) -> String {
+        let zone = if self.offset == 0 { "Z".to_string() } else { self.zone(":") };
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}", self.year, self.month, self.day,
+                self.hour, self.minute, self.second, zone)
+    }
+
+    /// The date as RSS wants it, like `Sat, 14 Mar 2015 15:09:26 +0000`.
+    pub fn to_rfc822(&self) -> String {
+        const DAYS: [&'static str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+        const MONTHS: [&'static str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul",
+                                            "Aug", "Sep", "Oct", "Nov", "Dec"];
+        let days = days_from_civil(self.year, self.month, self.day);
+        format!("{}, {:02} {} {:04} {:02}:{:02}:{:02} {}", DAYS[(((days % 7) + 7) % 7) as usize],
+                self.day, MONTHS[self.month as usize - 1], self.year, self.hour, self.minute,
+                self.second, self.zone(""))
+    }
+}
+
+/// Whether `block` is the `<!-- more -->` comment.
+pub fn is_more(block: &MarkdownStructure) -> bool {
+    match block {
+        &MarkdownStructure::MDX(ref html) => html.html().trim() == MORE,
+        _ => false,
+    }
+}
+
+/// Whether `event` is the `<!-- more -->` comment, for output rendered
+/// straight from the parser.
+pub fn is_more_event(event: &Event) -> bool {
+    match event {
+        &Event::Html(ref html) => html.trim() == MORE,
+        _ => false,
+    }
+}
+
+/// Removes `<!-- more -->` comments, so they don't show up in pages or
+/// any other output.
+pub struct StripMore;
+
+impl VisitorMut for StripMore {
+    fn visit_document_mut(&mut self, document: &mut Document) {
+        document.blocks_mut().retain(|block| !is_more(block));
+    }
+}
+
+/// An entry in a feed.
+///
+#[derive(Clone, Debug)]
+pub struct FeedEntry {
+    pub title: String,
+    pub date: FeedDate,
+    /// The absolute URL of the entry's page.
+    pub url: String,
+    pub author: Option<String>,
+    /// The summary and content, as HTML.
+    pub summary: String,
+    pub content: String,
+}
+
+impl FeedEntry {
+    /// The entry for `document`, which is published at `url`, if it has a
+    /// `date`. Its title is the front matter's `title`, or the first
+    /// heading, or else `title`.
+    pub fn new(document: &Document, url: &Url, title: &str) -> Result<Option<FeedEntry>, String> {
+        let date = match document.meta("date").and_then(MetaValue::as_str) {
+            Some(date) => try!(FeedDate::parse(date).ok_or_else(|| {
+                format!("invalid date `{}`", date)
+            })),
+            None => return Ok(None),
+        };
+        let mut document = document.clone();
+        let mut absolute = RewriteLinks {
+            extensions: vec![],
+            base_url: Some(url.clone()),
+        };
+        absolute.visit_document_mut(&mut document);
+        let options = HtmlOptions::default();
+        let blocks = document.blocks().to_vec();
+        let summary = match blocks.iter().position(is_more) {
+            Some(cut) => Document::new(blocks[..cut].to_vec()),
+            None => {
+                let first = blocks.iter().find(|block| match *block {
+                    &MarkdownStructure::MDP(_) => true,
+                    _ => false,
+                });
+                Document::new(first.into_iter().cloned().collect())
+            }
+        };
+        StripMore.visit_document_mut(&mut document);
+        Ok(Some(FeedEntry {
+            title: document.meta("title").and_then(MetaValue::as_str).map(|t| t.to_string())
+                .or(document.title()).unwrap_or(title.to_string()),
+            date: date,
+            url: url.serialize(),
+            author: document.meta("author").and_then(MetaValue::as_str).map(|a| a.to_string()),
+            summary: summary.body_html(&options),
+            content: document.body_html(&options),
+        }))
+    }
+}
+
+/// What a feed is about.
+///
+#[derive(Clone, Debug)]
+pub struct FeedOptions {
+    pub title: String,
+    /// The URL of the site the feed is for.
+    pub url: Url,
+    /// The author of entries that don't name one.
+    pub author: Option<String>,
+}
+
+/// Sort entries newest first.
+pub fn sort_entries(entries: &mut Vec<FeedEntry>) {
+    entries.sort_by(|a, b| b.date.timestamp().cmp(&a.date.timestamp()));
+}
+
+fn escape(s: &str) -> String {
+    format!("{}", Escape(s))
+}
+
+/// An Atom feed of `entries`, which should be sorted, published at
+/// `self_url`.
+///
+/// The feed was last updated when its newest entry was, or at the Unix
+/// epoch if it has none, so that the same entries always make the same
+/// feed. Its author is `options.author`, or failing that the feed's
+/// title, since Atom needs one.
+pub fn atom(options: &FeedOptions, self_url: &str, entries: &[FeedEntry]) -> String {
+    let mut xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                   <feed xmlns=\"http://www.w3.org/2005/Atom\">\n".to_string();
+    let url = escape(&options.url.serialize());
+    xml.push_str(&format!("<title>{}</title>\n<link href=\"{}\" />\n\
+                           <link rel=\"self\" href=\"{}\" />\n<id>{}</id>\n",
+                          escape(&options.title), url, escape(self_url), url));
+    let updated = entries.first().map_or(EPOCH, |entry| entry.date);
+    xml.push_str(&format!("<updated>{}</updated>\n", updated.to_rfc3339()));
+    let author = options.author.as_ref().unwrap_or(&options.title);
+    xml.push_str(&format!("<author><name>{}</name></author>\n", escape(author)));
+    for entry in entries.iter() {
+        let url = escape(&entry.url);
+        xml.push_str(&format!("<entry>\n<title>{}</title>\n<link href=\"{}\" />\n<id>{}</id>\n\
+                               <updated>{}</updated>\n",
+                              escape(&entry.title), url, url, entry.date.to_rfc3339()));
+        if let Some(ref author) = entry.author {
+            xml.push_str(&format!("<author><name>{}</name></author>\n", escape(author)));
+        }
+        xml.push_str(&format!("<summary type=\"html\">{}</summary>\n\
+                               <content type=\"html\">{}</content>\n</entry>\n",
+                              escape(&entry.summary), escape(&entry.content)));
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// An RSS 2.0 feed of `entries`, which should be sorted. The summary is
+/// the item's description, and the content goes in `content:encoded`.
+pub fn rss(options: &FeedOptions, entries: &[FeedEntry]) -> String {
+    let mut xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                   <rss version=\"2.0\" \
+                   xmlns:content=\"http://purl.org/rss/1.0/modules/content/\">\n\
+                   <channel>\n".to_string();
+    xml.push_str(&format!("<title>{0}</title>\n<link>{1}</link>\n\
+                           <description>{0}</description>\n",
+                          escape(&options.title), escape(&options.url.serialize())));
+    if let Some(entry) = entries.first() {
+        xml.push_str(&format!("<lastBuildDate>{}</lastBuildDate>\n", entry.date.to_rfc822()));
+    }
+    for entry in entries.iter() {
+        let url = escape(&entry.url);
+        xml.push_str(&format!("<item>\n<title>{}</title>\n<link>{}</link>\n\
+                               <guid isPermaLink=\"true\">{}</guid>\n<pubDate>{}</pubDate>\n\
+                               <description>{}</description>\n\
+                               <content:encoded>{}</content:encoded>\n</item>\n",
+                              escape(&entry.title), url, url, entry.date.to_rfc822(),
+                              escape(&entry.summary), escape(&entry.content)));
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+    use testing::lines;
+
+    use convert::parse_document;
+    use super::{FeedDate, FeedEntry, FeedOptions, atom, rss, sort_entries};
+
+    #[test]
+    fn test_dates() {
+        let date = FeedDate::parse("2015-03-14").unwrap();
+        assert_eq!(date.to_rfc3339(), "2015-03-14T00:00:00Z");
+        assert_eq!(date.to_rfc822(), "Sat, 14 Mar 2015 00:00:00 +0000");
+        let date = FeedDate::parse("2015-03-14T15:09:26.535-05:30").unwrap();
+        assert_eq!(date.to_rfc3339(), "2015-03-14T15:09:26-05:30");
+        assert_eq!(date.to_rfc822(), "Sat, 14 Mar 2015 15:09:26 -0530");
+        assert_eq!(date.timestamp(), 1426345766 + 5 * 3600 + 30 * 60);
+        assert_eq!(FeedDate::parse("1999-12-31 23:59").unwrap().to_rfc822(),
+                   "Fri, 31 Dec 1999 23:59:00 +0000");
+        assert_eq!(FeedDate::parse("2015-13-01"), None);
+        assert_eq!(FeedDate::parse("14/03/2015"), None);
+        assert_eq!(FeedDate::parse("2015-03-14T25:00"), None);
+    }
+
+    fn entry(ls: &[&str], url: &str) -> Option<FeedEntry> {
+        let document = parse_document(&lines(ls)).unwrap();
+        FeedEntry::new(&document, &Url::parse(url).unwrap(), "fallback").unwrap()
+    }
+
+    #[test]
+    fn test_entries() {
+        let first = entry(&["---", "date: 2015-03-14", "---", "# Pi & more", "",
+                            "Intro [link](other.html).", "", "Second.", "", "<!-- more -->",
+                            "", "Rest."], "https://example.com/blog/pi.html").unwrap();
+        assert_eq!(first.title, "Pi & more");
+        assert_eq!(first.summary, "<h1>Pi &amp; more</h1>\n<p>Intro \
+                                   <a href=\"https://example.com/blog/other.html\">link</a>.</p>\n\
+                                   <p>Second.</p>");
+        assert!(first.content.ends_with("<p>Second.</p>\n<p>Rest.</p>"));
+
+        let second = entry(&["---", "date: 2016-01-01", "title: Later", "author: Someone", "---",
+                             "# Heading", "", "Only this.", "", "Not this."],
+                           "https://example.com/later.html").unwrap();
+        assert_eq!((&second.title[..], &second.summary[..]), ("Later", "<p>Only this.</p>"));
+        assert!(entry(&["No date."], "https://example.com/").is_none());
+
+        let mut entries = vec![first, second];
+        sort_entries(&mut entries);
+        let options = FeedOptions {
+            title: "News <3".to_string(),
+            url: Url::parse("https://example.com/").unwrap(),
+            author: None,
+        };
+        let atom = atom(&options, "https://example.com/atom.xml", &entries);
+        assert!(atom.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                                  <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+                                  <title>News &lt;3</title>\n\
+                                  <link href=\"https://example.com/\" />\n\
+                                  <link rel=\"self\" href=\"https://example.com/atom.xml\" />\n\
+                                  <id>https://example.com/</id>\n\
+                                  <updated>2016-01-01T00:00:00Z</updated>\n\
+                                  <author><name>News &lt;3</name></author>\n\
+                                  <entry>\n<title>Later</title>\n"));
+        assert!(atom.contains("<author><name>Someone</name></author>\n\
+                               <summary type=\"html\">&lt;p&gt;Only this.&lt;/p&gt;</summary>"));
+        assert!(atom.contains("<title>Pi &amp; more</title>"));
+        let rss = rss(&options, &entries);
+        assert!(rss.contains("<lastBuildDate>Fri, 01 Jan 2016 00:00:00 +0000</lastBuildDate>"));
+        assert!(rss.contains("<item>\n<title>Later</title>\n\
+                              <link>https://example.com/later.html</link>\n\
+                              <guid isPermaLink=\"true\">https://example.com/later.html</guid>\n\
+                              <pubDate>Fri, 01 Jan 2016 00:00:00 +0000</pubDate>\n\
+                              <description>&lt;p&gt;Only this.&lt;/p&gt;</description>\n"));
+        assert!(rss.ends_with("</item>\n</channel>\n</rss>\n"));
+    }
+
+    #[test]
+    fn test_empty_atom_feed() {
+        let options = FeedOptions {
+            title: "Empty".to_string(),
+            url: Url::parse("https://example.com/").unwrap(),
+            author: Some("A & B".to_string()),
+        };
+        let atom = atom(&options, "https://example.com/atom.xml", &[]);
+        assert!(atom.ends_with("<id>https://example.com/</id>\n\
+                                <updated>1970-01-01T00:00:00Z</updated>\n\
+                                <author><name>A &amp; B</name></author>\n</feed>\n"));
+    }
+}
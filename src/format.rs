@@ -0,0 +1,81 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::str::FromStr;
+
+/// What the input file is written in.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum InputFormat {
+    Markdown,
+    /// A document dumped with `--to json`.
+    Json,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<InputFormat, String> {
+        match s {
+            "markdown" | "md" => Ok(InputFormat::Markdown),
+            "json" => Ok(InputFormat::Json),
+            _ => Err(format!("unknown input format `{}` (expected markdown or json)", s)),
+        }
+    }
+}
+
+/// What the document is rendered as.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    Html,
+    /// The parsed document, as described in `json`.
+    Json,
+    /// Normalised markdown, as written by `markdown`.
+    Markdown,
+    /// Plain text, as written by `plaintext`.
+    Text,
+    /// Text styled for a terminal, as written by `terminal`.
+    Terminal,
+    /// LaTeX, as written by `latex`.
+    Latex,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OutputFormat, String> {
+        match s {
+            "html" => Ok(OutputFormat::Html),
+            "json" => Ok(OutputFormat::Json),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "text" | "txt" => Ok(OutputFormat::Text),
+            "terminal" => Ok(OutputFormat::Terminal),
+            "latex" | "tex" => Ok(OutputFormat::Latex),
+            _ => Err(format!("unknown output format `{}` (expected html, json, markdown, text, \
+                              terminal or latex)", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputFormat, OutputFormat};
+
+    #[test]
+    fn test_parse_formats() {
+        assert_eq!("md".parse(), Ok(InputFormat::Markdown));
+        assert_eq!("json".parse(), Ok(InputFormat::Json));
+        assert_eq!("html".parse(), Ok(OutputFormat::Html));
+        assert_eq!("markdown".parse(), Ok(OutputFormat::Markdown));
+        assert_eq!("txt".parse(), Ok(OutputFormat::Text));
+        assert_eq!("tex".parse(), Ok(OutputFormat::Latex));
+        assert!("pdf".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The HTML renderer. Everything rendered as HTML goes through
+//! `write_events`, whether the events come straight from the parser or
+//! from a document tree.
+
+use std::fmt;
+use std::io;
+use std::iter::Peekable;
+
+use rustdoc::html::escape::Escape;
+
+use parser;
+use parser::{Alignment, Event, Tag, ToEvents};
+use slug::Slugger;
+use span::Span;
+use super::HtmlOptions;
+use super::IoWriter;
+use super::SoftBreakStyle;
+use super::WriteHtml;
+use super::write::{write_simple, write_sourcepos};
+
+/// Render a stream of parse events and their spans as HTML.
+///
+/// Top-level blocks go one per line. If `options.pretty` is set, every
+/// block goes on a line of its own, and the blocks inside lists are
+/// indented beneath them. The contents of other blocks are written as
+/// they are.
+pub fn write_events<I, W>(events: I, w: &mut W, options: &HtmlOptions) -> fmt::Result
+    where I: Iterator<Item=(Event, Span)>, W: fmt::Write
+{
+    let mut events = events.peekable();
+    let mut slugger = Slugger::new();
+    let mut depth = 0;
+    let mut started = false;
+    // The columns of the table being written, whether its head is, and
+    // which column the next cell is in.
+    let mut alignments = vec![];
+    let mut in_head = false;
+    let mut column = 0;
+    while let Some((event, span)) = events.next() {
+        let block = match event {
+            Event::Start(ref tag) => is_block(tag),
+            Event::Rule | Event::CodeBlock(..) | Event::Html(_) => true,
+            _ => false,
+        };
+        if block {
+            if started && (depth == 0 || options.pretty.is_some()) {
+                try!(w.write_str("\n"));
+                try!(write_indent(w, depth, options));
+            }
+            started = true;
+        }
+        match event {
+            Event::Start(Tag::Table(ref columns)) => alignments = columns.clone(),
+            Event::Start(Tag::TableHead) => in_head = true,
+            Event::End(Tag::TableHead) => in_head = false,
+            Event::Start(Tag::TableRow) => column = 0,
+            _ => {}
+        }
+        match event {
+            Event::Start(Tag::TableCell) => {
+                try!(write!(w, "<{}", if in_head { "th" } else { "td" }));
+                match alignments.get(column) {
+                    Some(&Alignment::Left) => try!(w.write_str(" align=\"left\"")),
+                    Some(&Alignment::Center) => try!(w.write_str(" align=\"center\"")),
+                    Some(&Alignment::Right) => try!(w.write_str(" align=\"right\"")),
+                    _ => {}
+                }
+                try!(write_sourcepos(w, &span, options));
+                try!(w.write_str(">"));
+                depth += 1;
+                column += 1;
+            }
+            Event::End(Tag::TableCell) => {
+                depth -= 1;
+                try!(write!(w, "</{}>", if in_head { "th" } else { "td" }));
+            }
+            Event::Start(Tag::Heading(level)) if options.heading_ids => {
+                let mut contents = vec![];
+                while let Some((event, span)) = events.next() {
+                    if event == Event::End(Tag::Heading(level)) {
+                        break;
+                    }
+                    contents.push((event, span));
+                }
+                let id = slugger.slug(&parser::collect_text(&mut contents.clone().into_iter()));
+                try!(write!(w, "<h{}", level));
+                try!(write_sourcepos(w, &span, options));
+                try!(write!(w, " id=\"{}\">", Escape(&id)));
+                let mut contents = contents.into_iter().peekable();
+                while let Some((event, _)) = contents.next() {
+                    try!(write_inline(event, &mut contents, w, options));
+                }
+                try!(write!(w, "</h{}>", level));
+            }
+            Event::Start(ref tag) if is_block(tag) => {
+                try!(write!(w, "<{}", tag_name(tag)));
+                if let &Tag::List(Some(start)) = tag {
+                    if start != 1 {
+                        try!(write!(w, " start=\"{}\"", start));
+                    }
+                }
+                try!(write_sourcepos(w, &span, options));
+                if events.peek().map_or(false, |&(ref next, _)| *next == Event::End(tag.clone())) {
+                    events.next();
+                    try!(w.write_str(" />"));
+                }
+                else {
+                    depth += 1;
+                    try!(w.write_str(">"));
+                }
+            }
+            Event::End(ref tag) if is_block(tag) => {
+                depth -= 1;
+                if options.pretty.is_some() && holds_blocks(tag) {
+                    try!(w.write_str("\n"));
+                    try!(write_indent(w, depth, options));
+                }
+                try!(write!(w, "</{}>", tag_name(tag)));
+            }
+            Event::Rule => {
+                try!(w.write_str("<hr"));
+                try!(write_sourcepos(w, &span, options));
+                try!(w.write_str(" />"));
+            }
+            Event::CodeBlock(info, code) => {
+                try!(w.write_str("<pre"));
+                try!(write_sourcepos(w, &span, options));
+                try!(w.write_str("><code"));
+                if let Some(language) = info.split_whitespace().next() {
+                    try!(write!(w, " class=\"language-{}\"", Escape(language)));
+                }
+                try!(write!(w, ">{}</code></pre>", Escape(&code)));
+            }
+            Event::Html(html) => try!(w.write_str(&html)),
+            event => try!(write_inline(event, &mut events, w, options)),
+        }
+    }
+    Ok(())
+}
+
+/// Stream the HTML for `events` to `writer`.
+pub fn write_events_to<I, W>(events: I, writer: W, options: &HtmlOptions) -> io::Result<()>
+    where I: Iterator<Item=(Event, Span)>, W: io::Write
+{
+    let mut w = IoWriter::new(writer);
+    let result = write_events(events, &mut w, options);
+    w.result(result)
+}
+
+/// Nodes of the document tree are rendered from their events.
+impl<T: ToEvents> WriteHtml for T {
+    fn write_html<W: fmt::Write>(&self, w: &mut W, options: &HtmlOptions) -> fmt::Result {
+        let mut events = vec![];
+        self.to_events(&mut events);
+        write_events(events.into_iter(), w, options)
+    }
+}
+
+fn write_indent<W: fmt::Write>(w: &mut W, depth: usize, options: &HtmlOptions) -> fmt::Result {
+    match options.pretty {
+        Some(indent) => w.write_str(&" ".repeat(depth * indent)),
+        None => Ok(()),
+    }
+}
+
+/// Write a single inline event. Inline tags hold plain text, which is
+/// taken from `events` up to the end of the tag.
+fn write_inline<I, W>(event: Event, events: &mut Peekable<I>, w: &mut W,
+                      options: &HtmlOptions) -> fmt::Result
+    where I: Iterator<Item=(Event, Span)>, W: fmt::Write
+{
+    match event {
+        Event::Text(s) => write!(w, "{}", Escape(&s)),
+        Event::Code(s) => write_simple(w, "code", &s),
+        Event::SoftBreak => {
+            match options.soft_break {
+                SoftBreakStyle::Newline => w.write_str("\n"),
+                SoftBreakStyle::Space => w.write_str(" "),
+                SoftBreakStyle::LineBreak => w.write_str("<br />\n"),
+            }
+        }
+        Event::HardBreak => w.write_str("<br />\n"),
+        Event::Start(Tag::Emphasis) => write_simple(w, "em", &parser::collect_text(events)),
+        Event::Start(Tag::Strong) => write_simple(w, "strong", &parser::collect_text(events)),
+        Event::Start(Tag::Link(url, title)) => {
+            try!(write!(w, "<a href=\"{}\"", Escape(&url)));
+            if let Some(title) = title {
+                try!(write!(w, " title=\"{}\"", Escape(&title)));
+            }
+            write!(w, ">{}</a>", Escape(&parser::collect_text(events)))
+        }
+        Event::Start(Tag::Image(src)) => {
+            write!(w, "<img src=\"{}\" alt=\"{}\" />", Escape(&src),
+                   Escape(&parser::collect_text(events)))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn is_block(tag: &Tag) -> bool {
+    match tag {
+        &Tag::Heading(_) | &Tag::Paragraph | &Tag::List(_) | &Tag::Item |
+        &Tag::BlockQuote | &Tag::Table(_) | &Tag::TableHead | &Tag::TableBody |
+        &Tag::TableRow | &Tag::TableCell => true,
+        _ => false,
+    }
+}
+
+/// Whether a block holds other blocks, rather than inline contents.
+fn holds_blocks(tag: &Tag) -> bool {
+    match tag {
+        &Tag::List(_) | &Tag::BlockQuote | &Tag::Table(_) | &Tag::TableHead |
+        &Tag::TableBody | &Tag::TableRow => true,
+        _ => false,
+    }
+}
+
+fn tag_name(tag: &Tag) -> String {
+    match tag {
+        &Tag::Heading(level) => format!("h{}", level),
+        &Tag::Paragraph => "p".to_string(),
+        &Tag::List(Some(_)) => "ol".to_string(),
+        &Tag::List(None) => "ul".to_string(),
+        &Tag::Item => "li".to_string(),
+        &Tag::Emphasis => "em".to_string(),
+        &Tag::Strong => "strong".to_string(),
+        &Tag::Link(..) => "a".to_string(),
+        &Tag::Image(_) => "img".to_string(),
+        &Tag::BlockQuote => "blockquote".to_string(),
+        &Tag::Table(_) => "table".to_string(),
+        &Tag::TableHead => "thead".to_string(),
+        &Tag::TableBody => "tbody".to_string(),
+        &Tag::TableRow => "tr".to_string(),
+        &Tag::TableCell => "td".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_events;
+    use convert::parse_document;
+    use html::{HtmlOptions, SoftBreakStyle};
+    use parser::Parser;
+    use testing::lines;
+
+    #[test]
+    fn test_events_match_document() {
+        let input = lines(&["# A *b* <c>", "", "Some  ", "[text](t.md \"T&\") &amp;", "**more**", "",
+                            "1. x", "   - `y`", "2. ![z](z.png)", "", "---", "", "#", "",
+                            "Setext", "---", "", "# A *b* <c>", "", "``` x y", "<&>", "```", "",
+                            "    z"]);
+        let document = parse_document(&input).unwrap();
+        for &(ids, soft_break, sourcepos) in [(false, SoftBreakStyle::Newline, false),
+                                              (true, SoftBreakStyle::LineBreak, true)].iter() {
+            let mut options = HtmlOptions::default();
+            options.heading_ids = ids;
+            options.soft_break = soft_break;
+            options.sourcepos = sourcepos;
+            let mut out = String::new();
+            write_events(Parser::new(&input).spanned(), &mut out, &options).unwrap();
+            assert_eq!(out, document.body_html(&options));
+        }
+    }
+
+    #[test]
+    fn test_pretty() {
+        let input = lines(&["# A", "", "- b *c*", "  - d", "", "e  ", " f"]);
+        let mut options = HtmlOptions::default();
+        options.pretty = Some(2);
+        let mut out = String::new();
+        write_events(Parser::new(&input).spanned(), &mut out, &options).unwrap();
+        assert_eq!(out, "<h1>A</h1>\n<ul>\n  <li>b <em>c</em></li>\n  <ul>\n    <li>d</li>\n  \
+                         </ul>\n</ul>\n<p>e<br />\nf</p>");
+    }
+
+    #[test]
+    fn test_html_blocks_are_raw() {
+        let input = lines(&["<div>", "<b>&</b>", "</div>", "", "<!-- more -->", "text"]);
+        let mut out = String::new();
+        write_events(Parser::new(&input).spanned(), &mut out, &HtmlOptions::default()).unwrap();
+        assert_eq!(out, "<div>\n<b>&</b>\n</div>\n<!-- more -->\n<p>text</p>");
+    }
+
+    #[test]
+    fn test_block_quotes() {
+        let input = lines(&["> a", "> > b", "", "> - c"]);
+        let mut out = String::new();
+        write_events(Parser::new(&input).spanned(), &mut out, &HtmlOptions::default()).unwrap();
+        assert_eq!(out, "<blockquote><p>a</p><blockquote><p>b</p></blockquote></blockquote>\n\
+                         <blockquote><ul><li>c</li></ul></blockquote>");
+        assert_eq!(out, parse_document(&input).unwrap().body_html(&HtmlOptions::default()));
+    }
+
+    #[test]
+    fn test_tables() {
+        let input = lines(&["a | b | c", ":- | :-: | -:", "1 | <2>"]);
+        let mut out = String::new();
+        write_events(Parser::new(&input).spanned(), &mut out, &HtmlOptions::default()).unwrap();
+        assert_eq!(out, "<table><thead><tr><th align=\"left\">a</th><th align=\"center\">b</th>\
+                         <th align=\"right\">c</th></tr></thead><tbody><tr>\
+                         <td align=\"left\">1</td><td align=\"center\">&lt;2&gt;</td>\
+                         <td align=\"right\"></td></tr></tbody></table>");
+        let document = parse_document(&input).unwrap();
+        let mut options = HtmlOptions::default();
+        options.sourcepos = true;
+        let mut out = String::new();
+        write_events(Parser::new(&input).spanned(), &mut out, &options).unwrap();
+        assert_eq!(out, document.body_html(&options));
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        let input = lines(&["```rust x", "a < b", "", "```", "", "    c"]);
+        let mut out = String::new();
+        write_events(Parser::new(&input).spanned(), &mut out, &HtmlOptions::default()).unwrap();
+        assert_eq!(out, "<pre><code class=\"language-rust\">a &lt; b\n\n</code></pre>\n\
+                         <pre><code>c\n</code></pre>");
+    }
+}
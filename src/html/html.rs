@@ -27,7 +27,7 @@ pub enum HtmlContents {
     /// A nested list of more HTML tags. A simple example of this would
     /// be a nested set, like:
     ///
-    ///    ```
+    ///    ```html
     ///    <ul>
     ///        <li>Item1</li>
     ///        <ul>
@@ -111,6 +111,12 @@ impl Html {
         }
     }
 
+    /// Add an attribute to this HTML tag.
+    ///
+    pub fn add_attribute(&mut self, attribute: HtmlAttribute) {
+        self.attributes.push(attribute);
+    }
+
     pub fn slice_contents<'a>(&'a self) -> Option<&'a [HtmlContents]> {
         match self.contents {
             Some(ref contents) => Some(contents),
@@ -119,10 +125,6 @@ impl Html {
     }
 }
 
-pub trait ToHtml {
-    fn to_html(&self) -> Html;
-}
-
 impl fmt::Display for Html {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         let name = Escape(&self.name);
@@ -22,9 +22,18 @@ pub struct HtmlAttribute {
     contents: String,
 }
 
+impl HtmlAttribute {
+    pub fn new(name: String, contents: String) -> HtmlAttribute {
+        HtmlAttribute {
+            name: name,
+            contents: contents,
+        }
+    }
+}
+
 impl fmt::Display for HtmlAttribute {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{}={}", Escape(&self.name),
-                             Escape(&self.contents))
+        write!(fmt, "{}=\"{}\"", Escape(&self.name),
+                                 Escape(&self.contents))
     }
 }
@@ -8,8 +8,17 @@
 // except according to those terms.
 
 pub use self::html::Html;
-pub use self::html::ToHtml;
 pub use self::htmlattr::HtmlAttribute;
+pub use self::options::HtmlOptions;
+pub use self::options::SoftBreakStyle;
+pub use self::write::WriteHtml;
+pub use self::write::IoWriter;
+pub use self::write::write_html_to;
+pub use self::events::write_events;
+pub use self::events::write_events_to;
 
+pub mod events;
 pub mod html;
 pub mod htmlattr;
+pub mod options;
+pub mod write;
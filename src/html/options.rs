@@ -0,0 +1,75 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::str::FromStr;
+
+/// How a soft line break (a plain newline inside a paragraph) is emitted.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum SoftBreakStyle {
+    /// Keep the newline as it was written. Browsers show it as a space.
+    Newline,
+    /// Join the lines with a single space.
+    Space,
+    /// Turn every soft break into a hard one, i.e. `<br />`.
+    LineBreak,
+}
+
+impl FromStr for SoftBreakStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SoftBreakStyle, String> {
+        match s {
+            "newline" => Ok(SoftBreakStyle::Newline),
+            "space" => Ok(SoftBreakStyle::Space),
+            "br" => Ok(SoftBreakStyle::LineBreak),
+            _ => Err(format!("unknown soft break style `{}` \
+                              (expected newline, space or br)", s)),
+        }
+    }
+}
+
+/// Options controlling how markdown is rendered to HTML.
+///
+#[derive(Clone, Debug)]
+pub struct HtmlOptions {
+    pub soft_break: SoftBreakStyle,
+    /// Give headings `id` attributes, so they can be linked to.
+    pub heading_ids: bool,
+    /// Pretty-print the output, indenting by this many spaces per level.
+    /// `None` keeps each block on a single line.
+    pub pretty: Option<usize>,
+    /// Give block elements `data-sourcepos` attributes holding the part
+    /// of the source they came from, as `line:column-line:column`.
+    pub sourcepos: bool,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> HtmlOptions {
+        HtmlOptions {
+            soft_break: SoftBreakStyle::Newline,
+            heading_ids: false,
+            pretty: None,
+            sourcepos: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SoftBreakStyle;
+
+    #[test]
+    fn test_parse_soft_break_style() {
+        assert_eq!("newline".parse(), Ok(SoftBreakStyle::Newline));
+        assert_eq!("space".parse(), Ok(SoftBreakStyle::Space));
+        assert_eq!("br".parse(), Ok(SoftBreakStyle::LineBreak));
+        assert!("tab".parse::<SoftBreakStyle>().is_err());
+    }
+}
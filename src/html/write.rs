@@ -0,0 +1,144 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::io;
+
+use rustdoc::html::escape::Escape;
+
+use span::Span;
+use super::HtmlOptions;
+
+/// Rendering straight to a writer, without building an `Html` tree.
+///
+/// Anything that implements `parser::ToEvents` gets this, rendering its
+/// events with `write_events`.
+pub trait WriteHtml {
+    fn write_html<W: fmt::Write>(&self, w: &mut W, options: &HtmlOptions) -> fmt::Result;
+
+    /// Render to a string using the default `HtmlOptions`.
+    fn to_html(&self) -> String {
+        self.to_html_with(&HtmlOptions::default())
+    }
+
+    fn to_html_with(&self, options: &HtmlOptions) -> String {
+        let mut html = String::new();
+        // Writing to a `String` can't fail.
+        self.write_html(&mut html, options).unwrap();
+        html
+    }
+}
+
+/// Write a tag holding just escaped text, like `Html::new_simple` does.
+pub fn write_simple<W: fmt::Write>(w: &mut W, name: &str, contents: &str) -> fmt::Result {
+    write!(w, "<{0}>{1}</{0}>", Escape(name), Escape(contents))
+}
+
+/// Write a `data-sourcepos` attribute for `span`, with a space before it,
+/// if `options.sourcepos` is set.
+pub fn write_sourcepos<W: fmt::Write>(w: &mut W, span: &Span, options: &HtmlOptions)
+                                      -> fmt::Result {
+    if options.sourcepos {
+        try!(write!(w, " data-sourcepos=\"{}\"", span));
+    }
+    Ok(())
+}
+
+/// Adapts an `io::Write` into a `fmt::Write`, keeping hold of the I/O
+/// error (which `fmt::Error` can't carry) if writing fails.
+pub struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    pub fn new(inner: W) -> IoWriter<W> {
+        IoWriter {
+            inner: inner,
+            error: None,
+        }
+    }
+
+    /// Turn the result of a `write_html` call into the I/O error
+    /// behind it, if there was one.
+    pub fn result(&mut self, result: fmt::Result) -> io::Result<()> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => Err(self.error.take().unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::Other, "formatting error")
+            })),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.inner.write_all(s.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.error = Some(e);
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+/// Stream the HTML for `value` to `writer`.
+pub fn write_html_to<T: WriteHtml, W: io::Write>(value: &T, writer: W, options: &HtmlOptions)
+                                                -> io::Result<()> {
+    let mut w = IoWriter::new(writer);
+    let result = value.write_html(&mut w, options);
+    w.result(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::io;
+
+    use html::HtmlOptions;
+    use super::{WriteHtml, write_html_to, write_simple};
+
+    struct Simple;
+
+    impl WriteHtml for Simple {
+        fn write_html<W: fmt::Write>(&self, w: &mut W, _: &HtmlOptions) -> fmt::Result {
+            write_simple(w, "p", "<hi>")
+        }
+    }
+
+    struct Failing;
+
+    impl io::Write for Failing {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_to_io() {
+        let mut out = vec![];
+        write_html_to(&Simple, &mut out, &HtmlOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "<p>&lt;hi&gt;</p>");
+    }
+
+    #[test]
+    fn test_io_errors_are_kept() {
+        let err = write_html_to(&Simple, Failing, &HtmlOptions::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
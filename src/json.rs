@@ -0,0 +1,573 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The parsed document as JSON, for tools outside of Rust.
+//!
+//! The document is an object with the schema's name and version, the
+//! front matter and the blocks of the body:
+//!
+//! ```text
+//! {"schema": "rustdown-ast", "version": 1, "metadata": {...}, "blocks": [...]}
+//! ```
+//!
+//! Every node is an object with a `type` and, optionally, a `span` of
+//! source (`{"start": POS, "end": POS}`, where a position is
+//! `{"line": L, "column": C, "offset": O}`). The node types are:
+//!
+//! * `heading`, with a `depth` and inline `children`
+//! * `paragraph`, with inline `children`
+//! * `list`, with `ordered`, a `start` number (null if unordered) and
+//!   `items`, which are `item` nodes with inline `children`, or nested
+//!   `list` nodes
+//! * `rule`
+//! * `code_block`, with its `info` string and `code`
+//! * `html`, with the block's `html`
+//! * `blockquote`, with the `blocks` quoted in it
+//! * `table`, with the `align` of each column (`none`, `left`, `center`
+//!   or `right`), a `head` row and the `rows` of its body. Rows are
+//!   `row` nodes with `cells`, which are `cell` nodes with inline
+//!   `children`
+//!
+//! Inline nodes are `text`, `emphasis`, `strong` and `code`, each with
+//! its `text`; `link`, with `text` and `url`; `image`, with `alt` and
+//! `src`; and `softbreak` and `hardbreak`.
+//!
+//! Anything that changes the meaning of an existing field bumps the
+//! version. Documents with a different version are rejected rather than
+//! misread.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use rustc_serialize::json::{Json, ToJson};
+
+use metadata;
+use metadata::{Metadata, MetaValue};
+use span::{Position, Span};
+use types::{Document, MarkdownStructure, Heading, Paragraph, BulletList, Bullet, CodeBlock};
+use types::BlockQuote;
+use types::{Table, TableRow, TableCell};
+use parser::Alignment;
+use types::HtmlBlock;
+use types::bulletlist::BulletElement;
+use types::inline::Contents;
+
+pub const SCHEMA: &'static str = "rustdown-ast";
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// A JSON document that isn't a valid AST.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct JsonError {
+    pub message: String,
+}
+
+impl JsonError {
+    fn new(message: String) -> JsonError {
+        JsonError {
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid JSON AST: {}", self.message)
+    }
+}
+
+impl Error for JsonError {
+    fn description(&self) -> &str {
+        "invalid JSON AST"
+    }
+}
+
+/// Build a node object out of its type and fields.
+fn node(kind: &str, span: Option<Span>, fields: Vec<(&str, Json)>) -> Json {
+    let mut object = BTreeMap::new();
+    object.insert("type".to_string(), kind.to_json());
+    if let Some(span) = span {
+        object.insert("span".to_string(), span.to_json());
+    }
+    for (key, value) in fields.into_iter() {
+        object.insert(key.to_string(), value);
+    }
+    Json::Object(object)
+}
+
+impl ToJson for Position {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("line".to_string(), self.line.to_json());
+        object.insert("column".to_string(), self.column.to_json());
+        object.insert("offset".to_string(), self.offset.to_json());
+        Json::Object(object)
+    }
+}
+
+impl ToJson for Span {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("start".to_string(), self.start.to_json());
+        object.insert("end".to_string(), self.end.to_json());
+        Json::Object(object)
+    }
+}
+
+impl ToJson for MetaValue {
+    fn to_json(&self) -> Json {
+        match self {
+            &MetaValue::String(ref s) | &MetaValue::Date(ref s) => s.to_json(),
+            &MetaValue::Integer(i) => i.to_json(),
+            &MetaValue::Float(f) => f.to_json(),
+            &MetaValue::Boolean(b) => b.to_json(),
+            &MetaValue::List(ref list) => Json::Array(list.iter().map(|v| v.to_json()).collect()),
+            &MetaValue::Map(ref map) => metadata_json(map),
+            &MetaValue::Null => Json::Null,
+        }
+    }
+}
+
+fn metadata_json(metadata: &Metadata) -> Json {
+    Json::Object(metadata.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+}
+
+/// The inline spans of the chain, as an array.
+impl ToJson for Contents {
+    fn to_json(&self) -> Json {
+        Json::Array(self.spans().map(|inline| {
+            let span = inline.span();
+            match inline {
+                &Contents::Normal(ref s, _, _) => node("text", span, vec![("text", s.to_json())]),
+                &Contents::Italic(ref s, _, _) => {
+                    node("emphasis", span, vec![("text", s.to_json())])
+                }
+                &Contents::Bold(ref s, _, _) => node("strong", span, vec![("text", s.to_json())]),
+                &Contents::Code(ref s, _, _) => node("code", span, vec![("text", s.to_json())]),
+                &Contents::Link(ref s, ref url, ref title, _, _) => {
+                    let mut fields = vec![("text", s.to_json()), ("url", url.to_json())];
+                    if let &Some(ref title) = title {
+                        fields.push(("title", title.to_json()));
+                    }
+                    node("link", span, fields)
+                }
+                &Contents::Image(ref alt, ref src, _, _) => {
+                    node("image", span, vec![("alt", alt.to_json()), ("src", src.to_json())])
+                }
+                &Contents::SoftBreak(..) => node("softbreak", span, vec![]),
+                &Contents::HardBreak(..) => node("hardbreak", span, vec![]),
+                &Contents::Nil => Json::Null,
+            }
+        }).collect())
+    }
+}
+
+impl ToJson for Heading {
+    fn to_json(&self) -> Json {
+        node("heading", Some(self.span()),
+             vec![("depth", self.depth().to_json()), ("children", self.contents().to_json())])
+    }
+}
+
+impl ToJson for Paragraph {
+    fn to_json(&self) -> Json {
+        node("paragraph", Some(self.span()), vec![("children", self.contents().to_json())])
+    }
+}
+
+impl ToJson for Bullet {
+    fn to_json(&self) -> Json {
+        node("item", Some(self.span()), vec![("children", self.contents().to_json())])
+    }
+}
+
+impl ToJson for BulletList {
+    fn to_json(&self) -> Json {
+        let items = self.elements().iter().map(|element| {
+            match element {
+                &BulletElement::Nested(ref list) => list.to_json(),
+                &BulletElement::Lone(ref bullet) => bullet.to_json(),
+            }
+        }).collect();
+        node("list", Some(self.span()),
+             vec![("ordered", self.is_ordered().to_json()),
+                  ("start", self.start().to_json()),
+                  ("items", Json::Array(items))])
+    }
+}
+
+impl ToJson for TableRow {
+    fn to_json(&self) -> Json {
+        let cells = self.cells().iter().map(|cell| {
+            node("cell", Some(cell.span()), vec![("children", cell.contents().to_json())])
+        }).collect();
+        node("row", Some(self.span()), vec![("cells", Json::Array(cells))])
+    }
+}
+
+impl ToJson for Table {
+    fn to_json(&self) -> Json {
+        let align = self.alignments().iter().map(|alignment| {
+            match alignment {
+                &Alignment::None => "none",
+                &Alignment::Left => "left",
+                &Alignment::Center => "center",
+                &Alignment::Right => "right",
+            }.to_json()
+        }).collect();
+        node("table", Some(self.span()),
+             vec![("align", Json::Array(align)),
+                  ("head", self.head().to_json()),
+                  ("rows", Json::Array(self.rows().iter().map(|r| r.to_json()).collect()))])
+    }
+}
+
+impl ToJson for MarkdownStructure {
+    fn to_json(&self) -> Json {
+        match self {
+            &MarkdownStructure::MDH(ref heading) => heading.to_json(),
+            &MarkdownStructure::MDP(ref paragraph) => paragraph.to_json(),
+            &MarkdownStructure::MDB(ref list) => list.to_json(),
+            &MarkdownStructure::MDR(span) => node("rule", Some(span), vec![]),
+            &MarkdownStructure::MDC(ref code) => {
+                node("code_block", Some(code.span()),
+                     vec![("info", code.info().to_json()), ("code", code.code().to_json())])
+            }
+            &MarkdownStructure::MDX(ref html) => {
+                node("html", Some(html.span()), vec![("html", html.html().to_json())])
+            }
+            &MarkdownStructure::MDQ(ref quote) => {
+                let blocks = quote.blocks().iter().map(|b| b.to_json()).collect();
+                node("blockquote", Some(quote.span()), vec![("blocks", Json::Array(blocks))])
+            }
+            &MarkdownStructure::MDT(ref table) => table.to_json(),
+        }
+    }
+}
+
+impl ToJson for Document {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("schema".to_string(), SCHEMA.to_json());
+        object.insert("version".to_string(), SCHEMA_VERSION.to_json());
+        object.insert("metadata".to_string(), metadata_json(self.metadata()));
+        object.insert("blocks".to_string(),
+                      Json::Array(self.blocks().iter().map(|b| b.to_json()).collect()));
+        Json::Object(object)
+    }
+}
+
+/// Read a document back from its JSON text.
+pub fn parse_document(source: &str) -> Result<Document, JsonError> {
+    let json = try!(Json::from_str(source).map_err(|e| JsonError::new(format!("{}", e))));
+    from_json(&json)
+}
+
+/// Read a document back from its JSON form.
+pub fn from_json(json: &Json) -> Result<Document, JsonError> {
+    if try!(string_field(json, "schema")) != SCHEMA {
+        return Err(JsonError::new(format!("expected a `{}` document", SCHEMA)));
+    }
+    let version = try!(u64_field(json, "version"));
+    if version != SCHEMA_VERSION {
+        return Err(JsonError::new(format!("unsupported schema version {} (expected {})",
+                                          version, SCHEMA_VERSION)));
+    }
+    let metadata = match json.find("metadata") {
+        Some(&Json::Object(ref object)) => {
+            object.iter().map(|(k, v)| (k.clone(), meta_from_json(v))).collect()
+        }
+        None | Some(&Json::Null) => Metadata::new(),
+        Some(_) => return Err(JsonError::new("`metadata` should be an object".to_string())),
+    };
+    let mut blocks = vec![];
+    for block in try!(array_field(json, "blocks")).iter() {
+        blocks.push(try!(block_from_json(block)));
+    }
+    Ok(Document::with_metadata(blocks, metadata))
+}
+
+fn meta_from_json(json: &Json) -> MetaValue {
+    match json {
+        &Json::String(ref s) if metadata::is_date(s) => MetaValue::Date(s.clone()),
+        &Json::String(ref s) => MetaValue::String(s.clone()),
+        &Json::I64(i) => MetaValue::Integer(i),
+        &Json::U64(u) if u <= i64::max_value() as u64 => MetaValue::Integer(u as i64),
+        &Json::U64(u) => MetaValue::Float(u as f64),
+        &Json::F64(f) => MetaValue::Float(f),
+        &Json::Boolean(b) => MetaValue::Boolean(b),
+        &Json::Array(ref list) => MetaValue::List(list.iter().map(meta_from_json).collect()),
+        &Json::Object(ref object) => {
+            MetaValue::Map(object.iter().map(|(k, v)| (k.clone(), meta_from_json(v))).collect())
+        }
+        &Json::Null => MetaValue::Null,
+    }
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, JsonError> {
+    json.find(key).ok_or_else(|| JsonError::new(format!("missing field `{}`", key)))
+}
+
+fn string_field(json: &Json, key: &str) -> Result<String, JsonError> {
+    try!(field(json, key)).as_string().map(|s| s.to_string()).ok_or_else(|| {
+        JsonError::new(format!("`{}` should be a string", key))
+    })
+}
+
+fn u64_field(json: &Json, key: &str) -> Result<u64, JsonError> {
+    try!(field(json, key)).as_u64().ok_or_else(|| {
+        JsonError::new(format!("`{}` should be a non-negative integer", key))
+    })
+}
+
+fn array_field<'a>(json: &'a Json, key: &str) -> Result<&'a Vec<Json>, JsonError> {
+    try!(field(json, key)).as_array().ok_or_else(|| {
+        JsonError::new(format!("`{}` should be an array", key))
+    })
+}
+
+fn position_from_json(json: &Json) -> Result<Position, JsonError> {
+    Ok(Position::new(try!(u64_field(json, "line")) as usize,
+                     try!(u64_field(json, "column")) as usize,
+                     try!(u64_field(json, "offset")) as usize))
+}
+
+/// A node's span. Nodes without one, such as those written by hand, get
+/// an empty span.
+fn span_from_json(json: &Json) -> Result<Span, JsonError> {
+    match json.find("span") {
+        Some(span) => Ok(Span::new(try!(position_from_json(try!(field(span, "start")))),
+                                   try!(position_from_json(try!(field(span, "end")))))),
+        None => Ok(Span::default()),
+    }
+}
+
+fn block_from_json(json: &Json) -> Result<MarkdownStructure, JsonError> {
+    let span = try!(span_from_json(json));
+    match &try!(string_field(json, "type"))[..] {
+        "heading" => {
+            let depth = try!(u64_field(json, "depth"));
+            if depth < 1 || depth > 6 {
+                return Err(JsonError::new(format!("invalid heading depth {}", depth)));
+            }
+            let mut heading = Heading::with_contents(try!(children_from_json(json)),
+                                                     depth as u32);
+            heading.set_span(span);
+            Ok(MarkdownStructure::MDH(heading))
+        }
+        "paragraph" => {
+            let mut paragraph = Paragraph::with_contents(try!(children_from_json(json)));
+            paragraph.set_span(span);
+            Ok(MarkdownStructure::MDP(paragraph))
+        }
+        "list" => Ok(MarkdownStructure::MDB(try!(list_from_json(json)))),
+        "rule" => Ok(MarkdownStructure::MDR(span)),
+        "code_block" => {
+            let mut code = CodeBlock::new(try!(string_field(json, "info")),
+                                          try!(string_field(json, "code")));
+            code.set_span(span);
+            Ok(MarkdownStructure::MDC(code))
+        }
+        "html" => {
+            let mut html = HtmlBlock::new(try!(string_field(json, "html")));
+            html.set_span(span);
+            Ok(MarkdownStructure::MDX(html))
+        }
+        "blockquote" => {
+            let mut blocks = vec![];
+            for block in try!(array_field(json, "blocks")).iter() {
+                blocks.push(try!(block_from_json(block)));
+            }
+            let mut quote = BlockQuote::new(blocks);
+            quote.set_span(span);
+            Ok(MarkdownStructure::MDQ(quote))
+        }
+        "table" => Ok(MarkdownStructure::MDT(try!(table_from_json(json)))),
+        other => Err(JsonError::new(format!("unknown block type `{}`", other))),
+    }
+}
+
+fn table_from_json(json: &Json) -> Result<Table, JsonError> {
+    let mut alignments = vec![];
+    for align in try!(array_field(json, "align")).iter() {
+        alignments.push(match align.as_string() {
+            Some("none") => Alignment::None,
+            Some("left") => Alignment::Left,
+            Some("center") => Alignment::Center,
+            Some("right") => Alignment::Right,
+            _ => return Err(JsonError::new(format!("unknown column alignment {}", align))),
+        });
+    }
+    let mut table = Table::new(alignments, try!(row_from_json(try!(field(json, "head")))));
+    table.set_span(try!(span_from_json(json)));
+    for row in try!(array_field(json, "rows")).iter() {
+        table.push(try!(row_from_json(row)));
+    }
+    Ok(table)
+}
+
+fn row_from_json(json: &Json) -> Result<TableRow, JsonError> {
+    let mut cells = vec![];
+    for cell in try!(array_field(json, "cells")).iter() {
+        let mut table_cell = TableCell::with_contents(try!(children_from_json(cell)));
+        table_cell.set_span(try!(span_from_json(cell)));
+        cells.push(table_cell);
+    }
+    let mut row = TableRow::new(cells);
+    row.set_span(try!(span_from_json(json)));
+    Ok(row)
+}
+
+fn list_from_json(json: &Json) -> Result<BulletList, JsonError> {
+    let ordered = try!(field(json, "ordered")).as_boolean().unwrap_or(false);
+    let mut list = if ordered {
+        BulletList::new_ordered(json.find("start").and_then(|s| s.as_u64()).unwrap_or(1))
+    }
+    else {
+        BulletList::new_unordered()
+    };
+    list.set_span(try!(span_from_json(json)));
+    for item in try!(array_field(json, "items")).iter() {
+        match &try!(string_field(item, "type"))[..] {
+            "item" => {
+                let mut bullet = Bullet::with_contents(try!(children_from_json(item)));
+                bullet.set_span(try!(span_from_json(item)));
+                list.push(BulletElement::Lone(bullet));
+            }
+            "list" => list.push(BulletElement::Nested(try!(list_from_json(item)))),
+            other => return Err(JsonError::new(format!("unknown list item type `{}`", other))),
+        }
+    }
+    Ok(list)
+}
+
+fn children_from_json(json: &Json) -> Result<Contents, JsonError> {
+    let mut spans = vec![];
+    for child in try!(array_field(json, "children")).iter() {
+        let span = try!(span_from_json(child));
+        let nil = Box::new(Contents::Nil);
+        spans.push(match &try!(string_field(child, "type"))[..] {
+            "text" => Contents::Normal(try!(string_field(child, "text")), span, nil),
+            "emphasis" => Contents::Italic(try!(string_field(child, "text")), span, nil),
+            "strong" => Contents::Bold(try!(string_field(child, "text")), span, nil),
+            "code" => Contents::Code(try!(string_field(child, "text")), span, nil),
+            "link" => {
+                let title = child.find("title").and_then(|t| t.as_string()).map(|t| t.to_string());
+                Contents::Link(try!(string_field(child, "text")),
+                               try!(string_field(child, "url")), title, span, nil)
+            }
+            "image" => {
+                Contents::Image(try!(string_field(child, "alt")),
+                                try!(string_field(child, "src")), span, nil)
+            }
+            "softbreak" => Contents::SoftBreak(span, nil),
+            "hardbreak" => Contents::HardBreak(span, nil),
+            other => return Err(JsonError::new(format!("unknown inline type `{}`", other))),
+        });
+    }
+    Ok(Contents::from_spans(spans))
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::{Json, ToJson};
+    use testing::lines;
+
+    use super::{parse_document, SCHEMA_VERSION};
+    use convert;
+    use html::HtmlOptions;
+
+    // This is the schema downstream tools rely on. If this test has to
+    // change, so does `SCHEMA_VERSION`.
+    #[test]
+    fn test_schema_version_1() {
+        assert_eq!(SCHEMA_VERSION, 1);
+        let document = convert::parse_document(&lines(&["---", "date: 2015-03-14", "---",
+                                                        "# *A*", "", "b  ", "[c](d.md)", "",
+                                                        "2. ![e](f.png)", "", "***"]))
+                       .unwrap();
+        let expected = r#"{
+            "blocks": [
+                {"type": "heading", "depth": 1,
+                 "span": {"start": {"line": 4, "column": 1, "offset": 25},
+                          "end": {"line": 4, "column": 6, "offset": 30}},
+                 "children": [
+                     {"type": "emphasis", "text": "A",
+                      "span": {"start": {"line": 4, "column": 3, "offset": 27},
+                               "end": {"line": 4, "column": 6, "offset": 30}}}]},
+                {"type": "paragraph",
+                 "span": {"start": {"line": 6, "column": 1, "offset": 32},
+                          "end": {"line": 7, "column": 10, "offset": 45}},
+                 "children": [
+                     {"type": "text", "text": "b",
+                      "span": {"start": {"line": 6, "column": 1, "offset": 32},
+                               "end": {"line": 6, "column": 2, "offset": 33}}},
+                     {"type": "hardbreak",
+                      "span": {"start": {"line": 6, "column": 2, "offset": 33},
+                               "end": {"line": 6, "column": 4, "offset": 35}}},
+                     {"type": "link", "text": "c", "url": "d.md",
+                      "span": {"start": {"line": 7, "column": 1, "offset": 36},
+                               "end": {"line": 7, "column": 10, "offset": 45}}}]},
+                {"type": "list", "ordered": true, "start": 2,
+                 "span": {"start": {"line": 9, "column": 1, "offset": 47},
+                          "end": {"line": 9, "column": 15, "offset": 61}},
+                 "items": [
+                     {"type": "item",
+                      "span": {"start": {"line": 9, "column": 1, "offset": 47},
+                               "end": {"line": 9, "column": 15, "offset": 61}},
+                      "children": [
+                          {"type": "image", "alt": "e", "src": "f.png",
+                           "span": {"start": {"line": 9, "column": 4, "offset": 50},
+                                    "end": {"line": 9, "column": 15, "offset": 61}}}]}]},
+                {"type": "rule",
+                 "span": {"start": {"line": 11, "column": 1, "offset": 63},
+                          "end": {"line": 11, "column": 4, "offset": 66}}}],
+            "metadata": {"date": "2015-03-14"},
+            "schema": "rustdown-ast",
+            "version": 1
+        }"#;
+        assert_eq!(document.to_json(), Json::from_str(expected).unwrap());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let document = convert::parse_document(&lines(&["---", "tags: [a, b]", "n: -3", "---",
+                                                        "## A `b`", "", "- c", "  1. d", "",
+                                                        "e **f**", "[g](h \"i\")", "", "```j", "k", "```",
+                                                        "", "> <b>", "> l", "", "| m | n |",
+                                                        "|:-|--:|", "| *o* |"]))
+                       .unwrap();
+        let json = format!("{}", document.to_json());
+        let parsed = parse_document(&json).unwrap();
+        assert_eq!(parsed.metadata(), document.metadata());
+        let options = HtmlOptions::default();
+        assert_eq!(parsed.body_html(&options), document.body_html(&options));
+        assert_eq!(format!("{}", parsed.to_json()), json);
+    }
+
+    #[test]
+    fn test_hand_written() {
+        let document = parse_document(r#"{"schema": "rustdown-ast", "version": 1, "blocks": [
+            {"type": "paragraph", "children": [{"type": "text", "text": "<hi>"}]}]}"#)
+                       .unwrap();
+        assert_eq!(document.body_html(&HtmlOptions::default()), "<p>&lt;hi&gt;</p>");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse_document("[").is_err());
+        assert!(parse_document(r#"{"schema": "rustdown-ast", "version": 2, "blocks": []}"#)
+                .is_err());
+        assert!(parse_document(r#"{"schema": "other", "version": 1, "blocks": []}"#).is_err());
+        let error = parse_document(r#"{"schema": "rustdown-ast", "version": 1,
+                                       "blocks": [{"type": "figure"}]}"#).unwrap_err();
+        assert_eq!(error.message, "unknown block type `figure`");
+    }
+}
@@ -0,0 +1,444 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rendering documents as LaTeX.
+//!
+//! Headings become `\section`, `\subsection` and so on down to
+//! `\subparagraph`, labelled with their anchor names so that links to
+//! those anchors become `\hyperref`s. Lists are `itemize` or `enumerate`,
+//! code spans `\texttt`, tables `tabular`, links `\href` and images
+//! `\includegraphics`, which need the `hyperref` and `graphicx`
+//! packages. A standalone document has a preamble loading them, and a
+//! title from the document's title, `author` and `date`. A title taken
+//! from the first heading takes that heading's place.
+
+use types::{Document, MarkdownStructure, Heading, Paragraph, BulletList, Bullet, CodeBlock};
+use types::BlockQuote;
+use types::{Table, TableRow};
+use parser::Alignment;
+use types::bulletlist::BulletElement;
+use types::inline::Contents;
+
+/// Options for rendering LaTeX.
+///
+#[derive(Clone, Debug, Default)]
+pub struct LatexOptions {
+    /// Whether to write a complete document, rather than just its body.
+    pub standalone: bool,
+    /// The document title. Defaults to `Document::title`.
+    pub title: Option<String>,
+}
+
+pub trait ToLatex {
+    /// Write the LaTeX, with links to any of the heading `labels` as
+    /// `\hyperref`s.
+    fn write_latex(&self, out: &mut String, labels: &[String]);
+
+    fn to_latex(&self) -> String {
+        let mut out = String::new();
+        self.write_latex(&mut out, &[]);
+        out
+    }
+}
+
+/// Render `document`, with a preamble if `options.standalone` is set.
+pub fn render(document: &Document, options: &LatexOptions) -> String {
+    if !options.standalone {
+        return document.to_latex();
+    }
+    let mut out = String::new();
+    out.push_str("\\documentclass{article}\n\
+                  \\usepackage[utf8]{inputenc}\n\
+                  \\usepackage[T1]{fontenc}\n\
+                  \\usepackage{graphicx}\n\
+                  \\usepackage{hyperref}\n\n");
+    let title = options.title.clone().or(document.title());
+    if let Some(ref title) = title {
+        out.push_str(&format!("\\title{{{}}}\n", escape(title)));
+        for &(key, command) in [("author", "author"), ("date", "date")].iter() {
+            let value = document.meta(key).map_or(String::new(), |value| format!("{}", value));
+            out.push_str(&format!("\\{}{{{}}}\n", command, escape(&value)));
+        }
+        out.push('\n');
+    }
+    out.push_str("\\begin{document}\n\n");
+    // A title taken from the first heading replaces it, rather than
+    // being set twice.
+    let title_heading = options.title.is_none() &&
+        document.meta("title").and_then(|title| title.as_str()).is_none();
+    let ids = document.heading_ids();
+    if title.is_some() {
+        out.push_str("\\maketitle");
+        if let (true, Some(id)) = (title_heading, ids.first()) {
+            out.push_str(&format!("\\label{{{}}}", id));
+        }
+        out.push_str("\n\n");
+    }
+    write_document(document, &mut out, title.is_some() && title_heading);
+    out.push_str("\n\\end{document}\n");
+    out
+}
+
+/// Writes the blocks separated by blank lines, with a final newline, and
+/// labels the headings with their anchor names. HTML blocks are left out.
+impl ToLatex for Document {
+    fn write_latex(&self, out: &mut String, _labels: &[String]) {
+        write_document(self, out, false)
+    }
+}
+
+/// Write the blocks of `document`, leaving out its first heading if
+/// `skip_title` is set.
+fn write_document(document: &Document, out: &mut String, skip_title: bool) {
+    let ids = document.heading_ids();
+    let mut headings = ids.iter();
+    let mut skip_title = skip_title;
+    let mut written = 0;
+    for block in document.blocks().iter().filter(|block| !block.is_html()) {
+        let id = match block {
+            &MarkdownStructure::MDH(_) => headings.next(),
+            _ => None,
+        };
+        if id.is_some() && skip_title {
+            skip_title = false;
+            continue;
+        }
+        if written > 0 {
+            out.push_str("\n\n");
+        }
+        block.write_latex(out, &ids);
+        if let Some(id) = id {
+            out.push_str(&format!("\\label{{{}}}", id));
+        }
+        written += 1;
+    }
+    if written > 0 {
+        out.push('\n');
+    }
+}
+
+impl ToLatex for MarkdownStructure {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        match self {
+            &MarkdownStructure::MDH(ref heading) => heading.write_latex(out, labels),
+            &MarkdownStructure::MDP(ref paragraph) => paragraph.write_latex(out, labels),
+            &MarkdownStructure::MDB(ref list) => list.write_latex(out, labels),
+            &MarkdownStructure::MDR(_) => out.push_str("\\noindent\\rule{\\linewidth}{0.4pt}"),
+            &MarkdownStructure::MDC(ref code) => code.write_latex(out, labels),
+            &MarkdownStructure::MDX(_) => {}
+            &MarkdownStructure::MDQ(ref quote) => quote.write_latex(out, labels),
+            &MarkdownStructure::MDT(ref table) => table.write_latex(out, labels),
+        }
+    }
+}
+
+impl ToLatex for BlockQuote {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        out.push_str("\\begin{quote}\n");
+        for block in self.blocks().iter().filter(|block| !block.is_html()) {
+            block.write_latex(out, labels);
+            out.push_str("\n\n");
+        }
+        out.push_str("\\end{quote}");
+    }
+}
+
+/// The head is set in bold, and ruled off from the body.
+impl ToLatex for Table {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        let columns: String = self.alignments().iter().map(|alignment| {
+            match *alignment {
+                Alignment::Center => 'c',
+                Alignment::Right => 'r',
+                Alignment::Left | Alignment::None => 'l',
+            }
+        }).collect();
+        out.push_str(&format!("\\begin{{tabular}}{{{}}}\n\\hline\n", columns));
+        write_row(self.head(), out, true, labels);
+        out.push_str("\\hline\n");
+        for row in self.rows().iter() {
+            write_row(row, out, false, labels);
+        }
+        if !self.rows().is_empty() {
+            out.push_str("\\hline\n");
+        }
+        out.push_str("\\end{tabular}");
+    }
+}
+
+fn write_row(row: &TableRow, out: &mut String, bold: bool, labels: &[String]) {
+    for (i, cell) in row.cells().iter().enumerate() {
+        if i > 0 {
+            out.push_str(" & ");
+        }
+        let mut text = String::new();
+        write_inline(cell.contents(), &mut text, true, labels);
+        // The first cell comes just after the last row's `\\`.
+        if i == 0 {
+            guard_bracket(&mut text, 0);
+        }
+        if bold && !text.is_empty() {
+            out.push_str(&format!("\\textbf{{{}}}", text));
+        }
+        else {
+            out.push_str(&text);
+        }
+    }
+    out.push_str(" \\\\\n");
+}
+
+/// Code is set verbatim, so nothing in it needs escaping.
+impl ToLatex for CodeBlock {
+    fn write_latex(&self, out: &mut String, _labels: &[String]) {
+        out.push_str("\\begin{verbatim}\n");
+        out.push_str(self.code());
+        out.push_str("\\end{verbatim}");
+    }
+}
+
+impl ToLatex for Heading {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        let command = match self.depth() {
+            1 => "section",
+            2 => "subsection",
+            3 => "subsubsection",
+            4 => "paragraph",
+            _ => "subparagraph",
+        };
+        out.push_str(&format!("\\{}{{", command));
+        write_inline(self.contents(), out, true, labels);
+        out.push('}');
+    }
+}
+
+impl ToLatex for Paragraph {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        write_inline(self.contents(), out, false, labels)
+    }
+}
+
+impl ToLatex for Bullet {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        write_inline(self.contents(), out, false, labels)
+    }
+}
+
+impl ToLatex for BulletList {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        write_list(self, out, 0, 0, labels)
+    }
+}
+
+impl ToLatex for Contents {
+    fn write_latex(&self, out: &mut String, labels: &[String]) {
+        write_inline(self, out, false, labels)
+    }
+}
+
+/// Write a list indented by `indent` spaces, inside `enumerated` other
+/// `enumerate`s, which decides the counter a start number is set on.
+fn write_list(list: &BulletList, out: &mut String, indent: usize, enumerated: usize,
+              labels: &[String]) {
+    let environment = if list.is_ordered() { "enumerate" } else { "itemize" };
+    let inner = " ".repeat(indent + 2);
+    out.push_str(&format!("{}\\begin{{{}}}\n", " ".repeat(indent), environment));
+    let start = list.start().unwrap_or(1);
+    if list.is_ordered() && start != 1 && enumerated < 4 {
+        let counter = ["enumi", "enumii", "enumiii", "enumiv"][enumerated];
+        out.push_str(&format!("{}\\setcounter{{{}}}{{{}}}\n", inner, counter, start - 1));
+    }
+    let enumerated = if list.is_ordered() { enumerated + 1 } else { enumerated };
+    let mut items = 0;
+    for element in list.elements().iter() {
+        match element {
+            &BulletElement::Lone(ref bullet) => {
+                out.push_str(&inner);
+                out.push_str("\\item");
+                if *bullet.contents() != Contents::Nil {
+                    out.push(' ');
+                    let start = out.len();
+                    bullet.write_latex(out, labels);
+                    guard_bracket(out, start);
+                }
+                out.push('\n');
+                items += 1;
+            }
+            &BulletElement::Nested(ref nested) => {
+                // A sublist has to be inside an item.
+                if items == 0 {
+                    out.push_str(&inner);
+                    out.push_str("\\item\n");
+                    items += 1;
+                }
+                write_list(nested, out, indent + 2, enumerated, labels);
+            }
+        }
+    }
+    out.push_str(&format!("{}\\end{{{}}}", " ".repeat(indent), environment));
+    if indent > 0 {
+        out.push('\n');
+    }
+}
+
+/// Write a chain of inline contents. With `single_line` set, line breaks
+/// are written as spaces, since they can't be used in headings. Links to
+/// `#label` use `\hyperref` when `label` is one of `labels`, and `\href`
+/// like any other link otherwise.
+fn write_inline(contents: &Contents, out: &mut String, single_line: bool, labels: &[String]) {
+    // Where the line after a hard break starts, once there's one.
+    let mut after_break = None;
+    for inline in contents.spans() {
+        let line_start = after_break.take();
+        match inline {
+            &Contents::Normal(ref s, _, _) => out.push_str(&escape(s)),
+            &Contents::Italic(ref s, _, _) => out.push_str(&format!("\\emph{{{}}}", escape(s))),
+            &Contents::Bold(ref s, _, _) => out.push_str(&format!("\\textbf{{{}}}", escape(s))),
+            &Contents::Code(ref s, _, _) => out.push_str(&format!("\\texttt{{{}}}", escape(s))),
+            &Contents::Link(ref s, ref url, _, _, _) => {
+                if url.starts_with('#') && labels.iter().any(|label| *label == url[1..]) {
+                    out.push_str(&format!("\\hyperref[{}]{{{}}}", &url[1..], escape(s)));
+                }
+                else {
+                    out.push_str(&format!("\\href{{{}}}{{{}}}", escape_url(url), escape(s)));
+                }
+            }
+            &Contents::Image(_, ref src, _, _) => {
+                out.push_str(&format!("\\includegraphics{{{}}}", escape_url(src)))
+            }
+            &Contents::SoftBreak(..) => out.push(if single_line { ' ' } else { '\n' }),
+            &Contents::HardBreak(..) if single_line => out.push(' '),
+            &Contents::HardBreak(..) => {
+                out.push_str("\\\\\n");
+                after_break = Some(out.len());
+            }
+            &Contents::Nil => {}
+        }
+        if let Some(start) = line_start {
+            guard_bracket(out, start);
+        }
+    }
+}
+
+/// Brace a `[` at `start` in `out`, which follows a command like `\item`
+/// or `\\`, so it isn't read as that command's optional argument.
+fn guard_bracket(out: &mut String, start: usize) {
+    if out[start..].starts_with('[') {
+        out.insert(start + 1, '}');
+        out.insert(start, '{');
+    }
+}
+
+/// Escape the characters that are special to LaTeX in text.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '\\' => out.push_str("\\textbackslash{}"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape a URL for `\href` or `\includegraphics`. Backslashes and braces
+/// can't be escaped there, so they're percent-encoded instead.
+fn escape_url(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            '\\' => out.push_str("\\%5C"),
+            '{' => out.push_str("\\%7B"),
+            '}' => out.push_str("\\%7D"),
+            '#' | '%' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, escape, LatexOptions, ToLatex};
+    use convert::parse_document;
+    use types::Document;
+    use testing::lines;
+
+    fn document(ls: &[&str]) -> Document {
+        let lines = lines(ls);
+        parse_document(&lines).unwrap()
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r"50% of $5 & #1_a {b} ~c^ \d"),
+                   r"50\% of \$5 \& \#1\_a \{b\} \textasciitilde{}c\textasciicircum{} \textbackslash{}d");
+    }
+
+    #[test]
+    fn test_body() {
+        let doc = document(&["# Design", "", "See *this* and `x_y` at [docs](http://a.b/c#d%20)",
+                             "or [above](#design), not [here](#nope{}).", "", "### Details",
+                             "", "3. three",
+                             "   - nested", "4. four", "", "---"]);
+        assert_eq!(doc.to_latex(),
+                   "\\section{Design}\\label{design}\n\n\
+                    See \\emph{this} and \\texttt{x\\_y} at \\href{http://a.b/c\\#d\\%20}{docs}\n\
+                    or \\hyperref[design]{above}, not \\href{\\#nope\\%7B\\%7D}{here}.\n\n\
+                    \\subsubsection{Details}\\label{details}\n\n\
+                    \\begin{enumerate}\n  \\setcounter{enumi}{2}\n  \\item three\n\
+                    \x20 \\begin{itemize}\n    \\item nested\n  \\end{itemize}\n\
+                    \x20 \\item four\n\\end{enumerate}\n\n\
+                    \\noindent\\rule{\\linewidth}{0.4pt}\n");
+    }
+
+    #[test]
+    fn test_blocks() {
+        let doc = document(&["> a", "", "x | y", "--: | :-:", "1 & 2 |", "", "```", "$", "```"]);
+        assert_eq!(doc.to_latex(),
+                   "\\begin{quote}\na\n\n\\end{quote}\n\n\
+                    \\begin{tabular}{rc}\n\\hline\n\\textbf{x} & \\textbf{y} \\\\\n\\hline\n\
+                    1 \\& 2 &  \\\\\n\\hline\n\\end{tabular}\n\n\
+                    \\begin{verbatim}\n$\n\\end{verbatim}\n");
+    }
+
+    #[test]
+    fn test_leading_brackets_are_not_arguments() {
+        assert_eq!(document(&["- [x] done", "- *[y]*"]).to_latex(),
+                   "\\begin{itemize}\n  \\item {[}x] done\n  \\item \\emph{[y]}\n\\end{itemize}\n");
+        assert_eq!(document(&["a\\", "[note] b"]).to_latex(), "a\\\\\n{[}note] b\n");
+        assert!(document(&["a | b", "- | -", "[1] | [2]"]).to_latex()
+                .contains("\\hline\n{[}1] & [2] \\\\\n"));
+        assert_eq!(document(&["[x] starts a paragraph"]).to_latex(), "[x] starts a paragraph\n");
+    }
+
+    #[test]
+    fn test_standalone() {
+        let doc = document(&["---", "author: A & B", "---", "# Plan", "", "Text."]);
+        let out = render(&doc, &LatexOptions { standalone: true, title: None });
+        assert!(out.starts_with("\\documentclass{article}\n"));
+        assert!(out.contains("\\title{Plan}\n\\author{A \\& B}\n\\date{}\n"));
+        assert!(out.contains("\\begin{document}\n\n\\maketitle\\label{plan}\n\nText.\n"));
+        assert!(!out.contains("\\section"));
+        assert!(out.ends_with("Text.\n\n\\end{document}\n"));
+        let titled = document(&["---", "title: Report", "---", "# Plan", "", "Text."]);
+        let out = render(&titled, &LatexOptions { standalone: true, title: None });
+        assert!(out.contains("\\maketitle\n\n\\section{Plan}\\label{plan}\n\nText."));
+        let untitled = render(&document(&["Text."]), &LatexOptions { standalone: true, title: None });
+        assert!(!untitled.contains("\\maketitle"));
+    }
+}
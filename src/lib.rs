@@ -0,0 +1,52 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Markdown parsing and rendering, and the `rustdown` commands built on
+//! them.
+
+#![feature(rustdoc)]
+
+extern crate rustdoc;
+extern crate getopts;
+extern crate rustc_serialize;
+extern crate url;
+extern crate toml;
+extern crate yaml_rust;
+
+pub mod build;
+pub mod commands;
+pub mod config;
+pub mod encoding;
+pub mod entities;
+pub mod feed;
+pub mod format;
+pub mod json;
+pub mod latex;
+pub mod lines;
+pub mod links;
+pub mod lint;
+pub mod markdown;
+pub mod mdfile;
+pub mod metadata;
+pub mod parser;
+pub mod plaintext;
+pub mod preprocessor;
+pub mod html;
+pub mod search;
+pub mod site;
+pub mod slug;
+pub mod span;
+pub mod standalone;
+pub mod template;
+pub mod terminal;
+#[cfg(test)]
+pub mod testing;
+pub mod text;
+pub mod types;
+pub mod convert;
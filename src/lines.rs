@@ -8,14 +8,145 @@
 // except according to those terms.
 
 use std::fs::File;
+use std::io;
 use std::io::{BufReader, BufRead};
-use std::iter::FromIterator;
 
-pub fn split_file_lines(file: File) -> Vec<String> {
+use encoding;
+use encoding::{Encoding, DecoderTrap};
+use span::{OffsetMap, SourceMap};
+
+pub fn split_file_lines(file: File, encoding: Encoding, trap: DecoderTrap)
+                        -> io::Result<(Vec<String>, SourceMap)> {
     let reader = BufReader::new(file);
-    read_to_lines(reader)
+    read_to_mapped_lines(reader, encoding, trap)
+}
+
+/// Read all of `reader`, decode it from `encoding` and split it into
+/// lines. Input that can't be decoded is reported as an
+/// `InvalidData` error wrapping a `DecodeError`, unless `trap` says to
+/// replace it.
+pub fn read_to_lines<R: BufRead>(reader: R, encoding: Encoding, trap: DecoderTrap)
+                                 -> io::Result<Vec<String>> {
+    read_to_mapped_lines(reader, encoding, trap).map(|(lines, _)| lines)
+}
+
+/// Like `read_to_lines`, but also return where each line came from in
+/// the bytes that were read.
+pub fn read_to_mapped_lines<R: BufRead>(mut reader: R, encoding: Encoding, trap: DecoderTrap)
+                                        -> io::Result<(Vec<String>, SourceMap)> {
+    let mut bytes = vec![];
+    try!(reader.read_to_end(&mut bytes));
+    let (s, decoded) = try!(encoding::decode_mapped(&bytes, encoding, trap).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }));
+    let mut map = SourceMap::new(decoded, s.len());
+    Ok((split(&s, &mut map), map))
+}
+
+/// Split `s` into lines, normalizing the input as it goes.
+///
+/// A leading byte order mark is dropped, and `\r\n`, `\r` and `\n` are
+/// all treated as line endings. NUL characters are replaced with U+FFFD
+/// REPLACEMENT CHARACTER, as CommonMark requires.
+pub fn split_lines(s: &str) -> Vec<String> {
+    split_lines_mapped(s).0
+}
+
+/// Like `split_lines`, but also return where each line came from in `s`.
+pub fn split_lines_mapped(s: &str) -> (Vec<String>, SourceMap) {
+    let mut map = SourceMap::new(OffsetMap::new(), s.len());
+    (split(s, &mut map), map)
 }
 
-pub fn read_to_lines<R: BufRead>(reader: R) -> Vec<String> {
-    FromIterator::from_iter(reader.lines().map(|e| e.unwrap()))
+fn split(s: &str, map: &mut SourceMap) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut line_map = OffsetMap::new();
+    let bom = s.len() - s.trim_left_matches('\u{feff}').len();
+    let mut start = bom;
+    let mut chars = s[bom..].char_indices().map(|(i, c)| (bom + i, c)).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\r' | '\n' => {
+                if c == '\r' && chars.peek().map(|&(_, c)| c) == Some('\n') {
+                    chars.next();
+                }
+                lines.push(line);
+                map.push_line(start, line_map);
+                line = String::new();
+                line_map = OffsetMap::new();
+                start = chars.peek().map(|&(i, _)| i).unwrap_or(s.len());
+            }
+            '\0' => {
+                line.push('\u{fffd}');
+                line_map.record(line.len(), i + 1 - start);
+            }
+            c => line.push(c),
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+        map.push_line(start, line_map);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind};
+    use testing::lines;
+
+    use encoding::{Encoding, DecoderTrap, DecodeError};
+    use super::read_to_lines;
+    use super::split_lines;
+
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(split_lines("a\nb\n"), lines(&["a", "b"]));
+        assert_eq!(split_lines("a\r\nb\r\n"), lines(&["a", "b"]));
+        assert_eq!(split_lines("a\rb\r\rc"), lines(&["a", "b", "", "c"]));
+        assert_eq!(split_lines("a\r\n\nb\n\r"), lines(&["a", "", "b", ""]));
+        assert_eq!(split_lines(""), lines(&[]));
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        assert_eq!(split_lines("\u{feff}# Heading\r\n"), lines(&["# Heading"]));
+        assert_eq!(split_lines("a\u{feff}b"), lines(&["a\u{feff}b"]));
+    }
+
+    #[test]
+    fn test_nul_is_replaced() {
+        assert_eq!(split_lines("a\0b\n\0"), lines(&["a\u{fffd}b", "\u{fffd}"]));
+    }
+
+    #[test]
+    fn test_read_to_lines() {
+        let reader = Cursor::new("\u{feff}Heading\r\n=======\r\n".as_bytes());
+        assert_eq!(read_to_lines(reader, Encoding::Utf8, DecoderTrap::Strict).unwrap(),
+                   lines(&["Heading", "======="]));
+    }
+
+    #[test]
+    fn test_read_to_lines_decodes_before_splitting() {
+        // In UTF-16, the bytes of a newline aren't a lone `\n`.
+        let reader = Cursor::new(&b"\xff\xfea\x00\n\x00\x0a\x01"[..]);
+        assert_eq!(read_to_lines(reader, Encoding::Utf8, DecoderTrap::Strict).unwrap(),
+                   lines(&["a", "\u{10a}"]));
+        let reader = Cursor::new(&b"caf\xe9\r\n"[..]);
+        assert_eq!(read_to_lines(reader, Encoding::Latin1, DecoderTrap::Strict).unwrap(),
+                   lines(&["caf\u{e9}"]));
+    }
+
+    #[test]
+    fn test_read_to_lines_invalid_input() {
+        let reader = Cursor::new(&b"caf\xe9\n"[..]);
+        let err = read_to_lines(reader, Encoding::Utf8, DecoderTrap::Strict).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let decode_error = err.get_ref().unwrap().downcast_ref::<DecodeError>().unwrap();
+        assert_eq!(decode_error.offset, 3);
+        let reader = Cursor::new(&b"caf\xe9\n"[..]);
+        assert_eq!(read_to_lines(reader, Encoding::Utf8, DecoderTrap::Replace).unwrap(),
+                   lines(&["caf\u{fffd}"]));
+    }
 }
@@ -0,0 +1,343 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Links between documents.
+//!
+//! Links with a scheme, like `https:` or `mailto:`, are external and are
+//! never followed. Anything else is a path to a local file, relative to
+//! the linking document or, if it starts with `/`, to the root of the
+//! site, with an optional `#fragment` naming a heading's anchor.
+//!
+//! `LinkChecker` checks that local links go somewhere, and `RewriteLinks`
+//! points them at the HTML files a tree of documents is converted to.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use url::{Url, UrlParser, ParseError};
+use url::percent_encoding::lossy_utf8_percent_decode;
+
+use convert;
+use lines;
+use mdfile;
+use preprocessor;
+use span::Span;
+use types::Document;
+use types::inline::Contents;
+use types::visit::{Visitor, VisitorMut};
+
+/// Where a link goes.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Target {
+    /// A URL with a scheme, or a protocol-relative `//host/path`.
+    External,
+    /// The decoded path and fragment of a local link. An empty path is
+    /// the linking document itself.
+    Local(String, Option<String>),
+}
+
+pub fn target(url: &str) -> Target {
+    if url.starts_with("//") {
+        return Target::External;
+    }
+    match Url::parse(url) {
+        Err(ParseError::RelativeUrlWithoutBase) => {}
+        _ => return Target::External,
+    }
+    let (url, fragment) = match url.find('#') {
+        Some(i) => (&url[..i], Some(decode(&url[i + 1..]))),
+        None => (url, None),
+    };
+    let path = match url.find('?') {
+        Some(i) => &url[..i],
+        None => url,
+    };
+    Target::Local(decode(path), fragment)
+}
+
+fn decode(s: &str) -> String {
+    lossy_utf8_percent_decode(s.as_bytes())
+}
+
+/// Whether `path` names a markdown file, whose headings can be linked to.
+pub fn is_markdown(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("md") | Some("markdown") => true,
+        _ => false,
+    }
+}
+
+/// The destination of a link or image, and where it is in the source.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Link {
+    pub url: String,
+    pub span: Span,
+}
+
+struct LinkCollector {
+    links: Vec<Link>,
+}
+
+impl Visitor for LinkCollector {
+    fn visit_inline(&mut self, inline: &Contents) {
+        match inline {
+            &Contents::Link(_, ref url, _, span, _) | &Contents::Image(_, ref url, span, _) => {
+                self.links.push(Link { url: url.clone(), span: span })
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Every link and image in `document`, in order.
+pub fn document_links(document: &Document) -> Vec<Link> {
+    let mut collector = LinkCollector { links: vec![] };
+    collector.visit_document(document);
+    collector.links
+}
+
+/// What was found checking a document's links.
+///
+#[derive(Clone, Debug, Default)]
+pub struct LinkReport {
+    /// Local links that don't go anywhere, with the reason why.
+    pub broken: Vec<(Link, String)>,
+    /// External links, which are left unchecked.
+    pub external: Vec<Link>,
+}
+
+/// Checks local links against the filesystem, without going online.
+pub struct LinkChecker {
+    root: PathBuf,
+    /// The heading anchors of each markdown file linked to so far.
+    anchors: HashMap<PathBuf, Result<Vec<String>, String>>,
+}
+
+impl LinkChecker {
+    /// A checker that resolves links starting with `/` from `root`.
+    pub fn new(root: &Path) -> LinkChecker {
+        LinkChecker {
+            root: root.to_path_buf(),
+            anchors: HashMap::new(),
+        }
+    }
+
+    /// Check the links in `document`, which was read from `path`.
+    pub fn check(&mut self, path: &Path, document: &Document) -> LinkReport {
+        let mut report = LinkReport::default();
+        let own_anchors = document.heading_ids();
+        for link in document_links(document).into_iter() {
+            let (file, fragment) = match target(&link.url) {
+                Target::External => {
+                    report.external.push(link);
+                    continue;
+                }
+                Target::Local(file, fragment) => (file, fragment),
+            };
+            let problem = if file.is_empty() {
+                fragment.and_then(|f| missing_anchor(&own_anchors, &f))
+            }
+            else {
+                self.check_local(path, &file, fragment)
+            };
+            if let Some(problem) = problem {
+                report.broken.push((link, problem));
+            }
+        }
+        report
+    }
+
+    fn check_local(&mut self, from: &Path, file: &str, fragment: Option<String>)
+                   -> Option<String> {
+        let resolved = if file.starts_with('/') {
+            self.root.join(&file[1..])
+        }
+        else {
+            from.parent().unwrap_or(Path::new("")).join(file)
+        };
+        if !resolved.exists() {
+            return Some(format!("{} doesn't exist", resolved.display()));
+        }
+        match fragment {
+            Some(ref fragment) if resolved.is_file() && is_markdown(&resolved) => {
+                match self.anchors_of(&resolved) {
+                    Ok(anchors) => missing_anchor(anchors, fragment),
+                    Err(e) => Some(e.clone()),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn anchors_of(&mut self, path: &Path) -> &Result<Vec<String>, String> {
+        self.anchors.entry(path.to_path_buf()).or_insert_with(|| {
+            let source = try!(mdfile::read_text_file(path).map_err(|e| {
+                format!("couldn't read {}: {}", path.display(), e)
+            }));
+            let lines = preprocessor::replace_tabs(&lines::split_lines(&source));
+            let document = try!(convert::parse_document(&lines).map_err(|e| {
+                format!("couldn't parse {}: {}", path.display(), e)
+            }));
+            Ok(document.heading_ids())
+        })
+    }
+}
+
+/// Rewrites local links to fit a converted tree of documents, so that
+/// `[guide](guide.md#setup)` links to `guide.html#setup`. External links
+/// are left alone.
+pub struct RewriteLinks {
+    /// Pairs of extensions, without the `.`, to replace the first of with
+    /// the second.
+    pub extensions: Vec<(String, String)>,
+    /// The URL the document will have. If it's set, local links are made
+    /// absolute by resolving them against it.
+    pub base_url: Option<Url>,
+}
+
+impl RewriteLinks {
+    /// Rewrites links to `.md` and `.markdown` files to `.html`.
+    pub fn new() -> RewriteLinks {
+        RewriteLinks {
+            extensions: vec![("md".to_string(), "html".to_string()),
+                             ("markdown".to_string(), "html".to_string())],
+            base_url: None,
+        }
+    }
+
+    pub fn rewrite(&self, url: &str) -> String {
+        let path = match target(url) {
+            Target::External => return url.to_string(),
+            Target::Local(ref path, _) if path.is_empty() => return url.to_string(),
+            Target::Local(..) => &url[..url.find(|c| c == '?' || c == '#').unwrap_or(url.len())],
+        };
+        let rest = &url[path.len()..];
+        let name_start = path.rfind('/').map_or(0, |i| i + 1);
+        let mut rewritten = path.to_string();
+        if let Some(dot) = path[name_start..].rfind('.') {
+            let extension = &path[name_start + dot + 1..];
+            let mapped = self.extensions.iter().find(|&&(ref from, _)| {
+                from.to_lowercase() == extension.to_lowercase()
+            });
+            if let Some(&(_, ref to)) = mapped {
+                rewritten = format!("{}.{}", &path[..name_start + dot], to);
+            }
+        }
+        rewritten.push_str(rest);
+        match self.base_url {
+            Some(ref base) => {
+                match UrlParser::new().base_url(base).parse(&rewritten) {
+                    Ok(url) => url.serialize(),
+                    Err(_) => rewritten,
+                }
+            }
+            None => rewritten,
+        }
+    }
+}
+
+impl VisitorMut for RewriteLinks {
+    fn visit_inline_mut(&mut self, inline: &mut Contents) {
+        if let &mut Contents::Link(_, ref mut url, _, _, _) = inline {
+            *url = self.rewrite(url);
+        }
+    }
+}
+
+fn missing_anchor(anchors: &[String], fragment: &str) -> Option<String> {
+    if fragment.is_empty() || anchors.iter().any(|a| a == fragment) {
+        None
+    }
+    else {
+        Some(format!("no heading with the anchor `{}`", fragment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use url::Url;
+
+    use convert::{parse_document, transform};
+    use html::HtmlOptions;
+    use testing::{lines, temp_dir};
+    use types::VisitorMut;
+    use super::{target, Target, LinkChecker, RewriteLinks};
+
+    fn local(path: &str, fragment: Option<&str>) -> Target {
+        Target::Local(path.to_string(), fragment.map(|f| f.to_string()))
+    }
+
+    #[test]
+    fn test_targets() {
+        assert_eq!(target("https://example.com/a.md"), Target::External);
+        assert_eq!(target("mailto:someone@example.com"), Target::External);
+        assert_eq!(target("//example.com/a.md"), Target::External);
+        assert_eq!(target("guide.md#setup"), local("guide.md", Some("setup")));
+        assert_eq!(target("../a%20b.md?x=1"), local("../a b.md", None));
+        assert_eq!(target("#caf%C3%A9"), local("", Some("café")));
+        assert_eq!(target("/img/logo.png"), local("/img/logo.png", None));
+    }
+
+    #[test]
+    fn test_check_links() {
+        let dir = temp_dir("links-test");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::File::create(dir.join("sub/guide.md")).unwrap()
+            .write_all(b"# Guide\n\n## Setup\n").unwrap();
+        fs::File::create(dir.join("logo.png")).unwrap();
+        let lines = lines(&[
+            "# Index",
+            "",
+            "[a](sub/guide.md#setup) [b](sub/guide.md#missing) [c](nope.md)",
+            "",
+            "![d](/logo.png) [e](#index) [f](#gone) [g](https://example.com) [h](sub/)",
+        ]);
+        let document = parse_document(&lines).unwrap();
+        let report = LinkChecker::new(&dir).check(&dir.join("index.md"), &document);
+        let broken: Vec<(&str, usize)> = report.broken.iter().map(|&(ref link, _)| {
+            (&link.url[..], link.span.start.column)
+        }).collect();
+        assert_eq!(broken, vec![("sub/guide.md#missing", 25), ("nope.md", 51), ("#gone", 29)]);
+        assert_eq!(report.external.len(), 1);
+        assert_eq!(report.broken[0].1, "no heading with the anchor `missing`");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let mut rewrite = RewriteLinks::new();
+        assert_eq!(rewrite.rewrite("guide.md#setup"), "guide.html#setup");
+        assert_eq!(rewrite.rewrite("../a.v2/README.MD?x=1"), "../a.v2/README.html?x=1");
+        assert_eq!(rewrite.rewrite("/docs/index.markdown"), "/docs/index.html");
+        assert_eq!(rewrite.rewrite("logo.png"), "logo.png");
+        assert_eq!(rewrite.rewrite("a.md/b"), "a.md/b");
+        assert_eq!(rewrite.rewrite("#setup"), "#setup");
+        assert_eq!(rewrite.rewrite("https://example.com/a.md"), "https://example.com/a.md");
+
+        rewrite.extensions = vec![("md".to_string(), "htm".to_string())];
+        rewrite.base_url = Some(Url::parse("https://example.com/docs/sub/page.htm").unwrap());
+        assert_eq!(rewrite.rewrite("../guide.md#setup"),
+                   "https://example.com/docs/guide.htm#setup");
+        assert_eq!(rewrite.rewrite("/a.md"), "https://example.com/a.htm");
+        assert_eq!(rewrite.rewrite("mailto:a@example.com"), "mailto:a@example.com");
+
+        let lines = vec!["See [the guide](guide.md#setup) and ![it](it.md)".to_string()];
+        let mut document = parse_document(&lines).unwrap();
+        let mut transforms: Vec<Box<VisitorMut>> = vec![Box::new(RewriteLinks::new())];
+        transform(&mut document, &mut transforms);
+        assert_eq!(document.body_html(&HtmlOptions::default()),
+                   "<p>See <a href=\"guide.html#setup\">the guide</a> and \
+                    <img src=\"it.md\" alt=\"it\" /></p>");
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checking markdown for mistakes and inconsistencies.
+//!
+//! A `Linter` runs a set of `Rule`s over a parsed document and its source
+//! lines, and reports a `Diagnostic` for each problem they find. The
+//! rules that come with rustdown are in `rules`, and others can be added
+//! with `Linter::add_rule`.
+//!
+//! Rules are configured in the `[lint]` table of `.rustdown.toml`. A rule
+//! set to `false` is turned off, and a rule's own table holds its
+//! settings, along with `enabled`:
+//!
+//! ```toml
+//! [lint]
+//! trailing-whitespace = false
+//!
+//! [lint.line-length]
+//! max = 100
+//! ```
+//!
+//! Rules can also be turned off for part of a file, from a comment on a
+//! line of its own until the end of the file or a matching
+//! `rustdown-enable` comment. Leaving out the rule names turns off every
+//! rule.
+//!
+//! ```markdown
+//! <!-- rustdown-disable line-length trailing-whitespace -->
+//! <!-- rustdown-enable line-length -->
+//! ```
+
+use toml;
+
+use convert;
+use metadata;
+use span::Span;
+use types::Document;
+
+pub mod report;
+pub mod rules;
+
+/// A problem found by a rule.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(rule: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            rule: rule,
+            message: message,
+            span: span,
+        }
+    }
+}
+
+/// What a rule gets to look at.
+pub struct Source<'a> {
+    /// Every line of the file, including any front matter.
+    pub lines: &'a [String],
+    /// The index in `lines` of the first line after the front matter.
+    pub body: usize,
+    pub document: &'a Document,
+}
+
+impl<'a> Source<'a> {
+    /// The lines after the front matter, with their line numbers.
+    pub fn body_lines(&self) -> Vec<(usize, &'a str)> {
+        self.lines.iter().enumerate().skip(self.body).map(|(i, l)| (i + 1, &l[..])).collect()
+    }
+}
+
+pub trait Rule {
+    /// The name used to configure and disable the rule.
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    /// Apply the settings from the rule's table in `.rustdown.toml`,
+    /// other than `enabled`. By default, a rule has no settings.
+    fn configure(&mut self, settings: &toml::Table) -> Result<(), String> {
+        match settings.keys().next() {
+            Some(key) => Err(format!("unknown setting `lint.{}.{}`", self.name(), key)),
+            None => Ok(()),
+        }
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>);
+}
+
+/// Runs rules over markdown files.
+pub struct Linter {
+    rules: Vec<Box<Rule>>,
+}
+
+impl Linter {
+    /// A linter without any rules.
+    pub fn new() -> Linter {
+        Linter {
+            rules: vec![],
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: Box<Rule>) {
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &[Box<Rule>] {
+        &self.rules
+    }
+
+    /// Configure the rules from the `[lint]` table of `.rustdown.toml`,
+    /// dropping the ones it turns off.
+    pub fn configure(&mut self, config: &toml::Table) -> Result<(), String> {
+        for name in config.keys() {
+            if !self.rules.iter().any(|rule| rule.name() == *name) {
+                return Err(format!("unknown lint rule `{}`", name));
+            }
+        }
+        let mut rules = vec![];
+        for mut rule in self.rules.drain(..) {
+            let enabled = match config.get(rule.name()) {
+                None => true,
+                Some(&toml::Value::Boolean(enabled)) => enabled,
+                Some(&toml::Value::Table(ref settings)) => {
+                    let mut settings = settings.clone();
+                    let enabled = match settings.remove("enabled") {
+                        None => true,
+                        Some(toml::Value::Boolean(enabled)) => enabled,
+                        Some(_) => {
+                            return Err(format!("`lint.{}.enabled` should be true or false",
+                                               rule.name()));
+                        }
+                    };
+                    try!(rule.configure(&settings));
+                    enabled
+                }
+                Some(_) => {
+                    return Err(format!("`lint.{}` should be true, false or a table",
+                                       rule.name()));
+                }
+            };
+            if enabled {
+                rules.push(rule);
+            }
+        }
+        self.rules = rules;
+        Ok(())
+    }
+
+    /// Check the markdown in `lines`, returning what the rules found in
+    /// source order.
+    pub fn lint(&self, lines: &[String]) -> Result<Vec<Diagnostic>, String> {
+        let (_, body) = try!(metadata::parse_front_matter(lines).map_err(|e| e.to_string()));
+        let document = try!(convert::parse_document(lines).map_err(|e| e.to_string()));
+        let source = Source {
+            lines: lines,
+            body: body,
+            document: &document,
+        };
+        let suppressions = Suppressions::new(&source);
+        let mut report = vec![];
+        for rule in self.rules.iter() {
+            rule.check(&source, &mut report);
+        }
+        report.retain(|d| !suppressions.is_disabled(d.rule, d.span.start.line));
+        report.sort_by(|a, b| {
+            (a.span.start.offset, a.rule).cmp(&(b.span.start.offset, b.rule))
+        });
+        Ok(report)
+    }
+}
+
+/// The default rules, from `rules::all`.
+impl Default for Linter {
+    fn default() -> Linter {
+        let mut linter = Linter::new();
+        for rule in rules::all().into_iter() {
+            linter.add_rule(rule);
+        }
+        linter
+    }
+}
+
+/// The `rustdown-disable` and `rustdown-enable` comments in a file.
+struct Suppressions {
+    /// The line of each comment, whether it disables or enables rules,
+    /// and which ones (all of them, if empty).
+    comments: Vec<(usize, bool, Vec<String>)>,
+}
+
+impl Suppressions {
+    fn new(source: &Source) -> Suppressions {
+        let mut comments = vec![];
+        for (number, line) in source.body_lines() {
+            let line = line.trim();
+            if !line.starts_with("<!--") || !line.ends_with("-->") || line.len() < 7 {
+                continue;
+            }
+            let mut words = line[4..line.len() - 3].split_whitespace();
+            let disable = match words.next() {
+                Some("rustdown-disable") => true,
+                Some("rustdown-enable") => false,
+                _ => continue,
+            };
+            comments.push((number, disable, words.map(|w| w.to_string()).collect()));
+        }
+        Suppressions {
+            comments: comments,
+        }
+    }
+
+    fn is_disabled(&self, rule: &str, line: usize) -> bool {
+        let mut disabled = false;
+        for &(at, disable, ref rules) in self.comments.iter() {
+            if at <= line && (rules.is_empty() || rules.iter().any(|r| r == rule)) {
+                disabled = disable;
+            }
+        }
+        disabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Linter;
+    use config::Config;
+    use convert;
+    use html::HtmlOptions;
+    use latex::ToLatex;
+    use plaintext::ToText;
+    use terminal::{ToTerminal, TerminalOptions};
+    use testing::lines;
+
+    fn rules_found(linter: &Linter, ls: &[&str]) -> Vec<(&'static str, usize)> {
+        linter.lint(&lines(ls)).unwrap().iter().map(|d| (d.rule, d.span.start.line)).collect()
+    }
+
+    #[test]
+    fn test_disable_comments() {
+        let linter = Linter::default();
+        let input = ["# A", "", "# B ", "", "<!-- rustdown-disable single-h1 -->", "",
+                     "# C ", "", "<!-- rustdown-disable -->", "", "# D ", "",
+                     "<!-- rustdown-enable -->", "", "# E"];
+        assert_eq!(rules_found(&linter, &input),
+                   vec![("single-h1", 3), ("trailing-whitespace", 3),
+                        ("trailing-whitespace", 7), ("single-h1", 15)]);
+    }
+
+    #[test]
+    fn test_disable_comments_are_not_shown() {
+        let input = lines(&["<!-- rustdown-disable line-length -->", "Text.", "",
+                            "<!-- rustdown-enable -->"]);
+        let document = convert::parse_document(&input).unwrap();
+        assert_eq!(document.body_html(&HtmlOptions::default()),
+                   "<!-- rustdown-disable line-length -->\n<p>Text.</p>\n\
+                    <!-- rustdown-enable -->");
+        assert_eq!(document.to_text(), "Text.\n");
+        let options = TerminalOptions { width: None, colour: false };
+        assert_eq!(document.to_terminal_with(&options), "Text.\n");
+        assert_eq!(document.to_latex(), "Text.\n");
+    }
+
+    #[test]
+    fn test_configure() {
+        let config = Config::parse("[lint]\ntrailing-whitespace = false\n\
+                                    [lint.line-length]\nmax = 5\n").unwrap();
+        let mut linter = Linter::default();
+        linter.configure(&config.lint).unwrap();
+        assert!(!linter.rules().iter().any(|rule| rule.name() == "trailing-whitespace"));
+        assert_eq!(rules_found(&linter, &["a b c d e f  "]), vec![("line-length", 1)]);
+
+        let config = Config::parse("[lint.line-length]\nenabled = false").unwrap();
+        let mut linter = Linter::default();
+        linter.configure(&config.lint).unwrap();
+        assert!(!linter.rules().iter().any(|rule| rule.name() == "line-length"));
+
+        for bad in ["[lint]\nno-such-rule = false", "[lint]\nline-length = 3",
+                    "[lint.line-length]\nmaximum = 5", "[lint.image-alt]\nx = 1"].iter() {
+            let config = Config::parse(bad).unwrap();
+            assert!(Linter::default().configure(&config.lint).is_err(), "{}", bad);
+        }
+    }
+}
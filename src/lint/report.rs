@@ -0,0 +1,167 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Writing out what the linter found.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use rustc_serialize::json::{Json, ToJson};
+
+use super::{Rule, Diagnostic};
+
+/// How diagnostics are reported.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum ReportFormat {
+    /// One `file:line:column: rule: message` line for each diagnostic.
+    Human,
+    /// An array of objects with the `file`, `rule`, `message` and
+    /// `span` of each diagnostic, where spans are as in `json`.
+    Json,
+    /// A SARIF 2.1.0 log, for code scanning tools.
+    Sarif,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ReportFormat, String> {
+        match s {
+            "human" => Ok(ReportFormat::Human),
+            "json" => Ok(ReportFormat::Json),
+            "sarif" => Ok(ReportFormat::Sarif),
+            _ => Err(format!("unknown report format `{}` (expected human, json or sarif)", s)),
+        }
+    }
+}
+
+/// The diagnostics for one file.
+///
+pub struct FileReport {
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub fn write_report(format: ReportFormat, reports: &[FileReport], rules: &[Box<Rule>])
+                    -> String {
+    match format {
+        ReportFormat::Human => human(reports),
+        ReportFormat::Json => format!("{}\n", json(reports).pretty()),
+        ReportFormat::Sarif => format!("{}\n", sarif(reports, rules).pretty()),
+    }
+}
+
+pub fn human(reports: &[FileReport]) -> String {
+    let mut out = String::new();
+    for report in reports.iter() {
+        for d in report.diagnostics.iter() {
+            out.push_str(&format!("{}:{}:{}: {}: {}\n", report.path, d.span.start.line,
+                                  d.span.start.column, d.rule, d.message));
+        }
+    }
+    out
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect::<BTreeMap<_, _>>())
+}
+
+pub fn json(reports: &[FileReport]) -> Json {
+    let mut diagnostics = vec![];
+    for report in reports.iter() {
+        for d in report.diagnostics.iter() {
+            diagnostics.push(object(vec![("file", report.path.to_json()),
+                                         ("rule", d.rule.to_json()),
+                                         ("message", d.message.to_json()),
+                                         ("span", d.span.to_json())]));
+        }
+    }
+    Json::Array(diagnostics)
+}
+
+/// A SARIF log with a single run, describing `rules` and giving a
+/// warning for each diagnostic.
+pub fn sarif(reports: &[FileReport], rules: &[Box<Rule>]) -> Json {
+    let descriptors = rules.iter().map(|rule| {
+        object(vec![("id", rule.name().to_json()),
+                    ("shortDescription", object(vec![("text", rule.description().to_json())]))])
+    }).collect();
+    let mut results = vec![];
+    for report in reports.iter() {
+        for d in report.diagnostics.iter() {
+            let region = object(vec![("startLine", d.span.start.line.to_json()),
+                                     ("startColumn", d.span.start.column.to_json()),
+                                     ("endLine", d.span.end.line.to_json()),
+                                     ("endColumn", d.span.end.column.to_json())]);
+            let location = object(vec![("artifactLocation",
+                                        object(vec![("uri", report.path.to_json())])),
+                                       ("region", region)]);
+            results.push(object(vec![("ruleId", d.rule.to_json()),
+                                     ("level", "warning".to_json()),
+                                     ("message", object(vec![("text", d.message.to_json())])),
+                                     ("locations", Json::Array(vec![
+                                         object(vec![("physicalLocation", location)])
+                                     ]))]));
+        }
+    }
+    let driver = object(vec![("name", "rustdown".to_json()),
+                             ("rules", Json::Array(descriptors))]);
+    object(vec![("$schema", "https://json.schemastore.org/sarif-2.1.0.json".to_json()),
+                ("version", "2.1.0".to_json()),
+                ("runs", Json::Array(vec![
+                    object(vec![("tool", object(vec![("driver", driver)])),
+                                ("results", Json::Array(results))])
+                ]))])
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::Json;
+
+    use lint::{Linter, Diagnostic};
+    use span::{Position, Span};
+    use super::{FileReport, human, json, sarif};
+
+    fn reports() -> Vec<FileReport> {
+        let span = Span::new(Position::new(2, 3, 10), Position::new(2, 5, 12));
+        vec![FileReport {
+            path: "a.md".to_string(),
+            diagnostics: vec![Diagnostic::new("image-alt", "no alt".to_string(), span)],
+        }]
+    }
+
+    #[test]
+    fn test_human() {
+        assert_eq!(human(&reports()), "a.md:2:3: image-alt: no alt\n");
+    }
+
+    #[test]
+    fn test_json() {
+        let json = json(&reports());
+        let d = &json.as_array().unwrap()[0];
+        assert_eq!(d.find("file"), Some(&Json::String("a.md".to_string())));
+        assert_eq!(d.find_path(&["span", "end", "column"]), Some(&Json::U64(5)));
+    }
+
+    #[test]
+    fn test_sarif() {
+        let linter = Linter::default();
+        let log = sarif(&reports(), linter.rules());
+        assert_eq!(log.find("version"), Some(&Json::String("2.1.0".to_string())));
+        let run = &log.find("runs").unwrap().as_array().unwrap()[0];
+        let rules = run.find_path(&["tool", "driver", "rules"]).unwrap().as_array().unwrap();
+        assert_eq!(rules.len(), linter.rules().len());
+        let result = &run.find("results").unwrap().as_array().unwrap()[0];
+        assert_eq!(result.find("ruleId"), Some(&Json::String("image-alt".to_string())));
+        let location = &result.find("locations").unwrap().as_array().unwrap()[0];
+        assert_eq!(location.find_path(&["physicalLocation", "region", "startColumn"]),
+                   Some(&Json::U64(3)));
+    }
+}
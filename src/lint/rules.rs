@@ -0,0 +1,343 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The rules that come with rustdown.
+
+use toml;
+
+use parser;
+use span::{Position, Span};
+use types::{MarkdownStructure, BulletList};
+use types::bulletlist::BulletElement;
+use types::inline::Contents;
+use types::visit::{Visitor, walk_list};
+use super::{Rule, Source, Diagnostic};
+
+/// Every rule, with its default settings.
+pub fn all() -> Vec<Box<Rule>> {
+    vec![Box::new(HeadingIncrement),
+         Box::new(SingleH1),
+         Box::new(TrailingWhitespace),
+         Box::new(MixedBullets),
+         Box::new(EmptyLink),
+         Box::new(ImageAlt),
+         Box::new(LineLength { max: 80 })]
+}
+
+/// The span of bytes `start..end` of line `number`.
+fn line_span(source: &Source, number: usize, start: usize, end: usize) -> Span {
+    let offset: usize = source.lines[..number - 1].iter().map(|l| l.len() + 1).sum();
+    let at = Position::new(number, 1, offset);
+    Span::new(at.advance(start), at.advance(end))
+}
+
+/// Headings should only go one level deeper at a time.
+pub struct HeadingIncrement;
+
+impl Rule for HeadingIncrement {
+    fn name(&self) -> &'static str {
+        "heading-increment"
+    }
+
+    fn description(&self) -> &'static str {
+        "heading levels should only increase by one at a time"
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>) {
+        let mut previous = None;
+        for block in source.document.blocks().iter() {
+            if let &MarkdownStructure::MDH(ref heading) = block {
+                match previous {
+                    Some(depth) if heading.depth() > depth + 1 => {
+                        let message = format!("level {} heading after a level {} heading",
+                                              heading.depth(), depth);
+                        report.push(Diagnostic::new(self.name(), message, heading.span()));
+                    }
+                    _ => {}
+                }
+                previous = Some(heading.depth());
+            }
+        }
+    }
+}
+
+/// A document should have one title.
+pub struct SingleH1;
+
+impl Rule for SingleH1 {
+    fn name(&self) -> &'static str {
+        "single-h1"
+    }
+
+    fn description(&self) -> &'static str {
+        "there should only be one top level heading"
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>) {
+        let mut first = true;
+        for block in source.document.blocks().iter() {
+            match block {
+                &MarkdownStructure::MDH(ref heading) if heading.depth() == 1 => {
+                    if !first {
+                        let message = "more than one top level heading".to_string();
+                        report.push(Diagnostic::new(self.name(), message, heading.span()));
+                    }
+                    first = false;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Lines shouldn't end in spaces, other than the two spaces of a hard
+/// break.
+pub struct TrailingWhitespace;
+
+impl Rule for TrailingWhitespace {
+    fn name(&self) -> &'static str {
+        "trailing-whitespace"
+    }
+
+    fn description(&self) -> &'static str {
+        "lines should not end in whitespace"
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>) {
+        let lines = source.body_lines();
+        for (i, &(number, line)) in lines.iter().enumerate() {
+            let end = line.trim_right().len();
+            if end == line.len() {
+                continue;
+            }
+            let continued = lines.get(i + 1).map_or(false, |&(_, next)| !next.trim().is_empty());
+            if end > 0 && line.len() - end == 2 && line.ends_with("  ") && continued {
+                continue;
+            }
+            let message = "trailing whitespace".to_string();
+            report.push(Diagnostic::new(self.name(), message,
+                                        line_span(source, number, end, line.len())));
+        }
+    }
+}
+
+/// The items of a list should all use the same bullet.
+pub struct MixedBullets;
+
+struct BulletChecker<'a, 'b> {
+    source: &'a Source<'a>,
+    report: &'b mut Vec<Diagnostic>,
+}
+
+impl<'a, 'b> Visitor for BulletChecker<'a, 'b> {
+    fn visit_list(&mut self, list: &BulletList) {
+        if !list.is_ordered() {
+            let mut expected = None;
+            for element in list.elements().iter() {
+                let bullet = match element {
+                    &BulletElement::Lone(ref bullet) => bullet,
+                    _ => continue,
+                };
+                let span = bullet.span();
+                let line = &self.source.lines[span.start.line - 1];
+                let marker = line[span.start.column - 1..].chars().next();
+                match expected {
+                    None => expected = marker,
+                    Some(expected) if marker != Some(expected) => {
+                        let message = format!("list item marked with `{}` after ones marked \
+                                               with `{}`", marker.unwrap_or(' '), expected);
+                        let marker = Span::new(span.start, span.start.advance(1));
+                        self.report.push(Diagnostic::new("mixed-bullets", message, marker));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        walk_list(self, list)
+    }
+}
+
+impl Rule for MixedBullets {
+    fn name(&self) -> &'static str {
+        "mixed-bullets"
+    }
+
+    fn description(&self) -> &'static str {
+        "the items of a list should use the same bullet"
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>) {
+        BulletChecker { source: source, report: report }.visit_document(source.document)
+    }
+}
+
+/// Links should have text and somewhere to go.
+pub struct EmptyLink;
+
+struct InlineChecker<'a, F: Fn(&Contents) -> Option<String>> {
+    rule: &'static str,
+    check: F,
+    report: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a, F: Fn(&Contents) -> Option<String>> Visitor for InlineChecker<'a, F> {
+    fn visit_inline(&mut self, inline: &Contents) {
+        if let Some(message) = (self.check)(inline) {
+            let span = inline.span().unwrap_or(Span::default());
+            self.report.push(Diagnostic::new(self.rule, message, span));
+        }
+    }
+}
+
+impl Rule for EmptyLink {
+    fn name(&self) -> &'static str {
+        "empty-link"
+    }
+
+    fn description(&self) -> &'static str {
+        "links should have text and a destination"
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>) {
+        let check = |inline: &Contents| match inline {
+            &Contents::Link(_, ref url, _, _, _) if url.trim().is_empty() || url == "#" => {
+                Some("link without a destination".to_string())
+            }
+            &Contents::Link(ref text, _, _, _, _) if text.trim().is_empty() => {
+                Some("link without any text".to_string())
+            }
+            _ => None,
+        };
+        InlineChecker { rule: self.name(), check: check, report: report }
+            .visit_document(source.document)
+    }
+}
+
+/// Images should describe themselves for readers who can't see them.
+pub struct ImageAlt;
+
+impl Rule for ImageAlt {
+    fn name(&self) -> &'static str {
+        "image-alt"
+    }
+
+    fn description(&self) -> &'static str {
+        "images should have alt text"
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>) {
+        let check = |inline: &Contents| match inline {
+            &Contents::Image(ref alt, _, _, _) if alt.trim().is_empty() => {
+                Some("image without alt text".to_string())
+            }
+            _ => None,
+        };
+        InlineChecker { rule: self.name(), check: check, report: report }
+            .visit_document(source.document)
+    }
+}
+
+/// Lines should be short enough to read in a terminal. Lines without
+/// any spaces, like long URLs, and link reference definitions can't be
+/// broken, so they're allowed.
+pub struct LineLength {
+    pub max: usize,
+}
+
+impl Rule for LineLength {
+    fn name(&self) -> &'static str {
+        "line-length"
+    }
+
+    fn description(&self) -> &'static str {
+        "lines should be no longer than `max` characters"
+    }
+
+    fn configure(&mut self, settings: &toml::Table) -> Result<(), String> {
+        for (key, value) in settings.iter() {
+            match (&key[..], value.as_integer()) {
+                ("max", Some(max)) if max > 0 => self.max = max as usize,
+                ("max", _) => return Err("`lint.line-length.max` should be a number".to_string()),
+                _ => return Err(format!("unknown setting `lint.line-length.{}`", key)),
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self, source: &Source, report: &mut Vec<Diagnostic>) {
+        for (number, line) in source.body_lines() {
+            let start = match line.char_indices().nth(self.max) {
+                Some((start, _)) => start,
+                None => continue,
+            };
+            if !line.trim().contains(' ') || parser::link_definition(line).is_some() {
+                continue;
+            }
+            let message = format!("line is {} characters long, more than {}",
+                                  line.chars().count(), self.max);
+            report.push(Diagnostic::new(self.name(), message,
+                                        line_span(source, number, start, line.len())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lint::Linter;
+    use testing::lines;
+
+    fn check(ls: &[&str]) -> Vec<String> {
+        let lines = lines(ls);
+        Linter::default().lint(&lines).unwrap().iter().map(|d| {
+            format!("{} {}: {}", d.span, d.rule, d.message)
+        }).collect()
+    }
+
+    #[test]
+    fn test_headings() {
+        assert_eq!(check(&["# A", "", "### B", "", "## C", "", "#### D", "", "# E"]),
+                   vec!["3:1-3:5 heading-increment: level 3 heading after a level 1 heading",
+                        "7:1-7:6 heading-increment: level 4 heading after a level 2 heading",
+                        "9:1-9:3 single-h1: more than one top level heading"]);
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        assert_eq!(check(&["---", "title: x ", "---", "hard  ", "break ", "", "end  "]),
+                   vec!["5:6-5:6 trailing-whitespace: trailing whitespace",
+                        "7:4-7:5 trailing-whitespace: trailing whitespace"]);
+    }
+
+    #[test]
+    fn test_mixed_bullets() {
+        assert_eq!(check(&["- a", "- b", "  * c", "  * d", "* e", "+ f"]),
+                   vec!["5:1-5:1 mixed-bullets: list item marked with `*` after ones marked \
+                         with `-`",
+                        "6:1-6:1 mixed-bullets: list item marked with `+` after ones marked \
+                         with `-`"]);
+    }
+
+    #[test]
+    fn test_links_and_images() {
+        assert_eq!(check(&["[a]() [](b) [c](#) ![](d.png) ![e](f.png)"]),
+                   vec!["1:1-1:5 empty-link: link without a destination",
+                        "1:7-1:11 empty-link: link without any text",
+                        "1:13-1:18 empty-link: link without a destination",
+                        "1:20-1:29 image-alt: image without alt text"]);
+    }
+
+    #[test]
+    fn test_line_length() {
+        let long = "word ".repeat(20);
+        let url = "x".repeat(100);
+        let definition = format!("[a]: http://example.com/{} \"a b\"", url);
+        assert_eq!(check(&[long.trim(), "", &url, "", &definition]),
+                   vec!["1:81-1:99 line-length: line is 99 characters long, more than 80"]);
+    }
+}
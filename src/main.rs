@@ -7,32 +7,46 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-#![feature(rustdoc)]
-
-extern crate rustdoc;
 extern crate getopts;
+extern crate rustc_serialize;
+extern crate rustdown;
 extern crate url;
 
 use std::env;
+use std::io;
+use std::io::Write;
 use std::path::Path;
 use std::fs::File;
+use std::process;
 use getopts::Options;
+use rustc_serialize::json::ToJson;
+use url::Url;
 
-use html::ToHtml;
-
-pub mod lines;
-pub mod mdfile;
-pub mod preprocessor;
-pub mod html;
-pub mod text;
-pub mod types;
-pub mod convert;
+use rustdown::{commands, convert, feed, html, json, latex, lines, mdfile, metadata, parser};
+use rustdown::{preprocessor, standalone};
+use rustdown::html::HtmlOptions;
+use rustdown::feed::StripMore;
+use rustdown::links::RewriteLinks;
+use rustdown::latex::LatexOptions;
+use rustdown::markdown::ToMarkdown;
+use rustdown::plaintext::{ToText, TextOptions};
+use rustdown::terminal::{ToTerminal, TerminalOptions};
+use rustdown::encoding::{Encoding, DecoderTrap};
+use rustdown::format::{InputFormat, OutputFormat};
+use rustdown::standalone::StandaloneOptions;
+use rustdown::template::Template;
+use rustdown::types::VisitorMut;
+use rustdown::types::visit::{MapSpans, ShiftHeadings};
 
 
 fn print_usage(program: &str, opts: Options) {
     let summary = opts.short_usage(program);
     let usage = opts.usage(&summary);
     println!("{}", usage);
+    println!("Commands:");
+    for &(name, description) in commands::COMMANDS.iter() {
+        println!("    {:<20}{}", name, description);
+    }
 }
 
 // Get the options.
@@ -42,6 +56,24 @@ fn opts() -> Option<getopts::Matches> {
 
     let program = "rustdown";
 
+    let opts = options();
+    let matches = match opts.parse(args) {
+        Ok(m) => { m }
+        Err(f) => {
+            println!("{}\n", f.to_string());
+            print_usage(&program, opts);
+            return None;
+        }
+    };
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return None;
+    }
+    return Some(matches);
+}
+
+// The options for converting a file.
+fn options() -> Options {
     let mut opts = Options::new();
     opts.opt("i",
              "input",
@@ -58,39 +90,354 @@ fn opts() -> Option<getopts::Matches> {
     opts.optflag("p",
                  "preview",
                  "preview the output in a web browser");
+    opts.optopt("f",
+                "from",
+                "the format of the input: markdown (default) or json",
+                "FORMAT");
+    opts.optopt("t",
+                "to",
+                "the format to write: html (default), json, markdown, text, \
+                 terminal or latex",
+                "FORMAT");
+    opts.optopt("",
+                "soft-break",
+                "how to render line breaks inside paragraphs: \
+                 newline (default), space or br",
+                "STYLE");
+    opts.optflag("",
+                 "pretty",
+                 "pretty-print the HTML, putting block elements on separate lines");
+    opts.optopt("",
+                "indent",
+                "pretty-print the HTML, indenting by this many spaces per \
+                 level (default: 2)",
+                "WIDTH");
+    opts.optopt("",
+                "width",
+                "wrap text output to this many columns, or not at all if 0 \
+                 (default: 72, or $COLUMNS for terminal output)",
+                "COLUMNS");
+    opts.optopt("",
+                "shift-headings",
+                "move every heading down this many levels, or up if negative",
+                "N");
+    opts.optflag("",
+                 "rewrite-links",
+                 "point links to .md and .markdown files at the .html files \
+                  they're converted to");
+    opts.optmulti("",
+                  "map-extension",
+                  "rewrite links to files with the extension FROM to TO \
+                   instead (implies --rewrite-links)",
+                  "FROM=TO");
+    opts.optopt("",
+                "base-url",
+                "the URL of the directory the output will be in, to resolve \
+                 rewritten links against (implies --rewrite-links)",
+                "URL");
+    opts.optflag("",
+                 "sourcepos",
+                 "add data-sourcepos attributes giving each block's position \
+                  in the input");
+    opts.optopt("",
+                "encoding",
+                "the encoding of the input file: utf-8 (default), utf-16, \
+                 utf-16le, utf-16be, ascii, latin1, latin9 or windows-1252",
+                "ENCODING");
+    opts.optflag("",
+                 "strict-encoding",
+                 "fail on input that isn't valid in its encoding, \
+                  instead of replacing it");
+    opts.optflag("s",
+                 "standalone",
+                 "emit a complete HTML or LaTeX document rather than a fragment");
+    opts.optopt("",
+                "template",
+                "render the document into this template file, replacing \
+                 {{title}}, {{body}}, {{toc}}, {{styles}} and front matter \
+                 keys such as {{author}} (implies --standalone)",
+                "FILE");
+    opts.optopt("",
+                "title",
+                "title of the standalone document (default: the first heading)",
+                "TITLE");
+    opts.optmulti("",
+                  "css",
+                  "link to this stylesheet from the standalone document",
+                  "URL");
+    opts.optmulti("",
+                  "inline-css",
+                  "include the CSS in this file in the standalone document",
+                  "FILE");
     opts.optflag("h", "help", "print this help menu");
-    let matches = match opts.parse(args) {
-        Ok(m) => { m }
-        Err(f) => {
-            println!("{}\n", f.to_string());
-            print_usage(&program, opts);
-            return None;
-        }
+    opts
+}
+
+// Build the HTML rendering options from the command line.
+fn html_options(matches: &getopts::Matches) -> Result<HtmlOptions, String> {
+    let mut options = HtmlOptions::default();
+    if let Some(style) = matches.opt_str("soft-break") {
+        options.soft_break = try!(style.parse());
+    }
+    // `--indent` on its own implies `--pretty`.
+    options.pretty = match matches.opt_str("indent") {
+        Some(width) => Some(try!(width.parse().map_err(|_| {
+            format!("invalid indent width `{}`", width)
+        }))),
+        None if matches.opt_present("pretty") => Some(2),
+        None => None,
     };
-    if matches.opt_present("h") {
-        print_usage(&program, opts);
-        return None;
+    options.sourcepos = matches.opt_present("sourcepos");
+    Ok(options)
+}
+
+// Get the width to wrap text to, if one was given, where `Some(None)`
+// means not wrapping at all.
+fn wrap_width(matches: &getopts::Matches) -> Result<Option<Option<usize>>, String> {
+    match matches.opt_str("width") {
+        Some(width) => match try!(width.parse().map_err(|_| format!("invalid width `{}`", width))) {
+            0 => Ok(Some(None)),
+            width => Ok(Some(Some(width))),
+        },
+        None => Ok(None),
     }
-    return Some(matches);
 }
 
-fn read_markdown_file(file: File) {
-    let blocks = preprocessor::replace_tabs(&lines::split_file_lines(file));
-    let markdown = convert::parse_block(&blocks);
-    let html = markdown.to_html();
-    println!("{}", html);
+// Build the plain text rendering options from the command line.
+fn text_options(matches: &getopts::Matches) -> Result<TextOptions, String> {
+    let mut options = TextOptions::default();
+    if let Some(width) = try!(wrap_width(matches)) {
+        options.width = width;
+    }
+    Ok(options)
 }
 
+// Build the terminal rendering options from the command line and the
+// terminal itself.
+fn terminal_options(matches: &getopts::Matches) -> Result<TerminalOptions, String> {
+    let mut options = TerminalOptions::detect();
+    if let Some(width) = try!(wrap_width(matches)) {
+        options.width = width;
+    }
+    Ok(options)
+}
 
+// Get the input encoding and how to handle errors in it.
+fn input_encoding(matches: &getopts::Matches) -> Result<(Encoding, DecoderTrap), String> {
+    let encoding = match matches.opt_str("encoding") {
+        Some(name) => try!(name.parse()),
+        None => Encoding::Utf8,
+    };
+    let trap = if matches.opt_present("strict-encoding") {
+        DecoderTrap::Strict
+    }
+    else {
+        DecoderTrap::Replace
+    };
+    Ok((encoding, trap))
+}
+
+// Build the standalone document options from the command line, if a
+// standalone document was asked for.
+fn standalone_options(matches: &getopts::Matches)
+                      -> Result<Option<StandaloneOptions>, String> {
+    if !matches.opt_present("standalone") && !matches.opt_present("template") {
+        return Ok(None);
+    }
+    let mut standalone = StandaloneOptions::default();
+    standalone.title = matches.opt_str("title");
+    if let Some(path) = matches.opt_str("template") {
+        standalone.template = Template::new(try!(read_text_file(&path)));
+    }
+    standalone.stylesheets = matches.opt_strs("css");
+    for path in matches.opt_strs("inline-css").iter() {
+        standalone.inline_css.push(try!(read_text_file(path)));
+    }
+    Ok(Some(standalone))
+}
+
+// Build the transforms to run over the document before rendering it.
+fn transforms(matches: &getopts::Matches) -> Result<Vec<Box<VisitorMut>>, String> {
+    let mut transforms: Vec<Box<VisitorMut>> = vec![];
+    if let Some(levels) = matches.opt_str("shift-headings") {
+        let levels = try!(levels.parse().map_err(|_| {
+            format!("invalid number of heading levels `{}`", levels)
+        }));
+        transforms.push(Box::new(ShiftHeadings(levels)));
+    }
+    let extensions = matches.opt_strs("map-extension");
+    let base_url = matches.opt_str("base-url");
+    if matches.opt_present("rewrite-links") || !extensions.is_empty() || base_url.is_some() {
+        let mut rewrite = RewriteLinks::new();
+        if !extensions.is_empty() {
+            rewrite.extensions = try!(extensions.iter().map(|mapping| {
+                let mut parts = mapping.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(from), Some(to)) if !from.is_empty() && !to.is_empty() => {
+                        Ok((from.trim_left_matches('.').to_string(),
+                            to.trim_left_matches('.').to_string()))
+                    }
+                    _ => Err(format!("invalid extension mapping `{}` (expected FROM=TO)",
+                                     mapping)),
+                }
+            }).collect());
+        }
+        if let Some(url) = base_url {
+            // A base URL without a trailing slash would drop its last
+            // directory when links are resolved against it.
+            let url = if url.ends_with('/') { url } else { url + "/" };
+            rewrite.base_url = Some(try!(Url::parse(&url).map_err(|e| {
+                format!("invalid base URL `{}`: {}", url, e)
+            })));
+        }
+        transforms.push(Box::new(rewrite));
+    }
+    Ok(transforms)
+}
+
+fn read_text_file(path: &str) -> Result<String, String> {
+    mdfile::read_text_file(Path::new(path)).map_err(|e| {
+        format!("Error reading {}:\n{}", path, e)
+    })
+}
+
+// Everything about how to convert the input, from the command line.
+struct Conversion {
+    encoding: (Encoding, DecoderTrap),
+    from: InputFormat,
+    to: OutputFormat,
+    options: HtmlOptions,
+    text: TextOptions,
+    terminal: TerminalOptions,
+    standalone: Option<StandaloneOptions>,
+    transforms: Vec<Box<VisitorMut>>,
+}
+
+fn conversion(matches: &getopts::Matches) -> Result<Conversion, String> {
+    Ok(Conversion {
+        encoding: try!(input_encoding(matches)),
+        from: match matches.opt_str("from") {
+            Some(format) => try!(format.parse()),
+            None => InputFormat::Markdown,
+        },
+        to: match matches.opt_str("to") {
+            Some(format) => try!(format.parse()),
+            None => OutputFormat::Html,
+        },
+        options: try!(html_options(matches)),
+        text: try!(text_options(matches)),
+        terminal: try!(terminal_options(matches)),
+        standalone: try!(standalone_options(matches)),
+        transforms: try!(transforms(matches)),
+    })
+}
+
+fn read_markdown_file(file: File, conversion: Conversion) {
+    let Conversion { encoding: (encoding, trap), from, to, options, text, terminal,
+                     standalone, mut transforms } = conversion;
+    let (lines, mut map) = match lines::split_file_lines(file, encoding, trap) {
+        Ok(read) => read,
+        Err(e) => {
+            println!("Error reading markdown file:\n{}", e);
+            return;
+        }
+    };
+    let lines = preprocessor::replace_tabs_mapped(&lines, &mut map);
+    if from == InputFormat::Markdown && to == OutputFormat::Html && standalone.is_none() &&
+       transforms.is_empty() {
+        // Fragments are rendered straight from the parser's events, since
+        // there's nothing to wrap them in or rewrite. Rendering a document
+        // goes through the same renderer.
+        let skip = match metadata::parse_front_matter(&lines) {
+            Ok((_, skip)) => skip,
+            Err(e) => {
+                println!("Error parsing markdown file:\n{}", e);
+                return;
+            }
+        };
+        let stdout = io::stdout();
+        let mut out = io::BufWriter::new(stdout.lock());
+        let events = parser::Parser::starting_at(&lines, skip).spanned().filter(|&(ref event, _)| {
+            !feed::is_more_event(event)
+        }).map(|(event, span)| (event, map.span(span)));
+        let result = html::write_events_to(events, &mut out, &options)
+            .and_then(|_| out.write_all(b"\n"));
+        if let Err(e) = result {
+            println!("Error writing HTML:\n{}", e);
+        }
+        return;
+    }
+    // The `<!-- more -->` cut only means anything to feeds.
+    transforms.insert(0, Box::new(StripMore));
+    let document = match from {
+        InputFormat::Markdown => {
+            transforms.insert(0, Box::new(MapSpans(map)));
+            convert::parse_document_with(&lines, &mut transforms)
+                .map_err(|e| format!("Error parsing markdown file:\n{}", e))
+        }
+        InputFormat::Json => {
+            json::parse_document(&lines.join("\n")).map(|mut document| {
+                convert::transform(&mut document, &mut transforms);
+                document
+            }).map_err(|e| format!("Error reading JSON document:\n{}", e))
+        }
+    };
+    let document = match document {
+        Ok(document) => document,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    match (to, &standalone) {
+        (OutputFormat::Json, _) => println!("{}", document.to_json().pretty()),
+        (OutputFormat::Markdown, _) => print!("{}", document.to_markdown()),
+        (OutputFormat::Text, _) => print!("{}", document.to_text_with(&text)),
+        (OutputFormat::Terminal, _) => print!("{}", document.to_terminal_with(&terminal)),
+        (OutputFormat::Latex, _) => {
+            let latex = LatexOptions {
+                standalone: standalone.is_some(),
+                title: standalone.as_ref().and_then(|standalone| standalone.title.clone()),
+            };
+            print!("{}", latex::render(&document, &latex))
+        }
+        (OutputFormat::Html, &Some(ref standalone)) => {
+            match standalone::render(&document, &options, standalone) {
+                Ok(html) => print!("{}", html),
+                Err(e) => println!("Error rendering markdown file:\n{}", e),
+            }
+        }
+        (OutputFormat::Html, &None) => {
+            let stdout = io::stdout();
+            let mut out = io::BufWriter::new(stdout.lock());
+            let result = html::write_html_to(&document, &mut out, &options)
+                .and_then(|_| out.write_all(b"\n"));
+            if let Err(e) = result {
+                println!("Error writing HTML:\n{}", e);
+            }
+        }
+    }
+}
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(status) = commands::run(&args) {
+        process::exit(status);
+    }
     match opts() {
         Some(matches) => {
             let input = matches.opt_str("i").expect("Some input argument is required!");
             let input_file = Path::new(&input);
+            let conversion = match conversion(&matches) {
+                Ok(conversion) => conversion,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
             match mdfile::open_markdown_file(&input_file) {
                 Ok(file) => {
-                    read_markdown_file(file);
+                    read_markdown_file(file, conversion);
                 }
                 Err(e) => {
                     println!("Error opening markdown file:\n{}", e);
@@ -102,3 +449,29 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{options, transforms};
+    use rustdown::convert;
+    use rustdown::html::HtmlOptions;
+
+    fn rewrite(args: &[&str], markdown: &str) -> String {
+        let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        args.extend(vec!["-i".to_string(), "in.md".to_string()]);
+        let matches = options().parse(args).unwrap();
+        let lines = vec![markdown.to_string()];
+        let mut transforms = transforms(&matches).unwrap();
+        let document = convert::parse_document_with(&lines, &mut transforms).unwrap();
+        document.body_html(&HtmlOptions::default())
+    }
+
+    #[test]
+    fn test_base_url_gets_a_trailing_slash() {
+        let expected = "<p><a href=\"https://example.com/docs/guide.html\">g</a></p>";
+        assert_eq!(rewrite(&["--base-url", "https://example.com/docs"], "[g](guide.md)"),
+                   expected);
+        assert_eq!(rewrite(&["--base-url", "https://example.com/docs/"], "[g](guide.md)"),
+                   expected);
+    }
+}
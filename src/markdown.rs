@@ -0,0 +1,775 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rendering documents back to markdown.
+//!
+//! The output is normalised CommonMark: headings are written in one style,
+//! every bullet uses the same marker, ordered lists are numbered the same
+//! way, rules are `***` (a `---` line could be taken for front matter),
+//! and links are written as numbered references, defined at the end of
+//! the output. Tables have pipes at both ends of every row, and are
+//! padded so their columns line up unless `align_tables` is turned off.
+//! Text is escaped wherever it could otherwise be read as markup, so
+//! parsing the output gives back the same document.
+
+use std::cmp;
+use std::str::FromStr;
+
+use metadata;
+use plaintext::pad;
+use types::{Document, MarkdownStructure, Heading, Paragraph, BulletList, Bullet, CodeBlock};
+use types::BlockQuote;
+use types::{Table, TableRow};
+use parser::Alignment;
+use types::bulletlist::BulletElement;
+use types::inline::Contents;
+
+/// How headings are written.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum HeadingStyle {
+    /// `# Heading`
+    Atx,
+    /// The text underlined with `=` or `-`. Only first and second level
+    /// headings can be written this way, so deeper ones are still ATX.
+    Setext,
+}
+
+impl FromStr for HeadingStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<HeadingStyle, String> {
+        match s {
+            "atx" => Ok(HeadingStyle::Atx),
+            "setext" => Ok(HeadingStyle::Setext),
+            _ => Err(format!("unknown heading style `{}` (expected atx or setext)", s)),
+        }
+    }
+}
+
+/// How the items of ordered lists are numbered.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Numbering {
+    /// Counting up from the list's start number.
+    Consecutive,
+    /// Every item has the list's start number, so moving items around
+    /// doesn't renumber them.
+    Same,
+}
+
+impl FromStr for Numbering {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Numbering, String> {
+        match s {
+            "consecutive" => Ok(Numbering::Consecutive),
+            "same" => Ok(Numbering::Same),
+            _ => Err(format!("unknown list numbering `{}` (expected consecutive or same)", s)),
+        }
+    }
+}
+
+/// Options controlling how documents are written as markdown.
+///
+#[derive(Clone, Debug)]
+pub struct MarkdownOptions {
+    pub heading_style: HeadingStyle,
+    /// The marker for bullet list items: `-`, `*` or `+`.
+    pub bullet: char,
+    pub numbering: Numbering,
+    /// Wrap paragraphs to lines of at most this many columns, where they
+    /// have spaces to break at. Otherwise lines are broken where they
+    /// were in the input.
+    pub wrap: Option<usize>,
+    /// Write links as references, like `[text][1]`, with the definitions
+    /// at the end of the document. Otherwise links are written inline.
+    pub reference_links: bool,
+    /// Pad the cells of tables so that each column's pipes line up.
+    pub align_tables: bool,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> MarkdownOptions {
+        MarkdownOptions {
+            heading_style: HeadingStyle::Atx,
+            bullet: '-',
+            numbering: Numbering::Consecutive,
+            wrap: None,
+            reference_links: true,
+            align_tables: true,
+        }
+    }
+}
+
+/// The markdown written so far, along with the link destinations that
+/// need reference definitions.
+pub struct MarkdownWriter<'a> {
+    out: String,
+    options: &'a MarkdownOptions,
+    references: Vec<(String, Option<String>)>,
+    /// How many block quotes the blocks being written are inside.
+    quote_depth: usize,
+}
+
+impl<'a> MarkdownWriter<'a> {
+    pub fn new(options: &'a MarkdownOptions) -> MarkdownWriter<'a> {
+        MarkdownWriter {
+            out: String::new(),
+            options: options,
+            references: vec![],
+            quote_depth: 0,
+        }
+    }
+
+    pub fn options(&self) -> &MarkdownOptions {
+        self.options
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    /// The number of the reference to `url` with `title`, which is added
+    /// if it hasn't been linked to before.
+    fn reference(&mut self, url: &str, title: &Option<String>) -> usize {
+        match self.references.iter().position(|&(ref u, ref t)| u == url && t == title) {
+            Some(i) => i + 1,
+            None => {
+                self.references.push((url.to_string(), title.clone()));
+                self.references.len()
+            }
+        }
+    }
+
+    /// The finished markdown, with reference definitions after it and a
+    /// final newline.
+    pub fn finish(self) -> String {
+        let mut out = self.out.trim_right_matches('\n').to_string();
+        if !self.references.is_empty() {
+            out.push_str("\n");
+            for (i, &(ref url, ref title)) in self.references.iter().enumerate() {
+                out.push_str(&format!("\n[{}]: {}{}", i + 1, escape_destination(url),
+                                      link_title(title)));
+            }
+        }
+        out.push('\n');
+        out
+    }
+}
+
+pub trait ToMarkdown {
+    fn write_markdown(&self, w: &mut MarkdownWriter);
+
+    /// Render to markdown using the default `MarkdownOptions`.
+    fn to_markdown(&self) -> String {
+        self.to_markdown_with(&MarkdownOptions::default())
+    }
+
+    fn to_markdown_with(&self, options: &MarkdownOptions) -> String {
+        let mut w = MarkdownWriter::new(options);
+        self.write_markdown(&mut w);
+        w.finish()
+    }
+}
+
+/// Writes the front matter, if there is any, then the blocks separated by
+/// blank lines. Front matter the document was parsed with is written back
+/// line for line; otherwise the metadata is written as YAML.
+impl ToMarkdown for Document {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        if let Some(lines) = self.front_matter() {
+            for line in lines.iter() {
+                w.push_str(line);
+                w.push_str("\n");
+            }
+            w.push_str("\n");
+        }
+        else if !self.metadata().is_empty() {
+            w.push_str("---\n");
+            w.push_str(&metadata::to_yaml(self.metadata()));
+            w.push_str("---\n\n");
+        }
+        write_blocks(self.blocks(), w);
+    }
+}
+
+/// Write `blocks` separated by blank lines.
+fn write_blocks(blocks: &[MarkdownStructure], w: &mut MarkdownWriter) {
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            w.push_str("\n\n");
+        }
+        block.write_markdown(w);
+    }
+}
+
+impl ToMarkdown for MarkdownStructure {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        match self {
+            &MarkdownStructure::MDH(ref heading) => heading.write_markdown(w),
+            &MarkdownStructure::MDP(ref paragraph) => paragraph.write_markdown(w),
+            &MarkdownStructure::MDB(ref list) => list.write_markdown(w),
+            &MarkdownStructure::MDR(_) => w.push_str("***"),
+            &MarkdownStructure::MDC(ref code) => code.write_markdown(w),
+            &MarkdownStructure::MDX(ref html) => w.push_str(html.html()),
+            &MarkdownStructure::MDQ(ref quote) => quote.write_markdown(w),
+            &MarkdownStructure::MDT(ref table) => table.write_markdown(w),
+        }
+    }
+}
+
+/// Every quoted line starts with `> `, or just `>` if it's blank.
+/// Paragraphs are wrapped to fit in after the markers.
+impl ToMarkdown for BlockQuote {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        let start = w.out.len();
+        w.quote_depth += 1;
+        write_blocks(self.blocks(), w);
+        w.quote_depth -= 1;
+        let quoted: Vec<String> = w.out[start..].split('\n').map(|line| {
+            if line.is_empty() { ">".to_string() } else { format!("> {}", line) }
+        }).collect();
+        w.out.truncate(start);
+        w.push_str(&quoted.join("\n"));
+    }
+}
+
+/// Tables are written with pipes at both ends of every row. With
+/// `align_tables` set, each column is padded to the width of its widest
+/// cell, following the column's alignment.
+impl ToMarkdown for Table {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        let head = row_markdown(self.head(), w);
+        let rows: Vec<Vec<String>> = self.rows().iter().map(|row| row_markdown(row, w)).collect();
+        let align = w.options().align_tables;
+        let mut widths = vec![3; self.alignments().len()];
+        if align {
+            for row in Some(&head).into_iter().chain(rows.iter()) {
+                for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                    *width = cmp::max(*width, cell.chars().count());
+                }
+            }
+        }
+        let delimiters: Vec<String> = self.alignments().iter().zip(widths.iter()).map(|(a, &n)| {
+            match *a {
+                Alignment::None => "-".repeat(n),
+                Alignment::Left => format!(":{}", "-".repeat(n - 1)),
+                Alignment::Right => format!("{}:", "-".repeat(n - 1)),
+                Alignment::Center => format!(":{}:", "-".repeat(n - 2)),
+            }
+        }).collect();
+        let alignments = if align { self.alignments() } else { &[] };
+        let mut lines = vec![table_line(&head, alignments, &widths),
+                             table_line(&delimiters, alignments, &widths)];
+        for row in rows.iter() {
+            lines.push(table_line(row, alignments, &widths));
+        }
+        w.push_str(&lines.join("\n"));
+    }
+}
+
+/// The markdown for each cell of `row`, with the pipes in it escaped.
+fn row_markdown(row: &TableRow, w: &mut MarkdownWriter) -> Vec<String> {
+    row.cells().iter().map(|cell| {
+        let start = w.out.len();
+        write_inline(cell.contents(), w, true);
+        w.out.split_off(start).replace('|', "\\|")
+    }).collect()
+}
+
+/// A row of a table, with each cell padded to its column's width and
+/// alignment. Cells with no alignment given are written as they are.
+fn table_line(cells: &[String], alignments: &[Alignment], widths: &[usize]) -> String {
+    let length = |s: &str| s.chars().count();
+    let cells: Vec<String> = cells.iter().enumerate().map(|(i, cell)| {
+        match alignments.get(i) {
+            Some(&alignment) => pad(cell, widths[i], alignment, &length),
+            None => cell.clone(),
+        }
+    }).collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+/// Code blocks are always fenced, with a fence longer than any run of
+/// the fence character in the code.
+impl ToMarkdown for CodeBlock {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        let c = if self.info().contains('`') { '~' } else { '`' };
+        let longest = longest_run(self.code(), c);
+        let fence = c.to_string().repeat(if longest < 3 { 3 } else { longest + 1 });
+        w.push_str(&fence);
+        w.push_str(self.info());
+        w.push_str("\n");
+        w.push_str(self.code());
+        w.push_str(&fence);
+    }
+}
+
+impl ToMarkdown for Heading {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        let empty = *self.contents() == Contents::Nil;
+        if w.options().heading_style == HeadingStyle::Setext && self.depth() <= 2 && !empty {
+            let start = w.out.len();
+            write_inline(self.contents(), w, true);
+            let width = w.out[start..].chars().count();
+            let underline = if self.depth() == 1 { "=" } else { "-" };
+            w.push_str("\n");
+            w.push_str(&underline.repeat(if width > 3 { width } else { 3 }));
+            return;
+        }
+        w.push_str(&"#".repeat(self.depth() as usize));
+        if !empty {
+            w.push_str(" ");
+            write_inline(self.contents(), w, true);
+        }
+    }
+}
+
+impl ToMarkdown for Paragraph {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        match w.options().wrap {
+            Some(width) => {
+                let width = width.saturating_sub(2 * w.quote_depth);
+                write_wrapped(self.contents(), w, width)
+            }
+            None => write_inline(self.contents(), w, false),
+        }
+    }
+}
+
+impl ToMarkdown for Bullet {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        write_inline(self.contents(), w, true)
+    }
+}
+
+impl ToMarkdown for BulletList {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        write_list(self, w, 0)
+    }
+}
+
+impl ToMarkdown for Contents {
+    fn write_markdown(&self, w: &mut MarkdownWriter) {
+        write_inline(self, w, false)
+    }
+}
+
+/// Write a list with its markers indented by `indent` spaces. Nested
+/// lists are indented to line up with the text of the item before them.
+fn write_list(list: &BulletList, w: &mut MarkdownWriter, indent: usize) {
+    let mut number = list.start().unwrap_or(1);
+    let mut nested_indent = indent + 2;
+    for (i, element) in list.elements().iter().enumerate() {
+        if i > 0 {
+            w.push_str("\n");
+        }
+        match element {
+            &BulletElement::Lone(ref bullet) => {
+                let marker = if list.is_ordered() {
+                    if w.options().numbering == Numbering::Consecutive {
+                        number += 1;
+                        format!("{}.", number - 1)
+                    }
+                    else {
+                        format!("{}.", number)
+                    }
+                }
+                else {
+                    w.options().bullet.to_string()
+                };
+                w.push_str(&" ".repeat(indent));
+                w.push_str(&marker);
+                if *bullet.contents() != Contents::Nil {
+                    w.push_str(" ");
+                    bullet.write_markdown(w);
+                }
+                nested_indent = indent + marker.len() + 1;
+            }
+            &BulletElement::Nested(ref nested) => write_list(nested, w, nested_indent),
+        }
+    }
+}
+
+/// Write a chain of inline contents. With `single_line` set, line breaks
+/// are written as spaces, since headings and list items can't span lines.
+fn write_inline(contents: &Contents, w: &mut MarkdownWriter, single_line: bool) {
+    let mut line_start = true;
+    for inline in contents.spans() {
+        match inline {
+            &Contents::Normal(ref s, _, _) => w.push_str(&escape_text(s, line_start)),
+            &Contents::SoftBreak(..) | &Contents::HardBreak(..) => {
+                let trimmed = w.out.trim_right_matches(' ').len();
+                w.out.truncate(trimmed);
+                if single_line {
+                    w.push_str(" ");
+                }
+                else if let &Contents::HardBreak(..) = inline {
+                    w.push_str("\\\n");
+                }
+                else {
+                    w.push_str("\n");
+                }
+            }
+            &Contents::Nil => {}
+            _ => {
+                let markup = inline_markup(inline, w);
+                w.push_str(&markup);
+            }
+        }
+        line_start = match inline {
+            &Contents::SoftBreak(..) | &Contents::HardBreak(..) => !single_line,
+            _ => false,
+        };
+    }
+}
+
+/// The markup for an inline that isn't plain text or a break.
+fn inline_markup(inline: &Contents, w: &mut MarkdownWriter) -> String {
+    match inline {
+        &Contents::Italic(ref s, _, _) => format!("*{}*", escape_text(s, false)),
+        &Contents::Bold(ref s, _, _) => format!("**{}**", escape_text(s, false)),
+        &Contents::Code(ref s, _, _) => code_span(s),
+        &Contents::Link(ref s, ref url, ref title, _, _) => {
+            let text = escape_text(s, false);
+            if w.options().reference_links && !url.is_empty() {
+                format!("[{}][{}]", text, w.reference(url, title))
+            }
+            else {
+                format!("[{}]({}{})", text, escape_destination(url), link_title(title))
+            }
+        }
+        &Contents::Image(ref alt, ref src, _, _) => {
+            format!("![{}]({})", escape_text(alt, false), escape_destination(src))
+        }
+        _ => String::new(),
+    }
+}
+
+/// A piece of a wrapped paragraph.
+enum Piece {
+    /// Plain text without spaces, escaped once it's known whether it
+    /// starts a line.
+    Text(String),
+    Markup(String),
+}
+
+/// Write a paragraph, refilling its lines to at most `width` columns.
+/// Lines are only broken at spaces in plain text, so a long word or
+/// link can still overflow.
+fn write_wrapped(contents: &Contents, w: &mut MarkdownWriter, width: usize) {
+    // Words are runs of pieces with no space between them; `None` marks
+    // a hard break.
+    let mut words: Vec<Option<Vec<Piece>>> = vec![];
+    let mut word = vec![];
+    for inline in contents.spans() {
+        match inline {
+            &Contents::Normal(ref s, _, _) => {
+                for (i, part) in s.split(' ').enumerate() {
+                    if i > 0 && !word.is_empty() {
+                        words.push(Some(word));
+                        word = vec![];
+                    }
+                    if !part.is_empty() {
+                        word.push(Piece::Text(part.to_string()));
+                    }
+                }
+            }
+            &Contents::SoftBreak(..) | &Contents::HardBreak(..) => {
+                if !word.is_empty() {
+                    words.push(Some(word));
+                    word = vec![];
+                }
+                if let &Contents::HardBreak(..) = inline {
+                    words.push(None);
+                }
+            }
+            &Contents::Nil => {}
+            _ => word.push(Piece::Markup(inline_markup(inline, w))),
+        }
+    }
+    if !word.is_empty() {
+        words.push(Some(word));
+    }
+    let mut column = 0;
+    for word in words.iter() {
+        let pieces = match word {
+            &Some(ref pieces) => pieces,
+            &None => {
+                w.push_str("\\\n");
+                column = 0;
+                continue;
+            }
+        };
+        let render = |line_start: bool| {
+            pieces.iter().enumerate().map(|(i, piece)| match piece {
+                &Piece::Text(ref s) => escape_text(s, line_start && i == 0),
+                &Piece::Markup(ref s) => s.clone(),
+            }).collect::<String>()
+        };
+        let length = render(false).chars().count();
+        if column > 0 && column + 1 + length > width {
+            w.push_str("\n");
+            column = 0;
+        }
+        if column > 0 {
+            w.push_str(" ");
+            column += 1;
+        }
+        w.push_str(&render(column == 0));
+        column += length;
+    }
+}
+
+/// Escape the characters of `s` that could be read as markup. At the
+/// start of a line, this includes anything that could start a block.
+fn escape_text(s: &str, line_start: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    let chars: Vec<char> = s.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        let before = if i > 0 { Some(chars[i - 1]) } else { None };
+        let after = chars.get(i + 1).cloned();
+        let alphanumeric = |c: Option<char>| c.map_or(false, |c| c.is_alphanumeric());
+        let escape = match c {
+            '\\' | '`' | '*' | '[' | ']' => true,
+            // `_` inside a word never counts as emphasis.
+            '_' => !(alphanumeric(before) && alphanumeric(after)),
+            '&' => is_reference(&s[s.char_indices().nth(i).unwrap().0 + 1..]),
+            '#' | '-' | '+' | '=' | '~' | '>' | '<' => line_start && i == 0,
+            '.' | ')' => line_start && i > 0 && chars[..i].iter().all(|c| c.is_digit(10)),
+            _ => false,
+        };
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Whether `s`, coming just after a `&`, would make it a character
+/// reference.
+fn is_reference(s: &str) -> bool {
+    let name = s.len() - s.trim_left_matches(|c: char| c.is_alphanumeric() || c == '#').len();
+    name > 0 && s[name..].starts_with(';')
+}
+
+/// A code span for `code`, fenced with more backticks than any run of
+/// them inside it.
+fn code_span(code: &str) -> String {
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    let pad = code.starts_with('`') || code.ends_with('`') ||
+              (code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty());
+    let space = if pad { " " } else { "" };
+    format!("{0}{1}{2}{1}{0}", fence, space, code)
+}
+
+/// The length of the longest run of `c` in `s`.
+fn longest_run(s: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in s.chars() {
+        run = if ch == c { run + 1 } else { 0 };
+        longest = if run > longest { run } else { longest };
+    }
+    longest
+}
+
+/// A link title, with a space before it, or nothing if there's no title.
+fn link_title(title: &Option<String>) -> String {
+    match title {
+        &Some(ref title) => {
+            format!(" \"{}\"", title.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+        &None => String::new(),
+    }
+}
+
+/// A link destination, in angle brackets if it has anything in it that
+/// would otherwise end it early.
+fn escape_destination(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for (i, c) in url.char_indices() {
+        if c == '\\' || c == '<' || c == '>' || (c == '&' && is_reference(&url[i + 1..])) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    if url.is_empty() || url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{}>", out)
+    }
+    else {
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ToMarkdown, MarkdownOptions, HeadingStyle, Numbering};
+    use convert::parse_document;
+    use html::HtmlOptions;
+    use types::{Document, Heading};
+    use testing::lines;
+
+    fn normalise(ls: &[&str]) -> String {
+        parse_document(&lines(ls)).unwrap().to_markdown()
+    }
+
+    #[test]
+    fn test_normalised_output() {
+        assert_eq!(normalise(&["Title", "=====", "", "* a", "+ b", "  * c", "", "3) x", "9) y",
+                               "", "---", "", "See [this](a.md), [that](a.md)  ",
+                               "and ![img](i.png)"]),
+                   "# Title\n\n- a\n- b\n  - c\n\n3. x\n4. y\n\n***\n\n\
+                    See [this][1], [that][1]\\\nand ![img](i.png)\n\n[1]: a.md\n");
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(normalise(&["\\# a \\*b\\* snake_case \\_c\\_ \\[d\\] &amp;copy; & `` `e` ``",
+                               "1\\. f", "\\- g"]),
+                   "\\# a \\*b\\* snake_case \\_c\\_ \\[d\\] \\&copy; & `` `e` ``\n\
+                    1\\. f\n\\- g\n");
+        let mut options = MarkdownOptions::default();
+        options.reference_links = false;
+        let document = parse_document(&lines(&["[a](<b c>)"])).unwrap();
+        assert_eq!(document.to_markdown_with(&options), "[a](<b c>)\n");
+        assert_eq!(Heading::new("x".to_string(), 3).to_markdown(), "### x\n");
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        assert_eq!(normalise(&["    a", "", "      b", "", "~~~ rust", "```", "~~~"]),
+                   "```\na\n\n  b\n```\n\n````rust\n```\n````\n");
+        assert_eq!(normalise(&["~~~ a`b", "c", "~~~"]), "~~~a`b\nc\n~~~\n");
+    }
+
+    #[test]
+    fn test_html_blocks_are_kept() {
+        assert_eq!(normalise(&["<div>", "  *a*   b", "</div>", "", "<!-- more -->", "c"]),
+                   "<div>\n  *a*   b\n</div>\n\n<!-- more -->\n\nc\n");
+    }
+
+    #[test]
+    fn test_block_quotes() {
+        assert_eq!(normalise(&["> # A", ">", ">b", "c", "> > - d", "> >", "> >   e"]),
+                   "> # A\n>\n> b\n> c\n>\n> > - d\n> >\n> > e\n");
+        let mut options = MarkdownOptions::default();
+        options.wrap = Some(10);
+        let document = parse_document(&lines(&["> one two three", "", "> > four five"])).unwrap();
+        assert_eq!(document.to_markdown_with(&options),
+                   "> one two\n> three\n\n> > four\n> > five\n");
+    }
+
+    #[test]
+    fn test_tables() {
+        let input = ["a|*b*|c", ":-:|-:|:--", "long cell | `x\\|y` |", "1 | 2 | 3 | 4"];
+        assert_eq!(normalise(&input),
+                   "|     a     |    *b* | c   |\n\
+                    | :-------: | -----: | :-- |\n\
+                    | long cell | `x\\|y` |     |\n\
+                    |     1     |      2 | 3   |\n");
+        let mut options = MarkdownOptions::default();
+        options.align_tables = false;
+        let document = parse_document(&lines(&input)).unwrap();
+        assert_eq!(document.to_markdown_with(&options),
+                   "| a | *b* | c |\n| :-: | --: | :-- |\n| long cell | `x\\|y` |  |\n\
+                    | 1 | 2 | 3 |\n");
+    }
+
+    #[test]
+    fn test_link_titles() {
+        assert_eq!(normalise(&["[a](b 'say \"hi\"') [c](b)"]),
+                   "[a][1] [c][2]\n\n[1]: b \"say \\\"hi\\\"\"\n[2]: b\n");
+        let mut options = MarkdownOptions::default();
+        options.reference_links = false;
+        let document = parse_document(&lines(&["[a](b \"c\")"])).unwrap();
+        assert_eq!(document.to_markdown_with(&options), "[a](b \"c\")\n");
+    }
+
+    #[test]
+    fn test_options() {
+        let document = parse_document(&lines(&["# Title", "", "## Sub", "", "### Deeper", "",
+                                               "* a", "  3. b", "  4. c", "", "one two three",
+                                               "four-five sixty seven 1. eight  ", "nine"]))
+                       .unwrap();
+        let options = MarkdownOptions {
+            heading_style: HeadingStyle::Setext,
+            bullet: '*',
+            numbering: Numbering::Same,
+            wrap: Some(12),
+            reference_links: true,
+            align_tables: true,
+        };
+        assert_eq!(document.to_markdown_with(&options),
+                   "Title\n=====\n\nSub\n---\n\n### Deeper\n\n* a\n  3. b\n  3. c\n\n\
+                    one two\nthree\nfour-five\nsixty seven\n1\\. eight\\\nnine\n");
+    }
+
+    #[test]
+    fn test_front_matter() {
+        assert_eq!(normalise(&["---", "title: \"A: B\"", "# The date", "date: 2015-03-14",
+                               "tags: [x, 1]", "---", "Text"]),
+                   "---\ntitle: \"A: B\"\n# The date\ndate: 2015-03-14\ntags: [x, 1]\n---\n\n\
+                    Text\n");
+        assert_eq!(normalise(&["+++", "title = \"A\"  # here", "+++", "Text"]),
+                   "+++\ntitle = \"A\"  # here\n+++\n\nText\n");
+        let document = Document::with_metadata(vec![], parse_document(&lines(&[
+            "---", "b: 2", "a: \"x\"", "---"])).unwrap().metadata().clone());
+        assert_eq!(document.to_markdown(), "---\na: \"x\"\nb: 2\n---\n");
+    }
+
+    // A small pseudo-random generator, so the property test below is
+    // repeatable.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, n: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % n
+        }
+    }
+
+    #[test]
+    fn test_round_trip_is_stable() {
+        let pieces = ["word", "*em*", "**strong**", "`code`", "[link](x.md)", "![img](i.png)",
+                      "\\*", "_", "&amp;", "#", "1.", "-", "[", "]", "` `` `", "a  ", "\\",
+                      "[ref]", "&copy;", "<b>", "\\~~~", "\\>", "\\<div>"];
+        let starts = ["", "# ", "## ", "- ", "  - ", "1. ", "* ", "[ref]: r.md", "***", "="];
+        let mut rng = Lcg(2015);
+        for _ in 0..300 {
+            let mut input = vec![];
+            for _ in 0..(1 + rng.next(6)) {
+                let mut line = starts[rng.next(starts.len())].to_string();
+                for _ in 0..rng.next(5) {
+                    line.push_str(pieces[rng.next(pieces.len())]);
+                    line.push(' ');
+                }
+                input.push(line);
+                if rng.next(3) == 0 {
+                    input.push(String::new());
+                }
+            }
+            let document = parse_document(&input).unwrap();
+            let markdown = document.to_markdown();
+            let output: Vec<String> = markdown.lines().map(|l| l.to_string()).collect();
+            let reparsed = parse_document(&output).unwrap();
+            let options = HtmlOptions::default();
+            assert_eq!(reparsed.body_html(&options), document.body_html(&options),
+                       "input {:?} rendered as {:?}", input, markdown);
+            assert_eq!(reparsed.to_markdown(), markdown);
+            let wrapped = MarkdownOptions { wrap: Some(10), ..MarkdownOptions::default() };
+            let markdown = document.to_markdown_with(&wrapped);
+            let output: Vec<String> = markdown.lines().map(|l| l.to_string()).collect();
+            let reparsed = parse_document(&output).unwrap();
+            assert_eq!(reparsed.to_markdown_with(&wrapped), markdown);
+        }
+    }
+}
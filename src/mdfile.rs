@@ -9,8 +9,17 @@
 
 use std::fs::File;
 use std::path::Path;
+use std::io::Read;
 use std::io::Result as IoResult;
 
 pub fn open_markdown_file(path: &Path) -> IoResult<File> {
     File::open(path)
 }
+
+/// Read a UTF-8 text file, such as a template or stylesheet.
+pub fn read_text_file(path: &Path) -> IoResult<String> {
+    let mut file = try!(File::open(path));
+    let mut s = String::new();
+    try!(file.read_to_string(&mut s));
+    Ok(s)
+}
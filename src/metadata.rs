@@ -0,0 +1,344 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Front matter: a YAML or TOML metadata header at the top of a document.
+//!
+//! YAML front matter is fenced with `---` lines, and TOML front matter
+//! with `+++` lines:
+//!
+//! ```text
+//! ---
+//! title: Release notes
+//! tags: [news, release]
+//! ---
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use rustc_serialize::json::Json;
+use toml;
+use yaml_rust::{Yaml, YamlLoader};
+
+/// The metadata of a document, by key.
+pub type Metadata = BTreeMap<String, MetaValue>;
+
+/// A single metadata value.
+///
+#[derive(PartialEq, Clone, Debug)]
+pub enum MetaValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    /// A date or date and time, as written, e.g. `2015-03-14` or
+    /// `2015-03-14T15:09:26Z`.
+    Date(String),
+    List(Vec<MetaValue>),
+    Map(Metadata),
+    Null,
+}
+
+impl MetaValue {
+    pub fn as_str<'a>(&'a self) -> Option<&'a str> {
+        match self {
+            &MetaValue::String(ref s) => Some(s),
+            &MetaValue::Date(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            &MetaValue::Integer(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn as_list<'a>(&'a self) -> Option<&'a [MetaValue]> {
+        match self {
+            &MetaValue::List(ref list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/// Formats scalars as written, and lists as their items separated by
+/// commas. Maps and nulls format as nothing.
+impl fmt::Display for MetaValue {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &MetaValue::String(ref s) => write!(fmt, "{}", s),
+            &MetaValue::Integer(i) => write!(fmt, "{}", i),
+            &MetaValue::Float(f) => write!(fmt, "{}", f),
+            &MetaValue::Boolean(b) => write!(fmt, "{}", b),
+            &MetaValue::Date(ref s) => write!(fmt, "{}", s),
+            &MetaValue::List(ref list) => {
+                for (i, value) in list.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(fmt, ", "));
+                    }
+                    try!(write!(fmt, "{}", value));
+                }
+                Ok(())
+            }
+            &MetaValue::Map(_) | &MetaValue::Null => Ok(()),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum FrontMatterFormat {
+    Yaml,
+    Toml,
+}
+
+impl FrontMatterFormat {
+    fn fence(&self) -> &'static str {
+        match *self {
+            FrontMatterFormat::Yaml => "---",
+            FrontMatterFormat::Toml => "+++",
+        }
+    }
+}
+
+/// Front matter that couldn't be parsed.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct FrontMatterError {
+    pub format: FrontMatterFormat,
+    pub message: String,
+}
+
+impl fmt::Display for FrontMatterError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let format = match self.format {
+            FrontMatterFormat::Yaml => "YAML",
+            FrontMatterFormat::Toml => "TOML",
+        };
+        write!(fmt, "invalid {} front matter: {}", format, self.message)
+    }
+}
+
+impl Error for FrontMatterError {
+    fn description(&self) -> &str {
+        "invalid front matter"
+    }
+}
+
+/// Find the front matter at the start of `lines`. Returns its format,
+/// its contents, and the number of lines it takes up including the
+/// fences.
+pub fn find_front_matter<'a>(lines: &'a [String])
+                             -> Option<(FrontMatterFormat, &'a [String], usize)> {
+    let format = match lines.first().map(|line| line.trim_right()) {
+        Some("---") => FrontMatterFormat::Yaml,
+        Some("+++") => FrontMatterFormat::Toml,
+        _ => return None,
+    };
+    lines[1..].iter().position(|line| line.trim_right() == format.fence())
+              .map(|end| (format, &lines[1..end + 1], end + 2))
+}
+
+/// Parse the front matter at the start of `lines`, if there is any.
+/// Returns the metadata along with the number of lines to skip to get to
+/// the rest of the document.
+///
+/// `---` fences are also thematic breaks, so lines between them that
+/// aren't a YAML mapping are left as part of the document.
+pub fn parse_front_matter(lines: &[String]) -> Result<(Metadata, usize), FrontMatterError> {
+    match find_front_matter(lines) {
+        Some((FrontMatterFormat::Yaml, contents, len)) => {
+            match parse_yaml(&contents.join("\n")) {
+                Ok(metadata) => Ok((metadata, len)),
+                Err(_) => Ok((Metadata::new(), 0)),
+            }
+        }
+        Some((FrontMatterFormat::Toml, contents, len)) => {
+            Ok((try!(parse_toml(&contents.join("\n"))), len))
+        }
+        None => Ok((Metadata::new(), 0)),
+    }
+}
+
+fn parse_yaml(source: &str) -> Result<Metadata, FrontMatterError> {
+    let error = |message: String| FrontMatterError {
+        format: FrontMatterFormat::Yaml,
+        message: message,
+    };
+    let docs = try!(YamlLoader::load_from_str(source).map_err(|e| error(format!("{}", e))));
+    match docs.into_iter().next() {
+        None => Ok(Metadata::new()),
+        Some(yaml) => match from_yaml(yaml) {
+            MetaValue::Map(map) => Ok(map),
+            MetaValue::Null => Ok(Metadata::new()),
+            _ => Err(error("expected a mapping of keys to values".to_string())),
+        }
+    }
+}
+
+fn from_yaml(yaml: Yaml) -> MetaValue {
+    match yaml {
+        Yaml::String(s) => {
+            if is_date(&s) { MetaValue::Date(s) } else { MetaValue::String(s) }
+        }
+        Yaml::Integer(i) => MetaValue::Integer(i),
+        Yaml::Real(s) => s.parse().map(MetaValue::Float).unwrap_or(MetaValue::String(s)),
+        Yaml::Boolean(b) => MetaValue::Boolean(b),
+        Yaml::Array(list) => MetaValue::List(list.into_iter().map(from_yaml).collect()),
+        Yaml::Hash(hash) => {
+            MetaValue::Map(hash.into_iter().map(|(k, v)| {
+                (format!("{}", from_yaml(k)), from_yaml(v))
+            }).collect())
+        }
+        _ => MetaValue::Null,
+    }
+}
+
+/// Whether `s` starts with a `YYYY-MM-DD` date. YAML has no date type of
+/// its own, so this is how dates are told apart from other strings.
+pub fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() >= 10
+        && b[..4].iter().all(|c| c.is_ascii_digit()) && b[4] == b'-'
+        && b[5..7].iter().all(|c| c.is_ascii_digit()) && b[7] == b'-'
+        && b[8..10].iter().all(|c| c.is_ascii_digit())
+        && (b.len() == 10 || b[10] == b'T' || b[10] == b' ')
+}
+
+/// Write metadata as YAML front matter, without the fences. Values are
+/// written in flow style, with every string quoted, so they read back
+/// the same.
+pub fn to_yaml(metadata: &Metadata) -> String {
+    let mut yaml = String::new();
+    for (key, value) in metadata.iter() {
+        yaml.push_str(&format!("{}: {}\n", yaml_key(key), yaml_value(value)));
+    }
+    yaml
+}
+
+fn yaml_key(key: &str) -> String {
+    let plain = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+                && !key.starts_with('-') && key.parse::<f64>().is_err();
+    if plain { key.to_string() } else { Json::String(key.to_string()).to_string() }
+}
+
+fn yaml_value(value: &MetaValue) -> String {
+    match value {
+        &MetaValue::String(ref s) | &MetaValue::Date(ref s) => Json::String(s.clone()).to_string(),
+        &MetaValue::Integer(i) => i.to_string(),
+        &MetaValue::Float(f) => format!("{:?}", f),
+        &MetaValue::Boolean(b) => b.to_string(),
+        &MetaValue::List(ref list) => {
+            let items: Vec<String> = list.iter().map(yaml_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        &MetaValue::Map(ref map) => {
+            let items: Vec<String> = map.iter().map(|(k, v)| {
+                format!("{}: {}", yaml_key(k), yaml_value(v))
+            }).collect();
+            format!("{{{}}}", items.join(", "))
+        }
+        &MetaValue::Null => "null".to_string(),
+    }
+}
+
+fn parse_toml(source: &str) -> Result<Metadata, FrontMatterError> {
+    let mut parser = toml::Parser::new(source);
+    match parser.parse() {
+        Some(table) => Ok(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect()),
+        None => {
+            let message = parser.errors.iter().map(|e| {
+                let (line, col) = parser.to_linecol(e.lo);
+                format!("{} at line {} column {}", e.desc, line + 1, col + 1)
+            }).collect::<Vec<_>>().join("; ");
+            Err(FrontMatterError {
+                format: FrontMatterFormat::Toml,
+                message: message,
+            })
+        }
+    }
+}
+
+fn from_toml(value: toml::Value) -> MetaValue {
+    match value {
+        toml::Value::String(s) => MetaValue::String(s),
+        toml::Value::Integer(i) => MetaValue::Integer(i),
+        toml::Value::Float(f) => MetaValue::Float(f),
+        toml::Value::Boolean(b) => MetaValue::Boolean(b),
+        toml::Value::Datetime(s) => MetaValue::Date(s),
+        toml::Value::Array(list) => MetaValue::List(list.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => {
+            MetaValue::Map(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::lines;
+
+    #[test]
+    fn test_yaml_front_matter() {
+        let doc = lines(&["---", "title: Notes", "date: 2015-03-14", "draft: false",
+                          "weight: 3", "tags: [news, release]", "---", "# Body"]);
+        let (metadata, len) = parse_front_matter(&doc).unwrap();
+        assert_eq!(len, 7);
+        assert_eq!(metadata["title"], MetaValue::String("Notes".to_string()));
+        assert_eq!(metadata["date"], MetaValue::Date("2015-03-14".to_string()));
+        assert_eq!(metadata["draft"], MetaValue::Boolean(false));
+        assert_eq!(metadata["weight"], MetaValue::Integer(3));
+        assert_eq!(metadata["tags"],
+                   MetaValue::List(vec![MetaValue::String("news".to_string()),
+                                        MetaValue::String("release".to_string())]));
+        assert_eq!(format!("{}", metadata["tags"]), "news, release");
+    }
+
+    #[test]
+    fn test_toml_front_matter() {
+        let doc = lines(&["+++", "title = \"Notes\"", "date = 2015-03-14T15:09:26Z",
+                          "ratio = 0.5", "[author]", "name = \"Ann\"", "+++"]);
+        let (metadata, len) = parse_front_matter(&doc).unwrap();
+        assert_eq!(len, 7);
+        assert_eq!(metadata["title"].as_str(), Some("Notes"));
+        assert_eq!(metadata["date"], MetaValue::Date("2015-03-14T15:09:26Z".to_string()));
+        assert_eq!(metadata["ratio"], MetaValue::Float(0.5));
+        match metadata["author"] {
+            MetaValue::Map(ref author) => assert_eq!(author["name"].as_str(), Some("Ann")),
+            ref other => panic!("expected a map, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_no_front_matter() {
+        assert_eq!(parse_front_matter(&lines(&["# Title", "---"])).unwrap().1, 0);
+        // An opening fence without a closing one is just a thematic break.
+        assert_eq!(parse_front_matter(&lines(&["---", "text"])).unwrap().1, 0);
+        assert_eq!(parse_front_matter(&lines(&["---", "---", "text"])).unwrap(),
+                   (Metadata::new(), 2));
+    }
+
+    #[test]
+    fn test_not_yaml_front_matter() {
+        // Between thematic breaks, rather than front matter.
+        assert_eq!(parse_front_matter(&lines(&["---", "Intro", "---", "body"])).unwrap(),
+                   (Metadata::new(), 0));
+        assert_eq!(parse_front_matter(&lines(&["---", "a: [b", "---"])).unwrap(),
+                   (Metadata::new(), 0));
+    }
+
+    #[test]
+    fn test_invalid_front_matter() {
+        let err = parse_front_matter(&lines(&["+++", "title = ", "+++"])).unwrap_err();
+        assert_eq!(err.format, FrontMatterFormat::Toml);
+        assert!(format!("{}", err).starts_with("invalid TOML front matter: "));
+    }
+}
@@ -0,0 +1,542 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Recognising the blocks of a document: headings, thematic breaks,
+//! lists, tables, code blocks, HTML blocks, block quotes and paragraphs.
+
+use span::{Position, Span, line_starts};
+use text;
+use super::{Alignment, Event, Tag, References, indent_of, span_of};
+use super::references::is_definitions;
+use super::inline::{inline_events, paragraph_inline_events};
+
+macro_rules! parse (
+    ($e:expr) => (match $e { Some(e) => return Some(e), None => () })
+);
+
+/// The span of a whole block, from the start of its first line's text to
+/// the end of its last line's, where `starts` are its lines' positions.
+fn block_span(block: &[String], starts: &[Position]) -> Span {
+    let last = block.len() - 1;
+    Span::new(starts[0].advance(indent_of(&block[0])),
+              starts[last].advance(block[last].trim_right().len()))
+}
+
+/// The events for a single block, which has no blank lines in it and
+/// starts at `start`. Blocks of link reference definitions have none.
+pub fn block_events(block: &[String], start: Position, references: &References)
+                    -> Vec<(Event, Span)> {
+    if is_definitions(block) {
+        return vec![];
+    }
+    let starts = line_starts(block, start);
+    let span = block_span(block, &starts);
+    let mut events = vec![];
+    if let Some((title, depth)) = heading_text(block) {
+        let line = &block[0];
+        let title_start = line.len() - title.trim_left().len();
+        events.push((Event::Start(Tag::Heading(depth)), span));
+        inline_events(title.trim(), starts[0].advance(title_start), references, &mut events);
+        events.push((Event::End(Tag::Heading(depth)), span));
+        return events;
+    }
+    if block.len() == 1 && is_rule(&block[0]) {
+        events.push((Event::Rule, span));
+        return events;
+    }
+    if table_events(block, &starts, references, &mut events) {
+        return events;
+    }
+    if list_events(block, &starts, references, &mut events) {
+        return events;
+    }
+    events.clear();
+    events.push((Event::Start(Tag::Paragraph), span));
+    paragraph_inline_events(block, &starts, references, &mut events);
+    events.push((Event::End(Tag::Paragraph), span));
+    events
+}
+
+/// The opening fence of a fenced code block.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Fence {
+    /// The fence character, `` ` `` or `~`.
+    pub c: char,
+    pub len: usize,
+    /// How far the fence is indented. Up to this much indentation is
+    /// removed from the lines of code.
+    pub indent: usize,
+}
+
+/// A code fence of at least three backticks or tildes, indented by less
+/// than four spaces. A backtick fence's info string can't contain
+/// backticks.
+pub fn opening_fence(line: &str) -> Option<Fence> {
+    let rest = line.trim_left_matches(' ');
+    let indent = line.len() - rest.len();
+    let c = match rest.chars().next() {
+        Some(c) if indent < 4 && (c == '`' || c == '~') => c,
+        _ => return None,
+    };
+    let len = rest.len() - rest.trim_left_matches(c).len();
+    if len < 3 || (c == '`' && rest[len..].contains('`')) {
+        return None;
+    }
+    Some(Fence { c: c, len: len, indent: indent })
+}
+
+/// Whether `line` closes the code block opened by `fence`: a fence of
+/// the same character, at least as long, with nothing after it.
+pub fn closes_fence(line: &str, fence: Fence) -> bool {
+    let rest = line.trim_left_matches(' ');
+    let len = rest.len() - rest.trim_left_matches(fence.c).len();
+    line.len() - rest.len() < 4 && len >= fence.len && rest[len..].trim().is_empty()
+}
+
+/// The event for a code block. The block is either fenced, in which case
+/// the closing fence is optional, or made of indented lines.
+pub fn code_block_events(block: &[String], start: Position) -> Vec<(Event, Span)> {
+    let starts = line_starts(block, start);
+    let span = block_span(block, &starts);
+    let last = block.len() - 1;
+    let mut code = String::new();
+    let info = match opening_fence(&block[0]) {
+        Some(fence) => {
+            let end = if last > 0 && closes_fence(&block[last], fence) { last } else { last + 1 };
+            for line in block[1..end].iter() {
+                code.push_str(strip_indent(line, fence.indent));
+                code.push('\n');
+            }
+            text::unescape(block[0].trim_left()[fence.len..].trim())
+        }
+        None => {
+            for line in block.iter() {
+                code.push_str(strip_indent(line, 4));
+                code.push('\n');
+            }
+            String::new()
+        }
+    };
+    vec![(Event::CodeBlock(info, code), span)]
+}
+
+/// `line` with up to `indent` spaces removed from its start.
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let spaces = line.len() - line.trim_left_matches(' ').len();
+    &line[if spaces < indent { spaces } else { indent }..]
+}
+
+/// The tags that start an HTML block whatever comes before them.
+static BLOCK_TAGS: &'static [&'static str] = &[
+    "address", "article", "aside", "base", "basefont", "blockquote", "body", "caption",
+    "center", "col", "colgroup", "dd", "details", "dialog", "dir", "div", "dl", "dt",
+    "fieldset", "figcaption", "figure", "footer", "form", "frame", "frameset", "h1", "h2",
+    "h3", "h4", "h5", "h6", "head", "header", "hr", "html", "iframe", "legend", "li", "link",
+    "main", "menu", "menuitem", "nav", "noframes", "ol", "optgroup", "option", "p", "param",
+    "search", "section", "summary", "table", "tbody", "td", "tfoot", "th", "thead", "title",
+    "tr", "track", "ul",
+];
+
+/// The tags whose contents are raw, along with the end of their blocks.
+static RAW_TAGS: &'static [(&'static str, &'static str)] = &[
+    ("pre", "</pre>"), ("script", "</script>"), ("style", "</style>"),
+    ("textarea", "</textarea>"),
+];
+
+/// How an HTML block ends.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum HtmlEnd {
+    /// With the first line that contains this lowercase text, which can
+    /// be the line the block starts on.
+    Containing(&'static str),
+    /// Just before the next blank line.
+    Blank,
+}
+
+/// If `line` starts an HTML block, how the block ends and whether it can
+/// interrupt a paragraph. Comments, raw tags like `<pre>` and block
+/// tags like `<div>` can. Otherwise a line holding nothing but a single
+/// opening or closing tag starts a block, but only after a blank line.
+pub fn html_block_start(line: &str) -> Option<(HtmlEnd, bool)> {
+    let rest = line.trim_left_matches(' ');
+    if line.len() - rest.len() > 3 || !rest.starts_with('<') {
+        return None;
+    }
+    if rest.starts_with("<!--") {
+        return Some((HtmlEnd::Containing("-->"), true));
+    }
+    let closing = rest[1..].starts_with('/');
+    let name_start = if closing { 2 } else { 1 };
+    let name_len = rest[name_start..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                                     .unwrap_or(rest.len() - name_start);
+    let name = rest[name_start..name_start + name_len].to_lowercase();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let after = &rest[name_start + name_len..];
+    let ends_name = after.is_empty() || after.starts_with(char::is_whitespace) ||
+                    after.starts_with('>') || after.starts_with("/>");
+    if !closing && ends_name {
+        if let Some(&(_, end)) = RAW_TAGS.iter().find(|&&(tag, _)| tag == name) {
+            return Some((HtmlEnd::Containing(end), true));
+        }
+    }
+    if ends_name && BLOCK_TAGS.contains(&&name[..]) {
+        return Some((HtmlEnd::Blank, true));
+    }
+    let after = after.trim_right();
+    let whole_tag = if closing {
+        after == ">"
+    }
+    else {
+        ends_name && after.ends_with('>') &&
+            !after[..after.len() - 1].contains(|c| c == '<' || c == '>')
+    };
+    if whole_tag { Some((HtmlEnd::Blank, false)) } else { None }
+}
+
+/// The event for an HTML block, which is kept as it was written.
+pub fn html_block_events(block: &[String], start: Position) -> Vec<(Event, Span)> {
+    let starts = line_starts(block, start);
+    let span = block_span(block, &starts);
+    vec![(Event::Html(block.join("\n")), span)]
+}
+
+/// If `line` starts with a block quote marker, `>` with up to three
+/// spaces before it, the length of the marker along with the space after
+/// it, if there is one.
+pub fn quote_marker(line: &str) -> Option<usize> {
+    let rest = line.trim_left_matches(' ');
+    let indent = line.len() - rest.len();
+    if indent > 3 || !rest.starts_with('>') {
+        return None;
+    }
+    Some(indent + if rest[1..].starts_with(' ') { 2 } else { 1 })
+}
+
+/// The lines of a block quote with their quote markers taken off, along
+/// with the length of each marker. Lines without a marker continue the
+/// quote's last paragraph, and are kept whole.
+pub fn quote_lines(block: &[String]) -> (Vec<String>, Vec<usize>) {
+    block.iter().map(|line| {
+        let marker = quote_marker(line).unwrap_or(0);
+        (line[marker..].to_string(), marker)
+    }).unzip()
+}
+
+/// The span of a whole block quote, which starts at `start`.
+pub fn quote_span(block: &[String], start: Position) -> Span {
+    block_span(block, &line_starts(block, start))
+}
+
+/// A thematic break: three or more `-`, `*` or `_` characters, all the
+/// same, optionally separated by spaces.
+pub fn is_rule(line: &str) -> bool {
+    let rest = line.trim_left_matches(' ');
+    if line.len() - rest.len() > 3 {
+        return false;
+    }
+    let marks: Vec<char> = rest.chars().filter(|c| *c != ' ' && *c != '\t').collect();
+    marks.len() >= 3 && (marks[0] == '-' || marks[0] == '*' || marks[0] == '_') &&
+        marks.iter().all(|c| *c == marks[0])
+}
+
+/// Add the events for a list to `events`, if every line of the block is
+/// a list item. Items indented further than the one before start a
+/// nested list. Returns whether the block was a list.
+fn list_events(block: &[String], starts: &[Position], references: &References,
+               events: &mut Vec<(Event, Span)>) -> bool {
+    // The indent of each open list, and the index of its `Start` event,
+    // whose span is filled in once the list is closed.
+    let mut open: Vec<(usize, usize)> = vec![];
+    for (line, &at) in block.iter().zip(starts.iter()) {
+        let item = match list_item(line) {
+            Some(item) => item,
+            None => return false,
+        };
+        let nested = match open.last() {
+            Some(&(indent, _)) => item.indent > indent,
+            None => true,
+        };
+        if !nested {
+            while open.len() > 1 && item.indent < open[open.len() - 1].0 {
+                let (_, start) = open.pop().unwrap();
+                close_list(events, start);
+            }
+            let ordered = match events[open[open.len() - 1].1].0 {
+                Event::Start(Tag::List(start)) => start.is_some(),
+                _ => false,
+            };
+            if ordered != item.number.is_some() {
+                return false;
+            }
+        }
+        else {
+            open.push((item.indent, events.len()));
+            events.push((Event::Start(Tag::List(item.number)), Span::default()));
+        }
+        let span = span_of(at, item.indent, line.trim_right().len());
+        events.push((Event::Start(Tag::Item), span));
+        inline_events(item.text, at.advance(item.start), references, events);
+        events.push((Event::End(Tag::Item), span));
+    }
+    while let Some((_, start)) = open.pop() {
+        close_list(events, start);
+    }
+    true
+}
+
+/// Add the `End` event for the list started at `events[start]`, which
+/// spans from its first item to the end of the last event.
+fn close_list(events: &mut Vec<(Event, Span)>, start: usize) {
+    let span = Span::new(events[start + 1].1.start, events[events.len() - 1].1.end);
+    let tag = match events[start].0 {
+        Event::Start(ref tag) => tag.clone(),
+        _ => unreachable!(),
+    };
+    events[start].1 = span;
+    events.push((Event::End(tag), span));
+}
+
+/// Add the events for a table to `events`, if the block's first line is
+/// a header row and its second a delimiter row with as many cells. Every
+/// line after that is a row of the table's body. Rows with too many
+/// cells lose the extra ones, and rows with too few are filled out with
+/// empty cells. Returns whether the block was a table.
+fn table_events(block: &[String], starts: &[Position], references: &References,
+                events: &mut Vec<(Event, Span)>) -> bool {
+    if block.len() < 2 || !block[0].contains('|') {
+        return false;
+    }
+    let alignments = match table_delimiters(&block[1]) {
+        Some(alignments) => alignments,
+        None => return false,
+    };
+    if table_cells(&block[0]).len() != alignments.len() {
+        return false;
+    }
+    events.push((Event::Start(Tag::Table(alignments.clone())), block_span(block, starts)));
+    let head = block_span(&block[..1], &starts[..1]);
+    events.push((Event::Start(Tag::TableHead), head));
+    table_row_events(&block[0], starts[0], alignments.len(), references, events);
+    events.push((Event::End(Tag::TableHead), head));
+    if block.len() > 2 {
+        let body = block_span(&block[2..], &starts[2..]);
+        events.push((Event::Start(Tag::TableBody), body));
+        for (line, &at) in block[2..].iter().zip(starts[2..].iter()) {
+            table_row_events(line, at, alignments.len(), references, events);
+        }
+        events.push((Event::End(Tag::TableBody), body));
+    }
+    events.push((Event::End(Tag::Table(alignments)), block_span(block, starts)));
+    true
+}
+
+/// Add the events for a row of `columns` cells, from the line `line`
+/// starting at `at`. An escaped pipe in a cell is a pipe, even in a
+/// code span.
+fn table_row_events(line: &str, at: Position, columns: usize, references: &References,
+                    events: &mut Vec<(Event, Span)>) {
+    let span = span_of(at, indent_of(line), line.trim_right().len());
+    events.push((Event::Start(Tag::TableRow), span));
+    let mut cells = table_cells(line);
+    cells.truncate(columns);
+    let end = line.trim_right().len();
+    while cells.len() < columns {
+        cells.push((end, ""));
+    }
+    for &(start, text) in cells.iter() {
+        let cell = span_of(at, start, start + text.len());
+        events.push((Event::Start(Tag::TableCell), cell));
+        inline_events(&text.replace("\\|", "|"), at.advance(start), references, events);
+        events.push((Event::End(Tag::TableCell), cell));
+    }
+    events.push((Event::End(Tag::TableRow), span));
+}
+
+/// The cells of a table row, split at the pipes that aren't escaped with
+/// a backslash, along with where each one's text starts. Pipes at the
+/// start and end of the row are optional.
+fn table_cells(line: &str) -> Vec<(usize, &str)> {
+    let mut cells = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if c == '|' && !escaped {
+            cells.push((start, &line[start..i]));
+            start = i + 1;
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    let closed = start == line.trim_right().len() && start > 0;
+    cells.push((start, &line[start..]));
+    if line.trim_left().starts_with('|') {
+        cells.remove(0);
+    }
+    if closed && !cells.is_empty() {
+        cells.pop();
+    }
+    cells.into_iter().map(|(start, cell)| {
+        (start + cell.len() - cell.trim_left().len(), cell.trim())
+    }).collect()
+}
+
+/// The alignment of each column, if `line` is a table's delimiter row: a
+/// row of cells made of dashes, with a colon at the start for left
+/// alignment, at the end for right, or both for centred.
+fn table_delimiters(line: &str) -> Option<Vec<Alignment>> {
+    if !line.contains('|') || indent_of(line) > 3 {
+        return None;
+    }
+    let mut alignments = vec![];
+    for &(_, cell) in table_cells(line).iter() {
+        let dashes = cell.trim_matches(':');
+        if dashes.is_empty() || !dashes.chars().all(|c| c == '-') ||
+           cell.len() > dashes.len() + 2 {
+            return None;
+        }
+        alignments.push(match (cell.starts_with(':'), cell.ends_with(':')) {
+            (true, true) => Alignment::Center,
+            (true, false) => Alignment::Left,
+            (false, true) => Alignment::Right,
+            (false, false) => Alignment::None,
+        });
+    }
+    if alignments.is_empty() { None } else { Some(alignments) }
+}
+
+/// Recognize all the different types of headings, regardless of how
+/// they're formatted. Return the heading's unparsed text and its depth
+/// if one was found in this block.
+pub fn heading_text(block: &[String]) -> Option<(String, u32)> {
+    parse!(pound_heading(block));
+    parse!(line_heading(block));
+    None
+}
+
+/// A heading created with a pound sign, like:
+///
+/// ```text
+/// # Heading
+/// ```
+///
+fn pound_heading(b: &[String]) -> Option<(String, u32)> {
+    if b.len() != 1 { return None }
+    text::starting_chars(&b[0], '#')
+}
+
+/// A heading created with an underline, like:
+///
+/// ```text
+/// Heading
+/// =======
+/// ```
+///
+fn line_heading(b: &[String]) -> Option<(String, u32)> {
+    if b.len() != 2 { return None }
+    // A list item can't be the text of a heading.
+    if list_item(&b[0]).is_some() { return None }
+    if text::all_chars_are('=', &b[1]) {
+        return Some((b[0].clone(), 1));
+    }
+    if text::all_chars_are('-', &b[1]) {
+        return Some((b[0].clone(), 2));
+    }
+    return None;
+}
+
+/// The start of a list item: how far its marker is indented, its number
+/// if the list is ordered, and the item's unparsed text along with where
+/// in the line it starts.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ListItem<'a> {
+    pub indent: usize,
+    pub number: Option<u64>,
+    pub text: &'a str,
+    pub start: usize,
+}
+
+/// Recognize a line starting with a list marker, either a bullet (`-`,
+/// `*` or `+`) or a number followed by `.` or `)`. The marker must be
+/// followed by a space, so `*emphasis*` isn't taken for a bullet.
+pub fn list_item<'a>(line: &'a str) -> Option<ListItem<'a>> {
+    let rest = line.trim_left_matches(' ');
+    let indent = line.len() - rest.len();
+    let digits = rest.len() - rest.trim_left_matches(|c: char| c.is_digit(10)).len();
+    let (number, marker) = if digits == 0 {
+        if !(rest.starts_with('-') || rest.starts_with('*') || rest.starts_with('+')) {
+            return None;
+        }
+        (None, 1)
+    }
+    else {
+        let delimiter = &rest[digits..];
+        if digits > 9 || !(delimiter.starts_with('.') || delimiter.starts_with(')')) {
+            return None;
+        }
+        (rest[..digits].parse().ok(), digits + 1)
+    };
+    let text = &rest[marker..];
+    if !(text.is_empty() || text.starts_with(' ')) {
+        return None;
+    }
+    Some(ListItem {
+        indent: indent,
+        number: number,
+        text: text.trim(),
+        start: line.len() - text.trim_left().len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pound_heading, line_heading, list_item, ListItem};
+
+    #[test]
+    fn test_pound_heads() {
+        assert_eq!(pound_heading(&vec!["# Hello, world".to_string()]),
+                   Some(("Hello, world".to_string(), 1)));
+    }
+
+    #[test]
+    fn test_line_heads() {
+        assert_eq!(line_heading(&vec!("Hello".to_string(), "=====".to_string())),
+                   Some(("Hello".to_string(), 1)));
+        assert_eq!(line_heading(&vec!("Hello".to_string(), "-----".to_string())),
+                   Some(("Hello".to_string(), 2)));
+        assert_eq!(line_heading(&vec!("Hello".to_string(), "== ==".to_string())),
+                   None);
+    }
+
+    #[test]
+    fn test_list_items_are_not_line_heads() {
+        let block = vec!("-".to_string(), "-".to_string());
+        assert_eq!(line_heading(&block), None);
+        let block = vec!("1. a".to_string(), "===".to_string());
+        assert_eq!(line_heading(&block), None);
+    }
+
+    #[test]
+    fn test_non_pound_heads() {
+        assert_eq!(pound_heading(&vec!("Hello, world".to_string())), None);
+        assert_eq!(pound_heading(&vec!(" ## Hello, world".to_string())), None);
+    }
+
+    #[test]
+    fn test_list_item() {
+        assert_eq!(list_item("  - a "), Some(ListItem { indent: 2, number: None, text: "a", start: 4 }));
+        assert_eq!(list_item("12) b"), Some(ListItem { indent: 0, number: Some(12), text: "b", start: 4 }));
+        assert_eq!(list_item("*"), Some(ListItem { indent: 0, number: None, text: "", start: 1 }));
+        assert_eq!(list_item("*a*"), None);
+        assert_eq!(list_item("2014 was a year"), None);
+    }
+}
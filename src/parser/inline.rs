@@ -0,0 +1,342 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The markup inside blocks: code spans, emphasis, links and images, and
+//! line breaks.
+
+use span::{Position, Span};
+use text;
+use super::{Event, Tag, References, indent_of, normalize_label, span_of};
+
+/// Add the inline events for the lines of a paragraph to `events`,
+/// with a break between each pair of lines. `starts` holds the position
+/// of the start of each line.
+///
+/// A line ending in two or more spaces, or in a backslash, is followed
+/// by a hard break. Any other line ending is a soft break.
+pub fn paragraph_inline_events(lines: &[String], starts: &[Position], references: &References,
+                               events: &mut Vec<(Event, Span)>) {
+    for (i, (line, &at)) in lines.iter().zip(starts.iter()).enumerate() {
+        let indent = indent_of(line);
+        let text = &line[indent..];
+        if i + 1 == lines.len() {
+            inline_events(text.trim_right(), at.advance(indent), references, events);
+            break;
+        }
+        let (text, hard) = split_line_break(text);
+        inline_events(text, at.advance(indent), references, events);
+        let span = span_of(at, indent + text.len(), line.len());
+        events.push((if hard { Event::HardBreak } else { Event::SoftBreak }, span));
+    }
+}
+
+/// Strip the line break marker from the end of `line`, returning the
+/// remaining text and whether the break was a hard one.
+fn split_line_break(line: &str) -> (&str, bool) {
+    let trimmed = line.trim_right_matches(' ');
+    if line.len() - trimmed.len() >= 2 {
+        return (trimmed, true);
+    }
+    let backslashes = line.len() - line.trim_right_matches('\\').len();
+    if backslashes % 2 == 1 {
+        return (&line[..line.len() - 1], true);
+    }
+    (trimmed, false)
+}
+
+/// Add the events for a line of inline markdown to `events`. `at` is
+/// the position of the start of `s`.
+///
+/// This understands code spans, `*emphasis*` and `**strong**` (with `*`
+/// or `_`), `[links](url)`, links to `references` and `![images](url)`. Emphasis and link text
+/// is plain, without any further markup. Anything that doesn't form
+/// complete markup is kept as text.
+pub fn inline_events(s: &str, at: Position, references: &References,
+                     events: &mut Vec<(Event, Span)>) {
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        let c = rest.chars().next().unwrap();
+        let markup = match c {
+            '`' => code_span(rest).map(|(code, len)| (Event::Code(code), None, len)),
+            '!' if rest[1..].starts_with('[') => {
+                link(&rest[1..], references).map(|(label, url, _, len)| {
+                    (Event::Start(Tag::Image(url)), Some((label, 2)), len + 1)
+                })
+            }
+            '[' => {
+                link(rest, references).map(|(label, url, title, len)| {
+                    (Event::Start(Tag::Link(url, title)), Some((label, 1)), len)
+                })
+            }
+            '*' | '_' => {
+                let before = s[..i].chars().next_back();
+                emphasis(rest, before).map(|(strong, content, len)| {
+                    let tag = if strong { Tag::Strong } else { Tag::Emphasis };
+                    (Event::Start(tag), Some((content, if strong { 2 } else { 1 })), len)
+                })
+            }
+            _ => None,
+        };
+        match markup {
+            Some((event, content, len)) => {
+                push_text(s, text_start, i, at, events);
+                let span = span_of(at, i, i + len);
+                match content {
+                    Some((content, offset)) => {
+                        let tag = match event {
+                            Event::Start(ref tag) => tag.clone(),
+                            _ => unreachable!(),
+                        };
+                        events.push((event, span));
+                        push_text(s, i + offset, i + offset + content.len(), at, events);
+                        events.push((Event::End(tag), span));
+                    }
+                    None => events.push((event, span)),
+                }
+                i += len;
+                text_start = i;
+            }
+            // Keep an escape and the escaped character together, so the
+            // character isn't taken as markup.
+            None if c == '\\' => i += 1 + rest[1..].chars().next().map_or(0, |c| c.len_utf8()),
+            // An unmatched run of delimiters is kept whole, so that part
+            // of it can't be matched later on.
+            None if c == '`' || c == '*' || c == '_' => i += delimiter_run(rest, c),
+            None => i += c.len_utf8(),
+        }
+    }
+    push_text(s, text_start, s.len(), at, events);
+}
+
+/// Add the text `s[start..end]`, with its escapes resolved, unless it's
+/// empty.
+fn push_text(s: &str, start: usize, end: usize, at: Position, events: &mut Vec<(Event, Span)>) {
+    if start < end {
+        events.push((Event::Text(text::unescape(&s[start..end])), span_of(at, start, end)));
+    }
+}
+
+/// The length of the run of `c` characters at the start of `s`.
+fn delimiter_run(s: &str, c: char) -> usize {
+    s.len() - s.trim_left_matches(c).len()
+}
+
+/// Find the position of the next unescaped `c` in `s`.
+pub fn find_unescaped(s: &str, c: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, ch) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        }
+        else if ch == '\\' {
+            escaped = true;
+        }
+        else if ch == c {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// A code span at the start of `s`, closed by a run of backticks of the
+/// same length as the one that opens it. Returns the code and the length
+/// of the whole span.
+fn code_span(s: &str) -> Option<(String, usize)> {
+    let run = delimiter_run(s, '`');
+    let mut i = run;
+    while let Some(found) = s[i..].find('`') {
+        let start = i + found;
+        let len = delimiter_run(&s[start..], '`');
+        if len == run {
+            let code = &s[run..start];
+            let code = if code.len() > 1 && code.starts_with(' ') && code.ends_with(' ') &&
+                          !code.trim().is_empty() {
+                &code[1..code.len() - 1]
+            }
+            else {
+                code
+            };
+            return Some((code.to_string(), start + len));
+        }
+        i = start + len;
+    }
+    None
+}
+
+/// A link at the start of `s`. This is either an inline link, like
+/// `[text](url)` or `[text](url "title")`, or a reference to one of
+/// `references`, like `[text][label]`, `[label][]` or `[label]`. Returns
+/// the unparsed text, the destination, the title and the length of the
+/// link.
+fn link<'a>(s: &'a str, references: &References)
+            -> Option<(&'a str, String, Option<String>, usize)> {
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut close = None;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = match close { Some(close) => close, None => return None };
+    let label = &s[1..close];
+    let target = &s[close + 1..];
+    if target.starts_with('(') {
+        let (url, title, after) = match destination(&target[1..]) {
+            Some(destination) => destination,
+            None => return None,
+        };
+        if !after.starts_with(')') {
+            return None;
+        }
+        let len = s.len() - after.len() + 1;
+        return Some((label, text::unescape(url), title.map(text::unescape), len));
+    }
+    let (key, len) = if target.starts_with('[') {
+        match find_unescaped(&target[1..], ']') {
+            Some(end) if target[1..end + 1].trim().is_empty() => (label, close + end + 3),
+            Some(end) => (&target[1..end + 1], close + end + 3),
+            None => return None,
+        }
+    }
+    else {
+        (label, close + 1)
+    };
+    references.get(&normalize_label(key)).map(|&(ref url, ref title)| {
+        (label, url.clone(), title.clone(), len)
+    })
+}
+
+/// A link destination, written either bare or in angle brackets, and
+/// optionally followed by a title. Returns the unparsed destination, the
+/// unparsed title and what follows them.
+pub fn destination(s: &str) -> Option<(&str, Option<&str>, &str)> {
+    let s = s.trim_left();
+    let (url, after) = if s.starts_with('<') {
+        match find_unescaped(&s[1..], '>') {
+            Some(end) => (&s[1..end + 1], &s[end + 2..]),
+            None => return None,
+        }
+    }
+    else {
+        let end = s.find(|c: char| c.is_whitespace() || c == ')').unwrap_or(s.len());
+        (&s[..end], &s[end..])
+    };
+    let after = after.trim_left();
+    match after.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => {
+            find_unescaped(&after[1..], quote).map(|end| {
+                (url, Some(&after[1..end + 1]), after[end + 2..].trim_left())
+            })
+        }
+        _ => Some((url, None, after)),
+    }
+}
+
+/// Emphasis at the start of `s`, opened and closed by one or two `*` or
+/// `_` characters. `before` is the character before `s`, since `_`
+/// doesn't count as emphasis inside a word. Returns whether the emphasis
+/// is strong, the unparsed text and the length of the emphasis.
+fn emphasis(s: &str, before: Option<char>) -> Option<(bool, &str, usize)> {
+    let c = s.chars().next().unwrap();
+    let run = delimiter_run(s, c);
+    if run > 2 {
+        return None;
+    }
+    let intraword = |ch: Option<char>| ch.map_or(false, |ch| ch.is_alphanumeric());
+    if c == '_' && intraword(before) {
+        return None;
+    }
+    let content_start = &s[run..];
+    if content_start.chars().next().map_or(true, |ch| ch.is_whitespace()) {
+        return None;
+    }
+    let mut i = run;
+    while let Some(found) = find_unescaped(&s[i..], c) {
+        let start = i + found;
+        let len = delimiter_run(&s[start..], c);
+        let content = &s[run..start];
+        if len == run && !content.is_empty() && !content.ends_with(char::is_whitespace) &&
+           !(c == '_' && intraword(s[start + len..].chars().next())) {
+            return Some((run == 2, content, start + len));
+        }
+        i = start + len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inline_events;
+    use parser::{Event, Tag, References};
+    use parser::Event::{Start, End, Text, Code};
+    use span::Position;
+
+    fn inline(s: &str) -> Vec<Event> {
+        let mut events = vec![];
+        inline_events(s, Position::start(), &References::new(), &mut events);
+        events.into_iter().map(|(event, _)| event).collect()
+    }
+
+    fn text(s: &str) -> Event {
+        Text(s.to_string())
+    }
+
+    #[test]
+    fn test_code_spans() {
+        assert_eq!(inline("a `b` c"), vec![text("a "), Code("b".to_string()), text(" c")]);
+        assert_eq!(inline("`` a`b ``"), vec![Code("a`b".to_string())]);
+        assert_eq!(inline("``a`"), vec![text("``a`")]);
+        assert_eq!(inline("`\\*`"), vec![Code("\\*".to_string())]);
+    }
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(inline("*a* __b__"),
+                   vec![Start(Tag::Emphasis), text("a"), End(Tag::Emphasis), text(" "),
+                        Start(Tag::Strong), text("b"), End(Tag::Strong)]);
+        assert_eq!(inline("snake_case_name"), vec![text("snake_case_name")]);
+        assert_eq!(inline("a * b * c"), vec![text("a * b * c")]);
+        assert_eq!(inline("\\*a*"), vec![text("*a*")]);
+        assert_eq!(inline("**a*"), vec![text("**a*")]);
+    }
+
+    #[test]
+    fn test_links() {
+        assert_eq!(inline("[a *b*](c.md \"T\") and ![d](e.png)"),
+                   vec![Start(Tag::Link("c.md".to_string(), Some("T".to_string()))),
+                        text("a *b*"),
+                        End(Tag::Link("c.md".to_string(), Some("T".to_string()))), text(" and "),
+                        Start(Tag::Image("e.png".to_string())), text("d"),
+                        End(Tag::Image("e.png".to_string()))]);
+        assert_eq!(inline("[a](<b c>)"),
+                   vec![Start(Tag::Link("b c".to_string(), None)), text("a"),
+                        End(Tag::Link("b c".to_string(), None))]);
+        assert_eq!(inline("[a](b 'it\\'s')"),
+                   vec![Start(Tag::Link("b".to_string(), Some("it's".to_string()))), text("a"),
+                        End(Tag::Link("b".to_string(), Some("it's".to_string())))]);
+        assert_eq!(inline("[a] (b)"), vec![text("[a] (b)")]);
+        assert_eq!(inline("[a](b"), vec![text("[a](b")]);
+    }
+}
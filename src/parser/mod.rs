@@ -0,0 +1,630 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A pull parser, which turns markdown lines into a stream of events.
+//!
+//! Blocks are separated by blank lines, apart from code blocks and some
+//! HTML blocks, which can have blank lines in them. Block quotes hold
+//! blocks of their own, which are parsed with a parser of their own.
+//! Each block is only parsed once the events before it have been
+//! consumed. The document tree in `types` and the HTML renderer are both
+//! built from these events.
+//!
+//! Recognising blocks is left to `blocks`, the markup inside them to
+//! `inline`, and link reference definitions to `references`.
+
+use std::collections::VecDeque;
+
+use span::{Position, Span, line_starts};
+use self::blocks::{block_events, code_block_events, closes_fence, opening_fence};
+use self::blocks::{HtmlEnd, html_block_events, html_block_start};
+use self::blocks::{is_rule, heading_text, quote_lines, quote_marker, quote_span};
+
+pub use self::references::{References, normalize_label, link_definition, link_references};
+
+pub mod blocks;
+pub mod inline;
+pub mod references;
+
+/// A part of the document that has a start and an end.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Tag {
+    /// A heading, with its depth.
+    Heading(u32),
+    Paragraph,
+    /// A list, with the number of its first item if it's ordered.
+    List(Option<u64>),
+    Item,
+    Emphasis,
+    Strong,
+    /// A link, with its destination and title.
+    Link(String, Option<String>),
+    /// An image, with its source. The image's text is its alt text.
+    Image(String),
+    /// A block quote, which holds other blocks.
+    BlockQuote,
+    /// A table, with the alignment of each of its columns. It holds a
+    /// `TableHead`, and a `TableBody` if it has any rows below that.
+    Table(Vec<Alignment>),
+    TableHead,
+    TableBody,
+    /// A row of a table's head or body, which holds its cells.
+    TableRow,
+    TableCell,
+}
+
+/// How the cells of a table column are aligned, as set by the colons in
+/// its delimiter row.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// A single parse event. Every `Start` is matched by an `End` with an
+/// equal tag.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Event {
+    Start(Tag),
+    End(Tag),
+    Text(String),
+    Code(String),
+    SoftBreak,
+    HardBreak,
+    Rule,
+    /// A fenced or indented code block, with its info string and its
+    /// code. Each line of the code ends with a newline.
+    CodeBlock(String, String),
+    /// A block of raw HTML, as its lines were written.
+    Html(String),
+}
+
+/// The kinds of block that are told apart before they're parsed.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+enum BlockKind {
+    Text,
+    Code,
+    Html,
+    Quote,
+}
+
+/// An iterator over the events of a document.
+pub struct Parser<'a> {
+    lines: &'a [String],
+    position: usize,
+    start: Position,
+    /// Where the contents of the last list's items start, while blocks
+    /// might still belong to it, since they aren't indented code then.
+    list_indent: Option<usize>,
+    references: References,
+    pending: VecDeque<(Event, Span)>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(lines: &'a [String]) -> Parser<'a> {
+        Parser::starting_at(lines, 0)
+    }
+
+    /// A parser for `lines[first..]`, such as the body after a document's
+    /// front matter. Positions still count from the start of `lines`.
+    ///
+    /// The lines are scanned for link reference definitions up front, so
+    /// that links can refer to definitions further down.
+    pub fn starting_at(lines: &'a [String], first: usize) -> Parser<'a> {
+        let offset = lines[..first].iter().fold(0, |offset, line| offset + line.len() + 1);
+        Parser {
+            lines: lines,
+            position: first,
+            start: Position::new(first + 1, 1, offset),
+            list_indent: None,
+            references: link_references(&lines[first..]),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// A parser for the whole of `lines`, which resolves links against
+    /// `references` rather than definitions of its own.
+    fn with_references(lines: &'a [String], references: References) -> Parser<'a> {
+        Parser {
+            lines: lines,
+            position: 0,
+            start: Position::start(),
+            list_indent: None,
+            references: references,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// A parser that only splits `lines` into blocks, with no link
+    /// references to resolve.
+    fn scanning(lines: &'a [String]) -> Parser<'a> {
+        Parser::with_references(lines, References::new())
+    }
+
+    /// Turn this into an iterator over events along with the span of
+    /// source each one came from. The span of a `Start` or `End` event is
+    /// that of the whole tag.
+    pub fn spanned(self) -> Spanned<'a> {
+        Spanned { parser: self }
+    }
+
+    /// Take the next block, if there are any left, along with the
+    /// position it starts at and its kind.
+    ///
+    /// Most blocks run up to the next blank line, though a code fence or
+    /// the start of most HTML blocks ends the block before it. A fenced
+    /// code block runs up to its closing fence, and an indented one up to
+    /// the next line that isn't blank or indented. HTML blocks end as
+    /// `HtmlEnd` says. A block quote ends with a blank line, or with a
+    /// line that isn't quoted and doesn't carry on the quote's last
+    /// paragraph.
+    fn next_block(&mut self) -> Option<(&'a [String], Position, BlockKind)> {
+        let lines = self.lines;
+        while self.position < lines.len() && is_blank(&lines[self.position]) {
+            self.advance_line();
+        }
+        if self.position == lines.len() {
+            return None;
+        }
+        let first = self.position;
+        let start = self.start;
+        let indent = indent_of(&lines[first]);
+        if let Some(fence) = opening_fence(&lines[first]) {
+            self.advance_line();
+            while self.position < lines.len() {
+                let closed = closes_fence(&lines[self.position], fence);
+                self.advance_line();
+                if closed {
+                    break;
+                }
+            }
+            self.list_indent = None;
+            return Some((&lines[first..self.position], start, BlockKind::Code));
+        }
+        if let Some((end, _)) = html_block_start(&lines[first]) {
+            while self.position < lines.len() {
+                let line = &lines[self.position];
+                match end {
+                    HtmlEnd::Blank if is_blank(line) => break,
+                    HtmlEnd::Blank => self.advance_line(),
+                    HtmlEnd::Containing(text) => {
+                        self.advance_line();
+                        if line.to_lowercase().contains(text) {
+                            break;
+                        }
+                    }
+                }
+            }
+            self.list_indent = None;
+            return Some((&lines[first..self.position], start, BlockKind::Html));
+        }
+        if quote_marker(&lines[first]).is_some() {
+            self.advance_line();
+            while self.position < lines.len() && !is_blank(&lines[self.position]) {
+                let line = &lines[self.position];
+                if quote_marker(line).is_none() &&
+                   (interrupts(line) || is_rule(line) ||
+                    !ends_in_paragraph(&quote_lines(&lines[first..self.position]).0)) {
+                    break;
+                }
+                self.advance_line();
+            }
+            self.list_indent = None;
+            return Some((&lines[first..self.position], start, BlockKind::Quote));
+        }
+        if indent >= self.list_indent.map_or(0, |list| list) + 4 {
+            let mut end = first;
+            while self.position < lines.len() {
+                let line = &lines[self.position];
+                if !is_blank(line) && indent_of(line) < 4 {
+                    break;
+                }
+                self.advance_line();
+                if !is_blank(line) {
+                    end = self.position;
+                }
+            }
+            while self.position > end {
+                self.back_line();
+            }
+            self.list_indent = None;
+            return Some((&lines[first..end], start, BlockKind::Code));
+        }
+        self.advance_line();
+        while self.position < lines.len() && !is_blank(&lines[self.position]) &&
+              !interrupts(&lines[self.position]) {
+            self.advance_line();
+        }
+        let block = &lines[first..self.position];
+        match blocks::list_item(&block[block.len() - 1]) {
+            Some(item) => self.list_indent = Some(item.start),
+            None if self.list_indent.map_or(false, |list| indent < list) => {
+                self.list_indent = None
+            }
+            None => {}
+        }
+        Some((block, start, BlockKind::Text))
+    }
+
+    /// The events for a block quote: those of the blocks inside it, with
+    /// their spans moved back to where they are in the quoted lines.
+    fn quote_events(&self, block: &[String], start: Position) -> Vec<(Event, Span)> {
+        let span = quote_span(block, start);
+        let starts = line_starts(block, start);
+        let (lines, markers) = quote_lines(block);
+        let outer = |at: Position| {
+            let i = at.line - 1;
+            Position::new(starts[i].line, at.column + markers[i],
+                          starts[i].offset + markers[i] + at.column - 1)
+        };
+        let mut events = vec![(Event::Start(Tag::BlockQuote), span)];
+        for (event, inner) in Parser::with_references(&lines, self.references.clone()).spanned() {
+            events.push((event, Span::new(outer(inner.start), outer(inner.end))));
+        }
+        events.push((Event::End(Tag::BlockQuote), span));
+        events
+    }
+
+    fn advance_line(&mut self) {
+        let len = self.lines[self.position].len();
+        self.start = Position::new(self.start.line + 1, 1, self.start.offset + len + 1);
+        self.position += 1;
+    }
+
+    fn back_line(&mut self) {
+        self.position -= 1;
+        let len = self.lines[self.position].len();
+        self.start = Position::new(self.start.line - 1, 1, self.start.offset - len - 1);
+    }
+
+    fn next_spanned(&mut self) -> Option<(Event, Span)> {
+        loop {
+            match self.pending.pop_front() {
+                Some(event) => return Some(event),
+                None => {}
+            }
+            match self.next_block() {
+                Some((block, start, BlockKind::Code)) => {
+                    self.pending.extend(code_block_events(block, start))
+                }
+                Some((block, start, BlockKind::Html)) => {
+                    self.pending.extend(html_block_events(block, start))
+                }
+                Some((block, start, BlockKind::Quote)) => {
+                    let events = self.quote_events(block, start);
+                    self.pending.extend(events)
+                }
+                Some((block, start, BlockKind::Text)) => {
+                    self.pending.extend(block_events(block, start, &self.references))
+                }
+                None => return None,
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Parser<'a> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.next_spanned().map(|(event, _)| event)
+    }
+}
+
+/// An iterator over events and their spans. See `Parser::spanned`.
+pub struct Spanned<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> Iterator for Spanned<'a> {
+    type Item = (Event, Span);
+
+    fn next(&mut self) -> Option<(Event, Span)> {
+        self.parser.next_spanned()
+    }
+}
+
+/// Things that can be turned back into parse events, like the nodes of
+/// the document tree. Anything that can be is rendered the same way as
+/// the parser's own events.
+pub trait ToEvents {
+    /// Add the events for this, along with their spans, to `events`.
+    fn to_events(&self, events: &mut Vec<(Event, Span)>);
+}
+
+/// Collect the text of events up to the end of the enclosing tag, as
+/// plain text. Line breaks become spaces.
+pub fn collect_text<I: Iterator<Item=(Event, Span)>>(events: &mut I) -> String {
+    let mut text = String::new();
+    let mut depth = 0;
+    while let Some((event, _)) = events.next() {
+        match event {
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::Start(_) => depth += 1,
+            Event::End(_) if depth == 0 => break,
+            Event::End(_) => depth -= 1,
+            Event::Rule | Event::CodeBlock(..) | Event::Html(_) => {}
+        }
+    }
+    text
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Whether `line` starts a block even in the middle of a paragraph.
+fn interrupts(line: &str) -> bool {
+    opening_fence(line).is_some() || quote_marker(line).is_some() ||
+        html_block_start(line).map_or(false, |(_, interrupts)| interrupts)
+}
+
+/// Whether the last block of `lines` is a paragraph that a following
+/// line could carry on, looking inside block quotes.
+fn ends_in_paragraph(lines: &[String]) -> bool {
+    let mut parser = Parser::scanning(lines);
+    let mut last = None;
+    while let Some((block, _, kind)) = parser.next_block() {
+        last = Some((block, kind));
+    }
+    match last {
+        Some((block, BlockKind::Text)) => {
+            !is_blank(&lines[lines.len() - 1]) && heading_text(block).is_none() &&
+                !is_rule(&block[block.len() - 1])
+        }
+        Some((block, BlockKind::Quote)) => ends_in_paragraph(&quote_lines(block).0),
+        _ => false,
+    }
+}
+
+/// The blocks of `lines` that aren't code or HTML blocks.
+fn text_blocks<'a>(lines: &'a [String]) -> Vec<&'a [String]> {
+    let mut parser = Parser::scanning(lines);
+    let mut blocks = vec![];
+    while let Some((block, _, kind)) = parser.next_block() {
+        if kind == BlockKind::Text {
+            blocks.push(block);
+        }
+    }
+    blocks
+}
+
+/// The span of `line[start..end]`, where the line starts at `at`.
+fn span_of(at: Position, start: usize, end: usize) -> Span {
+    Span::new(at.advance(start), at.advance(end))
+}
+
+/// The number of bytes of leading whitespace in `line`.
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_left().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Alignment, Parser, Event, Tag};
+    use span::{Position, Span};
+    use super::Event::{Start, End, Text, Code, SoftBreak, Rule, CodeBlock, Html};
+    use testing::lines;
+
+    fn text(s: &str) -> Event {
+        Text(s.to_string())
+    }
+
+    #[test]
+    fn test_block_events() {
+        let input = lines(&["# Title", "", "Some", "text", "", "***", "", "- a", "  - b"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events,
+                   vec![Start(Tag::Heading(1)), text("Title"), End(Tag::Heading(1)),
+                        Start(Tag::Paragraph), text("Some"), SoftBreak, text("text"),
+                        End(Tag::Paragraph),
+                        Rule,
+                        Start(Tag::List(None)), Start(Tag::Item), text("a"), End(Tag::Item),
+                        Start(Tag::List(None)), Start(Tag::Item), text("b"), End(Tag::Item),
+                        End(Tag::List(None)), End(Tag::List(None))]);
+    }
+
+    #[test]
+    fn test_blank_lines_split_blocks() {
+        let input = lines(&["", "a", "", "  ", "b", "c", "\t"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events,
+                   vec![Start(Tag::Paragraph), text("a"), End(Tag::Paragraph),
+                        Start(Tag::Paragraph), text("b"), SoftBreak, text("c"),
+                        End(Tag::Paragraph)]);
+        assert_eq!(Parser::new(&lines(&["", "\t"])).next(), None);
+    }
+
+    #[test]
+    fn test_rules() {
+        for rule in ["---", "* * *", "___ _"].iter() {
+            let events: Vec<Event> = Parser::new(&lines(&[rule])).collect();
+            assert_eq!(events, vec![Rule]);
+        }
+        let events: Vec<Event> = Parser::new(&lines(&["--"])).collect();
+        assert_eq!(events, vec![Start(Tag::Paragraph), text("--"), End(Tag::Paragraph)]);
+    }
+
+    fn code(info: &str, code: &str) -> Event {
+        CodeBlock(info.to_string(), code.to_string())
+    }
+
+    #[test]
+    fn test_fenced_code() {
+        let input = lines(&["text", "``` rust  x", "a", "", "  b", "````", "", "~~~", "```",
+                            "  ~~~~ "]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events, vec![Start(Tag::Paragraph), text("text"), End(Tag::Paragraph),
+                                code("rust  x", "a\n\n  b\n"), code("", "```\n")]);
+        let input = lines(&["  ```", "   a", " b", "", "[x]: y"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events, vec![code("", " a\nb\n\n[x]: y\n")]);
+        let events: Vec<Event> = Parser::new(&lines(&["``` a`b", "```"])).collect();
+        assert_eq!(events, vec![Start(Tag::Paragraph), text("``` a`b"), End(Tag::Paragraph),
+                                code("", "")]);
+    }
+
+    #[test]
+    fn test_indented_code() {
+        let input = lines(&["    a", "", "      b  ", "", "", "c", "", "    d"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events, vec![code("", "a\n\n  b  \n"),
+                                Start(Tag::Paragraph), text("c"), End(Tag::Paragraph),
+                                code("", "d\n")]);
+        let events: Vec<Event> = Parser::new(&lines(&["para", "    not code"])).collect();
+        assert_eq!(events, vec![Start(Tag::Paragraph), text("para"), SoftBreak,
+                                text("not code"), End(Tag::Paragraph)]);
+    }
+
+    #[test]
+    fn test_list_continuations_are_not_code() {
+        let input = lines(&["- a", "", "    b", "", "c", "", "    d"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events[5..],
+                   [Start(Tag::Paragraph), text("b"), End(Tag::Paragraph),
+                    Start(Tag::Paragraph), text("c"), End(Tag::Paragraph), code("", "d\n")]);
+    }
+
+    #[test]
+    fn test_html_blocks() {
+        let input = lines(&["<div class=\"x\">", "*a*", "", "<!-- one", "", "two -->", "b",
+                            "<pre>", "", "</PRE> c", "<b>", "", "d", "<span>", "", "<http://e>"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events,
+                   vec![Html("<div class=\"x\">\n*a*".to_string()),
+                        Html("<!-- one\n\ntwo -->".to_string()),
+                        Start(Tag::Paragraph), text("b"), End(Tag::Paragraph),
+                        Html("<pre>\n\n</PRE> c".to_string()),
+                        Html("<b>".to_string()),
+                        Start(Tag::Paragraph), text("d"), SoftBreak, text("<span>"),
+                        End(Tag::Paragraph),
+                        Start(Tag::Paragraph), text("<http://e>"), End(Tag::Paragraph)]);
+    }
+
+    #[test]
+    fn test_block_quotes() {
+        let input = lines(&["> # A", ">", "> b", "c", "", ">> d", "> ```", "e", "", "> f",
+                            "---"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events,
+                   vec![Start(Tag::BlockQuote),
+                        Start(Tag::Heading(1)), text("A"), End(Tag::Heading(1)),
+                        Start(Tag::Paragraph), text("b"), SoftBreak, text("c"),
+                        End(Tag::Paragraph),
+                        End(Tag::BlockQuote),
+                        Start(Tag::BlockQuote), Start(Tag::BlockQuote),
+                        Start(Tag::Paragraph), text("d"), End(Tag::Paragraph),
+                        End(Tag::BlockQuote), code("", ""), End(Tag::BlockQuote),
+                        Start(Tag::Paragraph), text("e"), End(Tag::Paragraph),
+                        Start(Tag::BlockQuote),
+                        Start(Tag::Paragraph), text("f"), End(Tag::Paragraph),
+                        End(Tag::BlockQuote),
+                        Rule]);
+    }
+
+    #[test]
+    fn test_block_quote_spans() {
+        let input = lines(&["x", "", ">  a *b*", "> c"]);
+        let events: Vec<(Event, Span)> = Parser::new(&input).spanned().collect();
+        let spans: Vec<String> = events.iter().map(|&(_, span)| format!("{}", span)).collect();
+        assert_eq!(spans[3..], ["3:1-4:3", "3:4-4:3", "3:4-3:5", "3:6-3:8", "3:7-3:7",
+                                "3:6-3:8", "3:9-3:8", "4:3-4:3", "3:4-4:3", "3:1-4:3"]);
+        assert_eq!(events[6].1.start, Position::new(3, 6, 8));
+    }
+
+    #[test]
+    fn test_tables() {
+        let input = lines(&["| a | `b\\|c` |", "|:--|:-:", "1 | 2 | 3", "\\| x", "", "a | b",
+                            "c | d"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        let columns = vec![Alignment::Left, Alignment::Center];
+        let cell = |events: Vec<Event>| {
+            let mut cell = vec![Start(Tag::TableCell)];
+            cell.extend(events);
+            cell.push(End(Tag::TableCell));
+            cell
+        };
+        let mut expected = vec![Start(Tag::Table(columns.clone())), Start(Tag::TableHead),
+                                Start(Tag::TableRow)];
+        expected.extend(cell(vec![text("a")]));
+        expected.extend(cell(vec![Code("b|c".to_string())]));
+        expected.extend(vec![End(Tag::TableRow), End(Tag::TableHead), Start(Tag::TableBody),
+                             Start(Tag::TableRow)]);
+        expected.extend(cell(vec![text("1")]));
+        expected.extend(cell(vec![text("2")]));
+        expected.extend(vec![End(Tag::TableRow), Start(Tag::TableRow)]);
+        expected.extend(cell(vec![text("| x")]));
+        expected.extend(cell(vec![]));
+        expected.extend(vec![End(Tag::TableRow), End(Tag::TableBody), End(Tag::Table(columns)),
+                             Start(Tag::Paragraph), text("a | b"), SoftBreak, text("c | d"),
+                             End(Tag::Paragraph)]);
+        assert_eq!(events, expected);
+        let events: Vec<Event> = Parser::new(&lines(&["| a | b |", "| - |"])).collect();
+        assert_eq!(events[0], Start(Tag::Paragraph));
+    }
+
+    #[test]
+    fn test_table_spans() {
+        let input = lines(&["x", "", "| a  | b |", "|---|---|", "| c |"]);
+        let spans: Vec<String> = Parser::new(&input).spanned().map(|(_, span)| {
+            format!("{}", span)
+        }).collect();
+        assert_eq!(spans[3..12], ["3:1-5:5", "3:1-3:10", "3:1-3:10", "3:3-3:3", "3:3-3:3",
+                                  "3:3-3:3", "3:8-3:8", "3:8-3:8", "3:8-3:8"]);
+    }
+
+    #[test]
+    fn test_code_spans_lines() {
+        let input = lines(&["a", "", "```", "x", "", "```", "", "    y", "", "    z", ""]);
+        let spans: Vec<String> = Parser::new(&input).spanned().map(|(_, span)| {
+            format!("{}", span)
+        }).collect();
+        assert_eq!(spans, vec!["1:1-1:1", "1:1-1:1", "1:1-1:1", "3:1-6:3", "8:5-10:5"]);
+    }
+
+    #[test]
+    fn test_spans() {
+        let input = lines(&["---", "a: b", "---", "", "Some *text*  ", "  more", "", "- x",
+                            "  1. y"]);
+        let events: Vec<(Event, Span)> = Parser::starting_at(&input, 3).spanned().collect();
+        let spans: Vec<String> = events.iter().map(|&(_, span)| format!("{}", span)).collect();
+        assert_eq!(spans, vec!["5:1-6:6", "5:1-5:5", "5:6-5:11", "5:7-5:10", "5:6-5:11",
+                               "5:12-5:13", "6:3-6:6", "5:1-6:6",
+                               "8:1-9:6", "8:1-8:3", "8:3-8:3", "8:1-8:3",
+                               "9:3-9:6", "9:3-9:6", "9:6-9:6", "9:3-9:6", "9:3-9:6",
+                               "8:1-9:6"]);
+        assert_eq!(events[1].1, Span::new(Position::new(5, 1, 14), Position::new(5, 6, 19)));
+    }
+
+    #[test]
+    fn test_reference_links() {
+        let input = lines(&["See [A][1], [b][] and [B]. [c][2]", "", "[1]: a.md",
+                            "[b]:  <b c.md> \"Title\"", "", "[1]: other.md"]);
+        let events: Vec<Event> = Parser::new(&input).collect();
+        let link = |url: &str, title: Option<&str>, text: &str| {
+            let tag = Tag::Link(url.to_string(), title.map(|t| t.to_string()));
+            vec![Start(tag.clone()), Text(text.to_string()), End(tag)]
+        };
+        let mut expected = vec![Start(Tag::Paragraph), text("See ")];
+        expected.extend(link("a.md", None, "A"));
+        expected.push(text(", "));
+        expected.extend(link("b c.md", Some("Title"), "b"));
+        expected.push(text(" and "));
+        expected.extend(link("b c.md", Some("Title"), "B"));
+        expected.extend(vec![text(". [c][2]"), End(Tag::Paragraph)]);
+        assert_eq!(events, expected);
+    }
+}
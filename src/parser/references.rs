@@ -0,0 +1,118 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+//! Link reference definitions, like `[label]: url`, which reference
+//! links such as `[text][label]` and `[label]` are resolved against.
+
+use std::collections::HashMap;
+
+use text;
+use super::text_blocks;
+use super::inline::{destination, find_unescaped};
+
+/// Link reference definitions, from normalised label to destination and
+/// title.
+pub type References = HashMap<String, (String, Option<String>)>;
+
+/// Labels match case-insensitively, with any run of whitespace matching
+/// any other.
+pub fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+/// A link reference definition, like `[label]: url "title"`. Returns the
+/// normalised label, the destination and the title.
+pub fn link_definition(line: &str) -> Option<(String, String, Option<String>)> {
+    let rest = line.trim_left_matches(' ');
+    if line.len() - rest.len() > 3 || !rest.starts_with('[') {
+        return None;
+    }
+    let close = match find_unescaped(&rest[1..], ']') {
+        Some(close) => close + 1,
+        None => return None,
+    };
+    let label = &rest[1..close];
+    if label.trim().is_empty() || !rest[close + 1..].starts_with(':') {
+        return None;
+    }
+    match destination(&rest[close + 2..]) {
+        Some((url, title, after)) if !url.is_empty() && after.trim().is_empty() => {
+            Some((normalize_label(label), text::unescape(url), title.map(text::unescape)))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a block is made up only of link reference definitions.
+pub fn is_definitions(block: &[String]) -> bool {
+    block.iter().all(|line| link_definition(line).is_some())
+}
+
+/// Collect the link reference definitions in `lines`. Definitions have
+/// to be in blocks of their own, outside of code blocks. The first
+/// definition of a label wins.
+pub fn link_references(lines: &[String]) -> References {
+    let mut references = References::new();
+    for block in text_blocks(lines).into_iter() {
+        if !is_definitions(block) {
+            continue;
+        }
+        for line in block.iter() {
+            let (label, url, title) = link_definition(line).unwrap();
+            references.entry(label).or_insert((url, title));
+        }
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_label, link_definition, is_definitions, link_references};
+    use testing::lines;
+
+    fn definition(label: &str, url: &str, title: Option<&str>)
+                  -> Option<(String, String, Option<String>)> {
+        Some((label.to_string(), url.to_string(), title.map(|t| t.to_string())))
+    }
+
+    #[test]
+    fn test_normalize_label() {
+        assert_eq!(normalize_label("Foo  Bar"), "foo bar");
+        assert_eq!(normalize_label(" a\tB "), "a b");
+    }
+
+    #[test]
+    fn test_link_definition() {
+        assert_eq!(link_definition("[Foo]: /url"), definition("foo", "/url", None));
+        assert_eq!(link_definition("   [a b]:  <c d.md>"), definition("a b", "c d.md", None));
+        assert_eq!(link_definition("[a]: /url \"Title\""), definition("a", "/url", Some("Title")));
+        assert_eq!(link_definition("[a\\]]: b\\_c"), definition("a\\]", "b_c", None));
+    }
+
+    #[test]
+    fn test_not_a_link_definition() {
+        assert_eq!(link_definition("    [a]: /url"), None);
+        assert_eq!(link_definition("[a] : /url"), None);
+        assert_eq!(link_definition("[ ]: /url"), None);
+        assert_eq!(link_definition("[a]:"), None);
+        assert_eq!(link_definition("[a]: /url trailing"), None);
+        assert_eq!(link_definition("[a: /url"), None);
+    }
+
+    #[test]
+    fn test_definitions_need_blocks_of_their_own() {
+        assert!(is_definitions(&lines(&["[a]: x", "[b]: y"])));
+        assert!(!is_definitions(&lines(&["[a]: x", "text"])));
+        let references = link_references(&lines(&["text", "[a]: x", "", "[b]: y", "[B]: z",
+                                                  "", "```", "", "[c]: x", "```"]));
+        assert_eq!(references.len(), 1);
+        assert_eq!(references["b"], ("y".to_string(), None));
+    }
+}
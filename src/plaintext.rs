@@ -0,0 +1,356 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rendering documents as plain text, for email bodies, commit messages
+//! and anywhere else markup would get in the way.
+//!
+//! Markup is dropped, but the structure stays readable: headings are
+//! underlined, with `=` for the first level and `-` for the rest, bullets
+//! are `-`, ordered lists are numbered, links are written as
+//! `text <url>`, and rules are `* * *`. Paragraphs and list items are
+//! wrapped to `TextOptions::width`. Tables are laid out in columns, with
+//! the head underlined, and aren't wrapped.
+
+use std::cmp;
+
+use types::{Document, MarkdownStructure, Heading, Paragraph, BulletList, Bullet, CodeBlock};
+use types::BlockQuote;
+use types::Table;
+use parser::Alignment;
+use types::bulletlist::BulletElement;
+use types::inline::Contents;
+
+/// Options for rendering plain text.
+///
+#[derive(Clone, Debug)]
+pub struct TextOptions {
+    /// The column to wrap paragraphs and list items at, if any.
+    pub width: Option<usize>,
+}
+
+impl Default for TextOptions {
+    fn default() -> TextOptions {
+        TextOptions { width: Some(72) }
+    }
+}
+
+pub trait ToText {
+    fn write_text(&self, out: &mut String, options: &TextOptions);
+
+    /// Render to plain text using the default `TextOptions`.
+    fn to_text(&self) -> String {
+        self.to_text_with(&TextOptions::default())
+    }
+
+    fn to_text_with(&self, options: &TextOptions) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out, options);
+        out
+    }
+}
+
+/// Writes the blocks separated by blank lines, with a final newline. Front
+/// matter and HTML blocks are left out.
+impl ToText for Document {
+    fn write_text(&self, out: &mut String, options: &TextOptions) {
+        if write_blocks(self.blocks(), out, options) {
+            out.push('\n');
+        }
+    }
+}
+
+/// Write `blocks` separated by blank lines, leaving out HTML blocks.
+/// Returns whether anything was written.
+fn write_blocks(blocks: &[MarkdownStructure], out: &mut String, options: &TextOptions) -> bool {
+    let blocks: Vec<&MarkdownStructure> = blocks.iter().filter(|block| !block.is_html()).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.push_str("\n\n");
+        }
+        block.write_text(out, options);
+    }
+    !blocks.is_empty()
+}
+
+impl ToText for MarkdownStructure {
+    fn write_text(&self, out: &mut String, options: &TextOptions) {
+        match self {
+            &MarkdownStructure::MDH(ref heading) => heading.write_text(out, options),
+            &MarkdownStructure::MDP(ref paragraph) => paragraph.write_text(out, options),
+            &MarkdownStructure::MDB(ref list) => list.write_text(out, options),
+            &MarkdownStructure::MDR(_) => out.push_str("* * *"),
+            &MarkdownStructure::MDC(ref code) => code.write_text(out, options),
+            &MarkdownStructure::MDX(_) => {}
+            &MarkdownStructure::MDQ(ref quote) => quote.write_text(out, options),
+            &MarkdownStructure::MDT(ref table) => table.write_text(out, options),
+        }
+    }
+}
+
+/// Quoted lines start with `> `, and are wrapped to fit in after it.
+impl ToText for BlockQuote {
+    fn write_text(&self, out: &mut String, options: &TextOptions) {
+        let options = TextOptions { width: options.width.map(|width| width.saturating_sub(2)) };
+        let mut text = String::new();
+        write_blocks(self.blocks(), &mut text, &options);
+        let lines: Vec<String> = text.split('\n').map(|line| {
+            if line.is_empty() { ">".to_string() } else { format!("> {}", line) }
+        }).collect();
+        out.push_str(&lines.join("\n"));
+    }
+}
+
+/// Columns are two spaces apart, each as wide as its widest cell.
+impl ToText for Table {
+    fn write_text(&self, out: &mut String, _: &TextOptions) {
+        let rows: Vec<Vec<String>> = Some(self.head()).into_iter().chain(self.rows().iter())
+            .map(|row| {
+                row.cells().iter().map(|cell| {
+                    inline_text(cell.contents()).replace('\n', " ")
+                }).collect()
+            }).collect();
+        let mut widths = vec![1; self.alignments().len()];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = cmp::max(*width, cell.chars().count());
+            }
+        }
+        let length = |s: &str| s.chars().count();
+        let mut lines = vec![];
+        for (i, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = row.iter().enumerate().map(|(column, cell)| {
+                pad(cell, widths[column], self.alignments()[column], &length)
+            }).collect();
+            lines.push(cells.join("  ").trim_right().to_string());
+            if i == 0 {
+                let underlines: Vec<String> = widths.iter().map(|&n| "-".repeat(n)).collect();
+                lines.push(underlines.join("  "));
+            }
+        }
+        out.push_str(&lines.join("\n"));
+    }
+}
+
+/// Pad `text` with spaces to `width` columns, measured with `length`, on
+/// the side `alignment` calls for.
+pub fn pad(text: &str, width: usize, alignment: Alignment, length: &Fn(&str) -> usize) -> String {
+    let padding = width.saturating_sub(length(text));
+    match alignment {
+        Alignment::Right => format!("{}{}", " ".repeat(padding), text),
+        Alignment::Center => {
+            let left = padding / 2;
+            format!("{}{}{}", " ".repeat(left), text, " ".repeat(padding - left))
+        }
+        Alignment::Left | Alignment::None => format!("{}{}", text, " ".repeat(padding)),
+    }
+}
+
+/// Code isn't wrapped, and is indented to set it apart.
+impl ToText for CodeBlock {
+    fn write_text(&self, out: &mut String, _: &TextOptions) {
+        let lines: Vec<String> = self.code().lines().map(|line| {
+            if line.is_empty() { String::new() } else { format!("    {}", line) }
+        }).collect();
+        out.push_str(&lines.join("\n"));
+    }
+}
+
+/// Headings aren't wrapped, so the underline is as long as the text.
+impl ToText for Heading {
+    fn write_text(&self, out: &mut String, _: &TextOptions) {
+        let text = inline_text(self.contents()).replace('\n', " ");
+        let underline = if self.depth() == 1 { "=" } else { "-" };
+        out.push_str(&text);
+        out.push('\n');
+        out.push_str(&underline.repeat(text.chars().count()));
+    }
+}
+
+impl ToText for Paragraph {
+    fn write_text(&self, out: &mut String, options: &TextOptions) {
+        out.push_str(&wrap(&inline_text(self.contents()), options.width, "", ""));
+    }
+}
+
+impl ToText for Bullet {
+    fn write_text(&self, out: &mut String, options: &TextOptions) {
+        self.contents().write_text(out, options)
+    }
+}
+
+impl ToText for BulletList {
+    fn write_text(&self, out: &mut String, options: &TextOptions) {
+        write_list(self, out, options, 0)
+    }
+}
+
+impl ToText for Contents {
+    fn write_text(&self, out: &mut String, options: &TextOptions) {
+        out.push_str(&wrap(&inline_text(self), options.width, "", ""));
+    }
+}
+
+/// Write a list with its markers indented by `indent` spaces. Items wrap
+/// to line up with their first line's text, and nested lists line up with
+/// the text of the item before them.
+fn write_list(list: &BulletList, out: &mut String, options: &TextOptions, indent: usize) {
+    let mut number = list.start().unwrap_or(1);
+    let mut nested_indent = indent + 2;
+    for (i, element) in list.elements().iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        match element {
+            &BulletElement::Lone(ref bullet) => {
+                let marker = if list.is_ordered() {
+                    number += 1;
+                    format!("{}. ", number - 1)
+                }
+                else {
+                    "- ".to_string()
+                };
+                let first = format!("{}{}", " ".repeat(indent), marker);
+                let rest = " ".repeat(first.len());
+                let text = wrap(&inline_text(bullet.contents()), options.width, &first, &rest);
+                out.push_str(text.trim_right());
+                nested_indent = first.len();
+            }
+            &BulletElement::Nested(ref nested) => write_list(nested, out, options, nested_indent),
+        }
+    }
+}
+
+/// The text of a chain of inline contents, with hard breaks as newlines.
+pub fn inline_text(contents: &Contents) -> String {
+    let mut text = String::new();
+    for inline in contents.spans() {
+        match inline {
+            &Contents::Normal(ref s, _, _) |
+            &Contents::Italic(ref s, _, _) |
+            &Contents::Bold(ref s, _, _) |
+            &Contents::Code(ref s, _, _) => text.push_str(s),
+            &Contents::Link(ref s, ref url, _, _, _) => text.push_str(&link_text(s, url)),
+            &Contents::Image(ref alt, _, _, _) => text.push_str(alt),
+            &Contents::SoftBreak(..) => text.push(' '),
+            &Contents::HardBreak(..) => text.push('\n'),
+            &Contents::Nil => {}
+        }
+    }
+    text
+}
+
+/// A link as `text <url>`, or just `<url>` when the text is the URL.
+pub fn link_text(text: &str, url: &str) -> String {
+    if url.is_empty() {
+        text.to_string()
+    }
+    else if text.is_empty() || text == url {
+        format!("<{}>", url)
+    }
+    else {
+        format!("{} <{}>", text, url)
+    }
+}
+
+/// Fill `text` into lines of at most `width` columns, starting the first
+/// line with `first` and the others with `rest`. Newlines in `text` are
+/// kept, and lines only break at spaces, so a long word can overflow.
+pub fn wrap(text: &str, width: Option<usize>, first: &str, rest: &str) -> String {
+    wrap_measured(text, width, first, rest, &|s: &str| s.chars().count())
+}
+
+/// `wrap`, measuring how many columns each word and prefix takes with
+/// `length`.
+pub fn wrap_measured(text: &str, width: Option<usize>, first: &str, rest: &str,
+                     length: &Fn(&str) -> usize) -> String {
+    let mut out = first.to_string();
+    let mut column = length(first);
+    let mut line_start = true;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+            out.push_str(rest);
+            column = length(rest);
+            line_start = true;
+        }
+        for word in line.split(' ').filter(|word| !word.is_empty()) {
+            let word_length = length(word);
+            if let Some(width) = width {
+                if !line_start && column + 1 + word_length > width {
+                    out.push('\n');
+                    out.push_str(rest);
+                    column = length(rest);
+                    line_start = true;
+                }
+            }
+            if !line_start {
+                out.push(' ');
+                column += 1;
+            }
+            out.push_str(word);
+            column += word_length;
+            line_start = false;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ToText, TextOptions, wrap};
+    use convert::parse_document;
+    use testing::lines;
+
+    fn text(ls: &[&str], width: Option<usize>) -> String {
+        let lines = lines(ls);
+        parse_document(&lines).unwrap().to_text_with(&TextOptions { width: width })
+    }
+
+    #[test]
+    fn test_structure() {
+        assert_eq!(text(&["# *Release* notes", "", "Fixes `--check` in [fmt](https://x.org/fmt).",
+                          "", "## Changes", "", "- One", "  - Nested", "- Two", "", "---", "",
+                          "1. First", "2. Second"], None),
+                   "Release notes\n=============\n\n\
+                    Fixes --check in fmt <https://x.org/fmt>.\n\n\
+                    Changes\n-------\n\n\
+                    - One\n  - Nested\n- Two\n\n\
+                    * * *\n\n\
+                    1. First\n2. Second\n");
+    }
+
+    #[test]
+    fn test_html_blocks_are_left_out() {
+        assert_eq!(text(&["<div>", "hidden", "</div>", "", "Shown.", "<!-- note -->"], None),
+                   "Shown.\n");
+        assert_eq!(text(&["<!-- only -->"], None), "");
+    }
+
+    #[test]
+    fn test_block_quotes() {
+        assert_eq!(text(&["> *a* b c", ">", "> - d", "", "e"], Some(6)),
+                   "> a b\n> c\n>\n> - d\n\ne\n");
+    }
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(text(&["Name | Size", "--- | ---:", "*a* | 1", "long name | 10"], Some(5)),
+                   "Name       Size\n---------  ----\na             1\nlong name    10\n");
+    }
+
+    #[test]
+    fn test_wrapping() {
+        assert_eq!(text(&["The quick brown fox jumps", "over the lazy dog.", "",
+                          "- a list item that is long enough to wrap"], Some(20)),
+                   "The quick brown fox\njumps over the lazy\ndog.\n\n\
+                    - a list item that\n  is long enough to\n  wrap\n");
+        assert_eq!(wrap("one two\nthree", Some(80), "> ", "  "), "> one two\n  three");
+        assert_eq!(wrap("unbreakable-word here", Some(5), "", ""), "unbreakable-word\nhere");
+    }
+}
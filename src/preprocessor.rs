@@ -7,13 +7,231 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::str;
+use std::iter;
 
+use span::{OffsetMap, SourceMap};
+
+/// The number of columns between tab stops.
+pub const TAB_STOP: usize = 4;
+
+/// Expand the tabs in `lines` to spaces, up to the next tab stop.
+///
+/// Tabs are expanded according to the column they appear in, so text
+/// aligned with tabs stays aligned. The contents of code blocks are left
+/// alone: only the indentation of an indented code block is expanded, and
+/// the lines inside a fenced code block are kept exactly as written.
 pub fn replace_tabs(lines: &Vec<String>) -> Vec<String> {
-    lines.iter().map(|s| str::replace("\t", "    ", s)).collect()
+    let mut tabs = TabExpander::new();
+    lines.iter().map(|line| tabs.expand(line)).collect()
+}
+
+/// Like `replace_tabs`, but also record in `map` how the expanded lines
+/// line up with `lines`.
+pub fn replace_tabs_mapped(lines: &[String], map: &mut SourceMap) -> Vec<String> {
+    let mut tabs = TabExpander::new();
+    lines.iter().enumerate().map(|(index, line)| {
+        let expanded = tabs.expand(line);
+        map.rewrite_line(index, tab_map(line, &expanded));
+        expanded
+    }).collect()
+}
+
+/// Map the bytes of `expanded` back to `line`, which it was expanded
+/// from. Bytes inside the spaces a tab became map to just after it.
+fn tab_map(line: &str, expanded: &str) -> OffsetMap {
+    let mut map = OffsetMap::new();
+    let mut byte = 0;
+    let mut column = 0;
+    for (i, c) in line.char_indices() {
+        if c == '\t' && expanded[byte..].starts_with(' ') {
+            let width = TAB_STOP - column % TAB_STOP;
+            map.record(byte, i);
+            byte += width;
+            column += width;
+            map.record(byte, i + 1);
+        }
+        else {
+            byte += c.len_utf8();
+            column += 1;
+        }
+    }
+    map
+}
+
+/// Expand every tab in `s`, assuming `s` starts at column zero.
+pub fn expand_tabs(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut column = 0;
+    for c in s.chars() {
+        if c == '\t' {
+            let width = TAB_STOP - column % TAB_STOP;
+            result.extend(iter::repeat(' ').take(width));
+            column += width;
+        }
+        else {
+            result.push(c);
+            column += 1;
+        }
+    }
+    result
+}
+
+/// Expand only the tabs in the leading whitespace of `s`, keeping the
+/// rest of the line verbatim.
+fn expand_indentation(s: &str) -> String {
+    let rest = s.trim_left_matches(|c| c == ' ' || c == '\t');
+    let mut result = expand_tabs(&s[..s.len() - rest.len()]);
+    result.push_str(rest);
+    result
+}
+
+/// The width of the leading whitespace of `s`, in columns.
+fn indentation(s: &str) -> usize {
+    let mut column = 0;
+    for c in s.chars() {
+        match c {
+            ' ' => column += 1,
+            '\t' => column += TAB_STOP - column % TAB_STOP,
+            _ => break,
+        }
+    }
+    column
+}
+
+/// If `s` opens a code fence, return the fence character and length.
+fn code_fence(s: &str) -> Option<(char, usize)> {
+    let c = match s.chars().next() {
+        Some(c) if c == '`' || c == '~' => c,
+        _ => return None,
+    };
+    let len = s.len() - s.trim_left_matches(c).len();
+    if len < 3 || (c == '`' && s[len..].contains('`')) {
+        return None;
+    }
+    Some((c, len))
+}
+
+/// If `s` starts with a list marker, return the marker's width, including
+/// the space after it.
+fn list_marker(s: &str) -> Option<usize> {
+    let digits = s.len() - s.trim_left_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = match s[digits..].chars().next() {
+        Some(c) if digits == 0 && (c == '-' || c == '*' || c == '+') => 1,
+        Some(c) if digits > 0 && digits < 10 && (c == '.' || c == ')') => digits + 1,
+        _ => return None,
+    };
+    match s[marker..].chars().next() {
+        Some(' ') | Some('\t') | None => Some(marker + 1),
+        _ => None,
+    }
+}
+
+/// Tracks just enough block structure, line by line, to know whether a
+/// line belongs to a code block.
+struct TabExpander {
+    /// The fence of the fenced code block we're inside, if any.
+    fence: Option<(char, usize)>,
+    /// The column list item contents start at, if we're inside a list.
+    list_indent: Option<usize>,
+    in_indented_code: bool,
+    previous_blank: bool,
+}
+
+impl TabExpander {
+    fn new() -> TabExpander {
+        TabExpander {
+            fence: None,
+            list_indent: None,
+            in_indented_code: false,
+            previous_blank: true,
+        }
+    }
+
+    fn expand(&mut self, line: &str) -> String {
+        let indent = indentation(line);
+        let rest = line.trim_left_matches(|c| c == ' ' || c == '\t');
+        if let Some((c, len)) = self.fence {
+            match code_fence(rest) {
+                Some((close, close_len)) if close == c && close_len >= len
+                    && indent < TAB_STOP && rest.trim_left_matches(c).trim().is_empty() => {
+                    self.fence = None;
+                    return expand_tabs(line);
+                }
+                _ => return line.to_string(),
+            }
+        }
+        if rest.is_empty() {
+            self.previous_blank = true;
+            return expand_tabs(line);
+        }
+        let base = self.list_indent.unwrap_or(0);
+        if indent >= base + TAB_STOP && (self.previous_blank || self.in_indented_code) {
+            self.in_indented_code = true;
+            self.previous_blank = false;
+            return expand_indentation(line);
+        }
+        self.in_indented_code = false;
+        if self.previous_blank && indent < base {
+            self.list_indent = None;
+        }
+        self.previous_blank = false;
+        if indent < TAB_STOP + base {
+            self.fence = code_fence(rest);
+            if let Some(width) = list_marker(rest) {
+                self.list_indent = Some(indent + width);
+            }
+        }
+        expand_tabs(line)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use testing::lines;
+
+    #[test]
+    fn test_expand_tabs_to_next_stop() {
+        assert_eq!(expand_tabs("\tfoo"), "    foo");
+        assert_eq!(expand_tabs("a\tb"), "a   b");
+        assert_eq!(expand_tabs("abc\td"), "abc d");
+        assert_eq!(expand_tabs("abcd\te"), "abcd    e");
+        assert_eq!(expand_tabs("  \t\tx"), "        x");
+    }
+
+    #[test]
+    fn test_replace_tabs_in_text() {
+        assert_eq!(replace_tabs(&lines(&["Name:\tvalue", "-\titem"])),
+                   lines(&["Name:   value", "-   item"]));
+    }
+
+    #[test]
+    fn test_indented_code_keeps_inner_tabs() {
+        assert_eq!(replace_tabs(&lines(&["\tfoo\tbaz\t\tbim"])),
+                   lines(&["    foo\tbaz\t\tbim"]));
+        assert_eq!(replace_tabs(&lines(&["  \tfoo\tbaz", "", "    a\ta"])),
+                   lines(&["    foo\tbaz", "", "    a\ta"]));
+    }
+
+    #[test]
+    fn test_indented_line_in_paragraph_is_not_code() {
+        assert_eq!(replace_tabs(&lines(&["para", "\ta\tb"])),
+                   lines(&["para", "    a   b"]));
+    }
+
+    #[test]
+    fn test_list_continuation_is_not_code() {
+        assert_eq!(replace_tabs(&lines(&["- foo", "", "\tbar\tbaz"])),
+                   lines(&["- foo", "", "    bar baz"]));
+        assert_eq!(replace_tabs(&lines(&["- foo", "", "\t\tbar\tbaz"])),
+                   lines(&["- foo", "", "        bar\tbaz"]));
+    }
+
+    #[test]
+    fn test_fenced_code_is_verbatim() {
+        assert_eq!(replace_tabs(&lines(&["```", "\tx\ty", "```", "a\tb"])),
+                   lines(&["```", "\tx\ty", "```", "a   b"]));
+        assert_eq!(replace_tabs(&lines(&["~~~~", "\tx", "~~~", "\ty", "~~~~", "\tz"])),
+                   lines(&["~~~~", "\tx", "~~~", "\ty", "~~~~", "    z"]));
+    }
 }
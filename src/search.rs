@@ -0,0 +1,296 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A search index of a site's pages, for searching in the browser.
+//!
+//! The index is JSON, versioned like `json`'s AST:
+//!
+//! ```text
+//! {"schema": "rustdown-search", "version": 1, "stop_words": [...],
+//!  "documents": [{"url": URL, "title": TITLE, "excerpt": TEXT,
+//!                 "headings": [{"id": ANCHOR, "text": TEXT}, ...],
+//!                 "terms": {TERM: COUNT, ...}}, ...]}
+//! ```
+//!
+//! Terms are the lowercased words of the page's text, without markup or
+//! stop words, with how many times each appears. `stop_words` lists the
+//! words left out, so a search box can drop them from queries too.
+//! `SEARCH_PAGE` is a page that searches the index, and can be replaced
+//! by any other user interface reading the same format.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rustc_serialize::json::{Json, ToJson};
+
+use types::{CodeBlock, Document};
+use types::inline::Contents;
+use types::visit::Visitor;
+
+pub const SCHEMA: &'static str = "rustdown-search";
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// How long excerpts are, in characters.
+const EXCERPT_LENGTH: usize = 160;
+
+/// Common English words that aren't worth searching for.
+pub static STOP_WORDS: &'static [&'static str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have", "if",
+    "in", "into", "is", "it", "its", "no", "not", "of", "on", "or", "so", "such", "that", "the",
+    "their", "then", "there", "these", "they", "this", "to", "was", "we", "were", "will",
+    "with", "you", "your",
+];
+
+pub fn default_stop_words() -> BTreeSet<String> {
+    STOP_WORDS.iter().map(|w| w.to_string()).collect()
+}
+
+/// Split `text` into lowercased words, leaving out stop words. Words are
+/// runs of letters and numbers, as `SEARCH_PAGE` splits queries with
+/// `[^\p{L}\p{N}]+`.
+pub fn tokenize(text: &str, stop_words: &BTreeSet<String>) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .filter(|word| !stop_words.contains(word))
+        .collect()
+}
+
+struct TextCollector {
+    text: Vec<String>,
+}
+
+impl Visitor for TextCollector {
+    fn visit_contents(&mut self, contents: &Contents) {
+        self.text.push(contents.plain_text());
+    }
+
+    fn visit_code_block(&mut self, code: &CodeBlock) {
+        self.text.push(code.code().to_string());
+    }
+}
+
+/// A page in the index.
+///
+#[derive(Clone, Debug)]
+pub struct SearchDocument {
+    pub url: String,
+    pub title: String,
+    pub excerpt: String,
+    /// The anchor and text of each heading.
+    pub headings: Vec<(String, String)>,
+    pub terms: BTreeMap<String, usize>,
+}
+
+impl SearchDocument {
+    pub fn new(url: &str, title: &str, document: &Document, stop_words: &BTreeSet<String>)
+               -> SearchDocument {
+        let mut collector = TextCollector { text: vec![] };
+        collector.visit_document(document);
+        let text = collector.text.join(" ");
+        let mut terms = BTreeMap::new();
+        for term in tokenize(title, stop_words).into_iter().chain(tokenize(&text, stop_words)) {
+            *terms.entry(term).or_insert(0) += 1;
+        }
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut excerpt = String::new();
+        for word in words.iter() {
+            if excerpt.chars().count() + word.chars().count() > EXCERPT_LENGTH {
+                excerpt.push_str("…");
+                break;
+            }
+            if !excerpt.is_empty() {
+                excerpt.push(' ');
+            }
+            excerpt.push_str(word);
+        }
+        let headings = document.heading_ids().into_iter()
+            .zip(document.headings().iter().map(|h| h.text()))
+            .collect();
+        SearchDocument {
+            url: url.to_string(),
+            title: title.to_string(),
+            excerpt: excerpt,
+            headings: headings,
+            terms: terms,
+        }
+    }
+}
+
+impl ToJson for SearchDocument {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("url".to_string(), self.url.to_json());
+        object.insert("title".to_string(), self.title.to_json());
+        object.insert("excerpt".to_string(), self.excerpt.to_json());
+        object.insert("headings".to_string(), Json::Array(self.headings.iter().map(|h| {
+            let mut heading = BTreeMap::new();
+            heading.insert("id".to_string(), h.0.to_json());
+            heading.insert("text".to_string(), h.1.to_json());
+            Json::Object(heading)
+        }).collect()));
+        object.insert("terms".to_string(), Json::Object(self.terms.iter().map(|(term, &n)| {
+            (term.clone(), n.to_json())
+        }).collect()));
+        Json::Object(object)
+    }
+}
+
+/// The index of `documents`.
+pub fn index(documents: &[SearchDocument], stop_words: &BTreeSet<String>) -> Json {
+    let mut object = BTreeMap::new();
+    object.insert("schema".to_string(), SCHEMA.to_json());
+    object.insert("version".to_string(), SCHEMA_VERSION.to_json());
+    object.insert("stop_words".to_string(),
+                  Json::Array(stop_words.iter().map(|w| w.to_json()).collect()));
+    object.insert("documents".to_string(),
+                  Json::Array(documents.iter().map(|d| d.to_json()).collect()));
+    Json::Object(object)
+}
+
+/// A page that searches `search-index.json`, next to it. Each word of
+/// the query matches terms starting with it, and pages are ranked by
+/// how often they use the words, with matches in the title counting
+/// more. Headings matching the query are linked to directly.
+pub static SEARCH_PAGE: &'static str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8" />
+<meta name="viewport" content="width=device-width, initial-scale=1" />
+<title>Search</title>
+</head>
+<body>
+<form id="search"><input type="search" name="q" placeholder="Search" autofocus /></form>
+<ol id="results"></ol>
+<script>
+(function() {
+  var form = document.getElementById("search");
+  var results = document.getElementById("results");
+  var index = null;
+
+  function tokenize(text) {
+    return text.toLowerCase().split(/[^\p{L}\p{N}]+/u).filter(function(word) {
+      return word && index.stop_words.indexOf(word) < 0;
+    });
+  }
+
+  function link(href, text) {
+    var a = document.createElement("a");
+    a.href = href;
+    a.textContent = text;
+    return a;
+  }
+
+  function search(query) {
+    var words = tokenize(query);
+    var found = index.documents.map(function(doc) {
+      var score = 0;
+      words.forEach(function(word) {
+        Object.keys(doc.terms).forEach(function(term) {
+          if (term.indexOf(word) === 0) {
+            score += doc.terms[term];
+          }
+        });
+        if (doc.title.toLowerCase().indexOf(word) >= 0) {
+          score += 10;
+        }
+      });
+      return {doc: doc, score: score};
+    }).filter(function(result) {
+      return result.score > 0;
+    }).sort(function(a, b) {
+      return b.score - a.score;
+    });
+    results.textContent = "";
+    found.forEach(function(result) {
+      var item = document.createElement("li");
+      item.appendChild(link(result.doc.url, result.doc.title));
+      result.doc.headings.forEach(function(heading) {
+        var text = heading.text.toLowerCase();
+        if (words.some(function(word) { return text.indexOf(word) >= 0; })) {
+          item.appendChild(document.createTextNode(" · "));
+          item.appendChild(link(result.doc.url + "#" + heading.id, heading.text));
+        }
+      });
+      var excerpt = document.createElement("p");
+      excerpt.textContent = result.doc.excerpt;
+      item.appendChild(excerpt);
+      results.appendChild(item);
+    });
+  }
+
+  form.addEventListener("submit", function(event) {
+    event.preventDefault();
+  });
+  form.q.addEventListener("input", function() {
+    if (index) {
+      search(form.q.value);
+    }
+  });
+  var request = new XMLHttpRequest();
+  request.onload = function() {
+    index = JSON.parse(request.responseText);
+    var query = new URLSearchParams(location.search).get("q");
+    if (query) {
+      form.q.value = query;
+      search(query);
+    }
+  };
+  request.open("GET", "search-index.json");
+  request.send();
+})();
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use rustc_serialize::json::Json;
+
+    use convert::parse_document;
+    use testing::lines;
+    use super::{tokenize, default_stop_words, index, SearchDocument, SEARCH_PAGE};
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("The *quick*, brown-fox's Über 2nd", &default_stop_words()),
+                   vec!["quick", "brown", "fox", "s", "über", "2nd"]);
+        let stop_words: BTreeSet<String> = vec!["fox".to_string()].into_iter().collect();
+        assert_eq!(tokenize("The fox", &stop_words), vec!["the"]);
+    }
+
+    #[test]
+    fn test_tokenize_matches_search_page() {
+        // The page has to split queries the same way the index was split.
+        assert!(SEARCH_PAGE.contains(".split(/[^\\p{L}\\p{N}]+/u)"));
+        assert_eq!(tokenize("a\u{2014}b\u{d7}c \u{10400}\u{10401}\u{1d400}", &BTreeSet::new()),
+                   vec!["a", "b", "c", "\u{10428}\u{10429}\u{1d400}"]);
+    }
+
+    #[test]
+    fn test_index() {
+        let lines = lines(&["# Setup", "", "Install the **tools** with `cargo`.", "",
+                            "## Install", "", "- [Download](x.html) the tools"]);
+        let document = parse_document(&lines).unwrap();
+        let stop_words = default_stop_words();
+        let page = SearchDocument::new("guide/setup.html", "Setup", &document, &stop_words);
+        assert_eq!(page.excerpt, "Setup Install the tools with cargo. Install Download the tools");
+        assert_eq!(page.headings, vec![("setup".to_string(), "Setup".to_string()),
+                                       ("install".to_string(), "Install".to_string())]);
+        let json = index(&[page], &stop_words);
+        assert_eq!(json.find("version"), Some(&Json::U64(1)));
+        let doc = &json.find("documents").unwrap().as_array().unwrap()[0];
+        assert_eq!(format!("{}", doc.find("terms").unwrap()),
+                   "{\"cargo\":1,\"download\":1,\"install\":2,\"setup\":2,\"tools\":2}");
+        assert_eq!(format!("{}", doc.find("headings").unwrap()),
+                   "[{\"id\":\"setup\",\"text\":\"Setup\"},{\"id\":\"install\",\"text\":\"Install\"}]");
+    }
+}
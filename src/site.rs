@@ -0,0 +1,505 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Building a directory into a small static site.
+//!
+//! Each directory with markdown in it becomes a section of the site, with
+//! its `index.md` as the section's page, or a generated page listing its
+//! contents if it doesn't have one. Pages and sections are ordered by the
+//! `weight` in their front matter, lightest first, and then by title,
+//! which is the front matter's `title`, the first heading, or the file
+//! name.
+//!
+//! As well as the values described in `standalone`, page templates get
+//! `nav`, a list of links to every page in the site, and `prev` and
+//! `next`, links to the pages before and after in the site's order. With
+//! a base URL, there's also a `sitemap.xml` listing every page, and if
+//! any pages have a `date`, `atom.xml` and `rss.xml` feeds of them, as
+//! described in `feed`. Every site has a `search-index.json` of its pages
+//! and a `search.html` page to search them with, as described in
+//! `search`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use rustdoc::html::escape::Escape;
+use url::{Url, UrlParser};
+
+use build;
+use build::{BuildOptions, Output};
+use feed;
+use feed::{FeedEntry, FeedOptions, StripMore};
+use html::{Html, HtmlAttribute};
+use links;
+use metadata::MetaValue;
+use search;
+use search::SearchDocument;
+use standalone;
+use types::{Document, MarkdownStructure, Heading, BulletList, Bullet, VisitorMut};
+use types::bulletlist::BulletElement;
+use types::inline::Contents;
+use span::Span;
+
+/// The template used for site pages when none is given.
+pub static SITE_TEMPLATE: &'static str = "\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\" />
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />
+<title>{{title}}</title>
+{{styles}}
+</head>
+<body>
+<nav class=\"site-nav\">
+{{nav}}
+</nav>
+<main>
+{{body}}
+</main>
+<nav class=\"pagination\">
+{{prev}}
+{{next}}
+</nav>
+</body>
+</html>
+";
+
+/// The template values site pages add to `standalone::RESERVED_KEYS`.
+static SITE_KEYS: &'static [&'static str] = &["nav", "prev", "next"];
+
+/// Options for building a static site.
+///
+#[derive(Clone, Debug)]
+pub struct SiteOptions {
+    /// The URL the site will be served from, which the sitemap needs to
+    /// give full URLs. Without it, there's no sitemap.
+    pub base_url: Option<Url>,
+    /// Words left out of the search index.
+    pub stop_words: BTreeSet<String>,
+}
+
+impl Default for SiteOptions {
+    fn default() -> SiteOptions {
+        SiteOptions {
+            base_url: None,
+            stop_words: search::default_stop_words(),
+        }
+    }
+}
+
+struct Page {
+    /// The page's path in the output, with `/` separators.
+    url: String,
+    title: String,
+    weight: i64,
+    /// The markdown file the page is converted from, if there is one.
+    source: Option<PathBuf>,
+    document: Document,
+}
+
+struct Section {
+    index: Page,
+    entries: Vec<Entry>,
+}
+
+enum Entry {
+    Page(Page),
+    Section(Section),
+}
+
+impl Entry {
+    fn page(&self) -> &Page {
+        match self {
+            &Entry::Page(ref page) => page,
+            &Entry::Section(ref section) => &section.index,
+        }
+    }
+}
+
+/// The URL of the page at `to`, relative to the page at `from`, where
+/// both are paths from the root of the site.
+pub fn relative_url(from: &str, to: &str) -> String {
+    let from: Vec<&str> = from.split('/').collect();
+    let to: Vec<&str> = to.split('/').collect();
+    let from_dirs = &from[..from.len() - 1];
+    let common = from_dirs.iter().zip(to.iter()).take_while(|&(a, b)| a == b).count();
+    let mut url = "../".repeat(from_dirs.len() - common);
+    url.push_str(&to[common..].join("/"));
+    url
+}
+
+/// The directory of a page's path, or `""` at the root.
+fn directory(url: &str) -> &str {
+    url.rfind('/').map_or("", |i| &url[..i])
+}
+
+fn read_page(src: &Path, source: &Path) -> Result<Page, String> {
+    let path = src.join(source);
+    let text = try!(build::read_page(&path));
+    let document = try!(build::parse_page(&text).and_then(|document| {
+        try!(standalone::check_reserved(&document, standalone::RESERVED_KEYS));
+        try!(standalone::check_reserved(&document, SITE_KEYS));
+        Ok(document)
+    }).map_err(|e| {
+        format!("Error parsing {}:\n{}", path.display(), e)
+    }));
+    let title = document.meta("title").and_then(MetaValue::as_str).map(|t| t.to_string())
+        .or(document.title())
+        .unwrap_or(source.file_stem().unwrap().to_string_lossy().into_owned());
+    Ok(Page {
+        url: build::manifest_path(&build::output_path(source)),
+        title: title,
+        weight: document.meta("weight").and_then(MetaValue::as_integer).unwrap_or(0),
+        source: Some(source.to_path_buf()),
+        document: document,
+    })
+}
+
+/// Build the section for `dir` out of `pages`, which are the pages in it
+/// or below it.
+fn section(dir: &str, pages: Vec<Page>) -> Section {
+    let mut index = None;
+    let mut own = vec![];
+    let mut below: Vec<(String, Vec<Page>)> = vec![];
+    for page in pages.into_iter() {
+        let page_dir = directory(&page.url).to_string();
+        if page_dir == dir {
+            let name = &page.url[if dir.is_empty() { 0 } else { dir.len() + 1 }..];
+            if name == "index.html" { index = Some(page) } else { own.push(page) }
+            continue;
+        }
+        let rest = &page_dir[if dir.is_empty() { 0 } else { dir.len() + 1 }..];
+        let child = match rest.find('/') {
+            Some(i) => &rest[..i],
+            None => rest,
+        };
+        let child = if dir.is_empty() { child.to_string() } else { format!("{}/{}", dir, child) };
+        match below.iter().position(|&(ref d, _)| *d == child) {
+            Some(i) => below[i].1.push(page),
+            None => below.push((child, vec![page])),
+        }
+    }
+    let mut entries: Vec<Entry> = own.into_iter().map(Entry::Page).collect();
+    entries.extend(below.into_iter().map(|(child, pages)| Entry::Section(section(&child, pages))));
+    entries.sort_by(|a, b| {
+        let (a, b) = (a.page(), b.page());
+        (a.weight, a.title.to_lowercase()).cmp(&(b.weight, b.title.to_lowercase()))
+    });
+    let index = index.unwrap_or_else(|| {
+        let url = if dir.is_empty() { "index.html".to_string() } else { format!("{}/index.html", dir) };
+        let title = match dir.rfind('/') {
+            _ if dir.is_empty() => "Home".to_string(),
+            Some(i) => dir[i + 1..].to_string(),
+            None => dir.to_string(),
+        };
+        Page {
+            document: index_document(&url, &title, &entries),
+            url: url,
+            title: title,
+            weight: 0,
+            source: None,
+        }
+    });
+    Section {
+        index: index,
+        entries: entries,
+    }
+}
+
+/// A page listing the entries of a section.
+fn index_document(url: &str, title: &str, entries: &[Entry]) -> Document {
+    let mut blocks = vec![MarkdownStructure::MDH(Heading::new(title.to_string(), 1))];
+    if !entries.is_empty() {
+        let mut list = BulletList::new_unordered();
+        for entry in entries.iter() {
+            let page = entry.page();
+            let link = Contents::Link(page.title.clone(), relative_url(url, &page.url), None,
+                                      Span::default(), Box::new(Contents::Nil));
+            list.push(BulletElement::Lone(Bullet::with_contents(link)));
+        }
+        blocks.push(MarkdownStructure::MDB(list));
+    }
+    Document::new(blocks)
+}
+
+/// Every page of the site in order: each section's page, then its
+/// entries.
+fn pages_in_order<'a>(section: &'a Section, pages: &mut Vec<&'a Page>) {
+    pages.push(&section.index);
+    for entry in section.entries.iter() {
+        match entry {
+            &Entry::Page(ref page) => pages.push(page),
+            &Entry::Section(ref section) => pages_in_order(section, pages),
+        }
+    }
+}
+
+fn link(from: &str, page: &Page) -> Html {
+    let mut link = Html::new_simple("a".to_string(), page.title.clone());
+    link.add_attribute(HtmlAttribute::new("href".to_string(), relative_url(from, &page.url)));
+    if from == page.url {
+        link.add_attribute(HtmlAttribute::new("aria-current".to_string(), "page".to_string()));
+    }
+    link
+}
+
+fn add_nav_items(list: &mut Html, from: &str, entries: &[Entry]) {
+    for entry in entries.iter() {
+        let mut item = Html::new_empty("li".to_string());
+        item.add_tag(link(from, entry.page()));
+        if let &Entry::Section(ref section) = entry {
+            if !section.entries.is_empty() {
+                let mut nested = Html::new_empty("ul".to_string());
+                add_nav_items(&mut nested, from, &section.entries);
+                item.add_tag(nested);
+            }
+        }
+        list.add_tag(item);
+    }
+}
+
+/// The site's navigation, as seen from the page at `from`: the home page
+/// followed by the root's entries, with each section's entries nested
+/// under it.
+fn nav(from: &str, root: &Section) -> Html {
+    let mut list = Html::new_empty("ul".to_string());
+    let mut home = Html::new_empty("li".to_string());
+    home.add_tag(link(from, &root.index));
+    list.add_tag(home);
+    add_nav_items(&mut list, from, &root.entries);
+    list
+}
+
+fn pagination_link(from: &str, page: Option<&&Page>, rel: &str) -> String {
+    match page {
+        Some(page) => {
+            let mut link = link(from, page);
+            link.add_attribute(HtmlAttribute::new("rel".to_string(), rel.to_string()));
+            format!("{}", link)
+        }
+        None => String::new(),
+    }
+}
+
+/// The full URL of the page at `path`.
+fn site_url(base: &Url, path: &str) -> Result<Url, String> {
+    UrlParser::new().base_url(base).parse(path).map_err(|e| {
+        format!("invalid URL for {}: {}", path, e)
+    })
+}
+
+fn sitemap(base: &Url, pages: &[&Page]) -> Result<String, String> {
+    let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                   <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n".to_string();
+    for page in pages.iter() {
+        let url = try!(site_url(base, &page.url)).serialize();
+        xml.push_str(&format!("<url><loc>{}</loc></url>\n", Escape(&url)));
+    }
+    xml.push_str("</urlset>\n");
+    Ok(xml)
+}
+
+/// What an output is made from, for saying which two outputs clash.
+fn origin(output: &Output) -> String {
+    match output {
+        &Output::Copy(ref source) | &Output::Page(ref source) |
+        &Output::Generated(Some(ref source), _) => format!("`{}`", build::manifest_path(source)),
+        &Output::Generated(None, _) => "the site's own files".to_string(),
+    }
+}
+
+/// Check that no two outputs are written to the same place, as a source
+/// file called `search.html` or `guide/index.html` next to a generated
+/// one would be.
+fn check_collisions(outputs: &[(PathBuf, Output)]) -> Result<(), String> {
+    let mut seen: HashMap<&Path, &Output> = HashMap::new();
+    for &(ref path, ref output) in outputs.iter() {
+        if let Some(other) = seen.insert(path, output) {
+            return Err(format!("Error with {}:
+it would be written from both {} and {}",
+                               build::manifest_path(path), origin(other), origin(output)));
+        }
+    }
+    Ok(())
+}
+
+/// Plan the outputs of a site built from `sources`, the files under
+/// `src`: a page for each markdown file and each section without an
+/// index, the sitemap, and copies of everything else. It's an error for
+/// two of them to have the same path.
+pub fn plan(src: &Path, sources: &[PathBuf], options: &BuildOptions, site: &SiteOptions)
+            -> Result<Vec<(PathBuf, Output)>, String> {
+    let mut outputs = vec![];
+    let mut pages = vec![];
+    let mut entries = vec![];
+    for source in sources.iter() {
+        if links::is_markdown(source) {
+            let mut page = try!(read_page(src, source));
+            if let Some(ref base) = site.base_url {
+                let url = try!(site_url(base, &page.url));
+                let entry = try!(FeedEntry::new(&page.document, &url, &page.title).map_err(|e| {
+                    format!("Error with {}:\n{}", src.join(source).display(), e)
+                }));
+                entries.extend(entry);
+            }
+            StripMore.visit_document_mut(&mut page.document);
+            pages.push(page);
+        }
+        else {
+            outputs.push((source.clone(), Output::Copy(source.clone())));
+        }
+    }
+    let root = section("", pages);
+    let mut order = vec![];
+    pages_in_order(&root, &mut order);
+
+    let mut html = options.html.clone();
+    html.heading_ids = true;
+    for (i, page) in order.iter().enumerate() {
+        let mut values = standalone::template_values(&page.document, &html, &options.standalone);
+        values.insert("title".to_string(), format!("{}", Escape(&page.title)));
+        values.insert("nav".to_string(), format!("{}", nav(&page.url, &root)));
+        let previous = if i > 0 { order.get(i - 1) } else { None };
+        values.insert("prev".to_string(), pagination_link(&page.url, previous, "prev"));
+        values.insert("next".to_string(), pagination_link(&page.url, order.get(i + 1), "next"));
+        let contents = options.standalone.template.render(&values).into_bytes();
+        outputs.push((PathBuf::from(&page.url), Output::Generated(page.source.clone(), contents)));
+    }
+    let documents: Vec<SearchDocument> = order.iter().map(|page| {
+        SearchDocument::new(&page.url, &page.title, &page.document, &site.stop_words)
+    }).collect();
+    let index = format!("{}\n", search::index(&documents, &site.stop_words));
+    outputs.push((PathBuf::from("search-index.json"), Output::Generated(None, index.into_bytes())));
+    outputs.push((PathBuf::from("search.html"),
+                  Output::Generated(None, search::SEARCH_PAGE.as_bytes().to_vec())));
+    if let Some(ref base) = site.base_url {
+        outputs.push((PathBuf::from("sitemap.xml"),
+                      Output::Generated(None, try!(sitemap(base, &order)).into_bytes())));
+        if !entries.is_empty() {
+            feed::sort_entries(&mut entries);
+            let options = FeedOptions {
+                title: root.index.title.clone(),
+                url: base.clone(),
+                author: None,
+            };
+            let atom_url = try!(site_url(base, "atom.xml")).serialize();
+            outputs.push((PathBuf::from("atom.xml"),
+                          Output::Generated(None, feed::atom(&options, &atom_url, &entries)
+                                                      .into_bytes())));
+            outputs.push((PathBuf::from("rss.xml"),
+                          Output::Generated(None, feed::rss(&options, &entries).into_bytes())));
+        }
+    }
+    try!(check_collisions(&outputs));
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use url::Url;
+
+    use build::{build, BuildOptions};
+    use template::Template;
+    use super::{relative_url, SiteOptions};
+    use testing::{read, temp_dir, write};
+
+    #[test]
+    fn test_relative_url() {
+        assert_eq!(relative_url("index.html", "a/b.html"), "a/b.html");
+        assert_eq!(relative_url("a/b.html", "index.html"), "../index.html");
+        assert_eq!(relative_url("a/b.html", "a/c/d.html"), "c/d.html");
+        assert_eq!(relative_url("a/c/d.html", "a/e/f.html"), "../e/f.html");
+    }
+
+    #[test]
+    fn test_site() {
+        let dir = temp_dir("site-test");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write(&src.join("index.md"), "---\ntitle: Handbook\n---\nWelcome.\n");
+        write(&src.join("zebra.md"), "---\nweight: -1\n---\n# Zebras\n");
+        write(&src.join("apple.md"), "---\ndate: 2015-03-14\n---\n# Apples\n\nRed.\n\n\
+                                      <!-- more -->\n\nGreen.\n");
+        write(&src.join("guide/setup.md"), "# Setup\n");
+        write(&src.join("guide/img/a.png"), "PNG");
+
+        let mut options = BuildOptions::default();
+        options.standalone.template = Template::new("{{nav}}|{{prev}}|{{next}}".to_string());
+        options.site = Some(SiteOptions {
+            base_url: Some(Url::parse("https://example.com/handbook/").unwrap()),
+            ..SiteOptions::default()
+        });
+        let summary = build(&src, &out, &options).unwrap();
+        assert_eq!((summary.converted, summary.generated, summary.copied), (4, 6, 1));
+
+        assert_eq!(read(&out.join("apple.html")),
+                   "<ul><li><a href=\"index.html\">Handbook</a></li>\
+                    <li><a href=\"zebra.html\">Zebras</a></li>\
+                    <li><a href=\"apple.html\" aria-current=\"page\">Apples</a></li>\
+                    <li><a href=\"guide/index.html\">guide</a>\
+                    <ul><li><a href=\"guide/setup.html\">Setup</a></li></ul></li></ul>|\
+                    <a href=\"zebra.html\" rel=\"prev\">Zebras</a>|\
+                    <a href=\"guide/index.html\" rel=\"next\">guide</a>");
+        let setup = read(&out.join("guide/setup.html"));
+        assert!(setup.contains("<a href=\"../index.html\">Handbook</a>"));
+        assert!(setup.ends_with("|<a href=\"index.html\" rel=\"prev\">guide</a>|"));
+
+        options.standalone.template = Template::new("{{body}}".to_string());
+        build(&src, &out, &options).unwrap();
+        assert_eq!(read(&out.join("guide/index.html")),
+                   "<h1 id=\"guide\">guide</h1>\n<ul><li><a href=\"setup.html\">Setup</a></li></ul>");
+        assert_eq!(read(&out.join("sitemap.xml")),
+                   "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                    <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n\
+                    <url><loc>https://example.com/handbook/index.html</loc></url>\n\
+                    <url><loc>https://example.com/handbook/zebra.html</loc></url>\n\
+                    <url><loc>https://example.com/handbook/apple.html</loc></url>\n\
+                    <url><loc>https://example.com/handbook/guide/index.html</loc></url>\n\
+                    <url><loc>https://example.com/handbook/guide/setup.html</loc></url>\n\
+                    </urlset>\n");
+        assert!(!read(&out.join("apple.html")).contains("more"));
+        let atom = read(&out.join("atom.xml"));
+        assert!(atom.contains("<title>Handbook</title>"));
+        assert!(atom.contains("<link href=\"https://example.com/handbook/apple.html\" />"));
+        assert!(atom.contains("<summary type=\"html\">&lt;h1&gt;Apples&lt;/h1&gt;\n\
+                               &lt;p&gt;Red.&lt;/p&gt;</summary>"));
+        assert!(read(&out.join("rss.xml")).contains("<pubDate>Sat, 14 Mar 2015"));
+        assert!(read(&out.join("search-index.json")).contains("\"url\":\"guide/setup.html\""));
+        assert!(read(&out.join("search.html")).contains("search-index.json"));
+        let summary = build(&src, &out, &options).unwrap();
+        assert_eq!(summary.unchanged, 11);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_colliding_outputs() {
+        let dir = temp_dir("site-collision-test");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        let mut options = BuildOptions::default();
+        options.site = Some(SiteOptions {
+            base_url: Some(Url::parse("https://example.com/").unwrap()),
+            ..SiteOptions::default()
+        });
+        write(&src.join("index.md"), "# Home\n");
+        write(&src.join("guide/setup.md"), "# Setup\n");
+        for name in ["search.html", "sitemap.xml", "guide/index.html"].iter() {
+            write(&src.join(name), "");
+            assert_eq!(build(&src, &out, &options),
+                       Err(format!("Error with {}:\nit would be written from both `{}` and \
+                                    the site's own files", name, name)));
+            fs::remove_file(src.join(name)).unwrap();
+        }
+        write(&src.join("notes.md"), "---\ndate: 2015-03-14\n---\n# Notes\n");
+        write(&src.join("atom.xml"), "");
+        assert!(build(&src, &out, &options).unwrap_err().starts_with("Error with atom.xml:"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Turning heading text into anchor names.
+
+use std::collections::HashSet;
+
+/// Make an anchor name out of `s`.
+///
+/// Letters are lowercased, spaces become hyphens, and everything other
+/// than letters, digits, hyphens and underscores is dropped, so that
+/// `"What's new?"` becomes `whats-new`.
+pub fn slugify(s: &str) -> String {
+    s.trim().chars().filter_map(|c| {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            Some(c.to_lowercase().collect::<String>())
+        }
+        else if c.is_whitespace() {
+            Some("-".to_string())
+        }
+        else {
+            None
+        }
+    }).collect()
+}
+
+/// Hands out unique anchor names for the headings of a document.
+///
+/// The first heading with a given slug gets it unchanged. Later ones get
+/// `-1`, `-2` and so on appended.
+pub struct Slugger {
+    seen: HashSet<String>,
+}
+
+impl Slugger {
+    pub fn new() -> Slugger {
+        Slugger {
+            seen: HashSet::new(),
+        }
+    }
+
+    pub fn slug(&mut self, s: &str) -> String {
+        let base = match slugify(s) {
+            ref slug if slug.is_empty() => "section".to_string(),
+            slug => slug,
+        };
+        let mut slug = base.clone();
+        let mut n = 0;
+        while self.seen.contains(&slug) {
+            n += 1;
+            slug = format!("{}-{}", base, n);
+        }
+        self.seen.insert(slug.clone());
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slugify, Slugger};
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World"), "hello-world");
+        assert_eq!(slugify("What's new in 0.1?"), "whats-new-in-01");
+        assert_eq!(slugify("  snake_case and-dashes "), "snake_case-and-dashes");
+        assert_eq!(slugify("Über Straße"), "über-straße");
+    }
+
+    #[test]
+    fn test_unique_slugs() {
+        let mut slugger = Slugger::new();
+        assert_eq!(slugger.slug("Setup"), "setup");
+        assert_eq!(slugger.slug("Setup"), "setup-1");
+        assert_eq!(slugger.slug("setup"), "setup-2");
+        assert_eq!(slugger.slug("Setup 1"), "setup-1-1");
+        assert_eq!(slugger.slug("???"), "section");
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Positions in the markdown source.
+//!
+//! Positions are counted in the lines the parser is given, after line
+//! endings have been normalised and tabs expanded, so offsets are into
+//! those lines joined with `\n`. Lines and columns count from 1, and
+//! columns count bytes. A `SourceMap` maps them back to the bytes that
+//! were actually read.
+
+use std::cmp;
+use std::fmt;
+
+/// A single position in the source.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize, offset: usize) -> Position {
+        Position {
+            line: line,
+            column: column,
+            offset: offset,
+        }
+    }
+
+    /// The position of the very start of the source.
+    pub fn start() -> Position {
+        Position::new(1, 1, 0)
+    }
+
+    /// The position `bytes` further along the same line.
+    pub fn advance(&self, bytes: usize) -> Position {
+        Position::new(self.line, self.column + bytes, self.offset + bytes)
+    }
+}
+
+/// The part of the source a node was parsed from. `start` is the
+/// position of its first byte, and `end` the position just after its
+/// last one.
+///
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        Span {
+            start: start,
+            end: end,
+        }
+    }
+}
+
+/// Formats the span the way `data-sourcepos` attributes do, as
+/// `line:column-line:column` with the end column being that of the last
+/// byte.
+impl fmt::Display for Span {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}-{}:{}", self.start.line, self.start.column,
+               self.end.line, self.end.column.saturating_sub(1))
+    }
+}
+
+/// The position of the start of each of `lines`, where the first line
+/// starts at `first`.
+pub fn line_starts(lines: &[String], first: Position) -> Vec<Position> {
+    let mut position = first;
+    lines.iter().map(|line| {
+        let start = position;
+        position = Position::new(start.line + 1, 1, start.offset + line.len() + 1);
+        start
+    }).collect()
+}
+
+/// Maps byte offsets in some text to offsets in the text it was made
+/// from. Between two recorded points offsets map one to one, but never
+/// past the second point.
+///
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct OffsetMap {
+    points: Vec<(usize, usize)>,
+}
+
+impl OffsetMap {
+    pub fn new() -> OffsetMap {
+        OffsetMap { points: vec![] }
+    }
+
+    /// Record that `offset` came from `original`. Points have to be
+    /// recorded in order.
+    pub fn record(&mut self, offset: usize, original: usize) {
+        let (last, last_original) = self.points.last().cloned().unwrap_or((0, 0));
+        if offset == last && !self.points.is_empty() {
+            self.points.pop();
+        }
+        else if offset + last_original == original + last {
+            return;
+        }
+        self.points.push((offset, original));
+    }
+
+    /// The offset in the original text that `offset` came from.
+    pub fn original(&self, offset: usize) -> usize {
+        let i = match self.points.binary_search_by(|&(point, _)| point.cmp(&offset)) {
+            Ok(i) => return self.points[i].1,
+            Err(i) => i,
+        };
+        let (from, to) = if i == 0 { (0, 0) } else { self.points[i - 1] };
+        match self.points.get(i) {
+            Some(&(_, next)) => cmp::min(to + offset - from, next),
+            None => to + offset - from,
+        }
+    }
+}
+
+/// Maps positions in the lines the parser is given back to the source
+/// they were read from, undoing decoding, the normalising of line
+/// endings and the expanding of tabs.
+///
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct SourceMap {
+    /// From the decoded text to the bytes that were read.
+    decoded: OffsetMap,
+    /// The length of the decoded text.
+    len: usize,
+    /// Where each line starts in the decoded text, and the maps from the
+    /// line as the parser sees it back to the decoded text, last first.
+    lines: Vec<(usize, Vec<OffsetMap>)>,
+}
+
+impl SourceMap {
+    /// A map for text of `len` bytes, decoded as `decoded` says.
+    pub fn new(decoded: OffsetMap, len: usize) -> SourceMap {
+        SourceMap {
+            decoded: decoded,
+            len: len,
+            lines: vec![],
+        }
+    }
+
+    /// Add a line starting at `start` in the decoded text, whose bytes
+    /// map back to it as `map` says.
+    pub fn push_line(&mut self, start: usize, map: OffsetMap) {
+        self.lines.push((start, vec![map]));
+    }
+
+    /// Record that line `index`, counting from 0, was rewritten, and its
+    /// new bytes map to the old ones as `map` says.
+    pub fn rewrite_line(&mut self, index: usize, map: OffsetMap) {
+        if let Some(&mut (_, ref mut maps)) = self.lines.get_mut(index) {
+            maps.push(map);
+        }
+    }
+
+    /// The position in the original source of `position`.
+    pub fn position(&self, position: Position) -> Position {
+        let (start, maps) = match self.lines.get(position.line.saturating_sub(1)) {
+            Some(&(start, ref maps)) => (start, maps),
+            None => return Position::new(position.line, 1, self.decoded.original(self.len)),
+        };
+        let byte = maps.iter().rev().fold(position.column.saturating_sub(1), |byte, map| {
+            map.original(byte)
+        });
+        let line_start = self.decoded.original(start);
+        let offset = self.decoded.original(start + byte);
+        Position::new(position.line, offset - line_start + 1, offset)
+    }
+
+    /// The span in the original source of `span`.
+    pub fn span(&self, span: Span) -> Span {
+        Span::new(self.position(span.start), self.position(span.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Position, Span, OffsetMap, line_starts};
+    use lines;
+    use preprocessor;
+
+    #[test]
+    fn test_line_starts() {
+        let lines = vec!["ab".to_string(), "".to_string(), "c".to_string()];
+        assert_eq!(line_starts(&lines, Position::new(3, 1, 10)),
+                   vec![Position::new(3, 1, 10), Position::new(4, 1, 13),
+                        Position::new(5, 1, 14)]);
+    }
+
+    #[test]
+    fn test_sourcepos_format() {
+        let start = Position::new(2, 3, 7);
+        assert_eq!(format!("{}", Span::new(start, start.advance(4))), "2:3-2:6");
+    }
+
+    #[test]
+    fn test_offset_map() {
+        let mut map = OffsetMap::new();
+        map.record(0, 2);
+        map.record(3, 5);
+        map.record(3, 5);
+        map.record(7, 6);
+        assert_eq!((0..9).map(|i| map.original(i)).collect::<Vec<_>>(),
+                   vec![2, 3, 4, 5, 6, 6, 6, 6, 7]);
+    }
+
+    #[test]
+    fn test_source_map() {
+        let (lines, mut map) = lines::split_lines_mapped("# A\r\n\r\n\tx");
+        let lines = preprocessor::replace_tabs_mapped(&lines, &mut map);
+        assert_eq!(lines[2], "    x");
+        assert_eq!(map.position(Position::new(3, 5, 9)), Position::new(3, 2, 8));
+        assert_eq!(map.position(Position::new(1, 3, 2)), Position::new(1, 3, 2));
+        assert_eq!(map.position(Position::new(4, 1, 11)), Position::new(4, 1, 9));
+        let (_, map) = lines::split_lines_mapped("\u{feff}a\0b");
+        assert_eq!(map.position(Position::new(1, 5, 4)), Position::new(1, 3, 5));
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Wrapping rendered markdown into a complete HTML document.
+
+use std::collections::HashMap;
+
+use rustdoc::html::escape::Escape;
+
+use html::Html;
+use html::HtmlAttribute;
+use html::HtmlOptions;
+use template::Template;
+use types::Document;
+
+/// Options for rendering a standalone HTML document.
+///
+#[derive(Clone, Debug, Default)]
+pub struct StandaloneOptions {
+    /// The document title. Defaults to the text of the first heading.
+    pub title: Option<String>,
+    /// The template to render into. Defaults to `template::DEFAULT_TEMPLATE`.
+    pub template: Template,
+    /// URLs of stylesheets to link to.
+    pub stylesheets: Vec<String>,
+    /// CSS to include directly in the document.
+    pub inline_css: Vec<String>,
+}
+
+/// The template values filled in by rustdown, which front matter can't
+/// set. A front-matter `title` isn't a conflict: it's the document title.
+pub static RESERVED_KEYS: &'static [&'static str] = &["body", "toc", "styles"];
+
+/// Check that no front-matter key of `document` is one of `keys`, which
+/// the template value of the same name would otherwise shadow.
+pub fn check_reserved(document: &Document, keys: &[&str]) -> Result<(), String> {
+    match keys.iter().find(|key| document.meta(key).is_some()) {
+        Some(key) => Err(format!("front matter key `{}` is reserved for the template", key)),
+        None => Ok(()),
+    }
+}
+
+/// The values available to templates, given the document and its
+/// rendered body.
+///
+/// These are `title`, `body`, `toc` (a table of contents, as nested
+/// lists) and `styles` (the stylesheet links and inline CSS), along with
+/// every top-level key of the front matter, such as `author` or `date`.
+pub fn template_values(document: &Document,
+                       options: &HtmlOptions,
+                       standalone: &StandaloneOptions) -> HashMap<String, String> {
+    let mut values: HashMap<String, String> = document.metadata().iter().map(|(key, value)| {
+        (key.clone(), format!("{}", Escape(&format!("{}", value))))
+    }).collect();
+    let title = standalone.title.clone().or(document.title()).unwrap_or(String::new());
+    values.insert("title".to_string(), format!("{}", Escape(&title)));
+    values.insert("body".to_string(), document.body_html(options));
+    values.insert("toc".to_string(),
+                  document.toc().map_or(String::new(), |toc| format!("{}", toc)));
+    values.insert("styles".to_string(), styles(standalone));
+    values
+}
+
+/// Render `document` as a complete HTML document. Headings are always
+/// given `id`s, so the table of contents can link to them. Fails if the
+/// front matter uses one of the `RESERVED_KEYS`.
+pub fn render(document: &Document,
+              options: &HtmlOptions,
+              standalone: &StandaloneOptions) -> Result<String, String> {
+    try!(check_reserved(document, RESERVED_KEYS));
+    let mut options = options.clone();
+    options.heading_ids = true;
+    Ok(standalone.template.render(&template_values(document, &options, standalone)))
+}
+
+fn styles(standalone: &StandaloneOptions) -> String {
+    let mut styles: Vec<String> = standalone.stylesheets.iter().map(|url| {
+        let mut link = Html::new_empty("link".to_string());
+        link.add_attribute(HtmlAttribute::new("rel".to_string(), "stylesheet".to_string()));
+        link.add_attribute(HtmlAttribute::new("href".to_string(), url.clone()));
+        format!("{}", link)
+    }).collect();
+    // CSS isn't escaped inside `<style>`, so it can't go through `Html`.
+    styles.extend(standalone.inline_css.iter().map(|css| {
+        format!("<style>\n{}\n</style>", css.trim_right())
+    }));
+    styles.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use convert::parse_document;
+    use html::HtmlOptions;
+    use template::Template;
+    use super::{render, StandaloneOptions};
+    use testing::lines;
+
+    #[test]
+    fn test_default_template() {
+        let document = parse_document(&lines(&["# Fish & Chips", "", "Tasty."])).unwrap();
+        let html = render(&document, &HtmlOptions::default(), &StandaloneOptions::default())
+                   .unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>\n<html>\n<head>\n\
+                                  <meta charset=\"utf-8\" />\n"));
+        assert!(html.contains("<meta name=\"viewport\""));
+        assert!(html.contains("<title>Fish &amp; Chips</title>"));
+        assert!(html.contains("<body>\n<h1 id=\"fish--chips\">Fish &amp; Chips</h1>\n\
+                               <p>Tasty.</p>\n</body>"));
+    }
+
+    #[test]
+    fn test_title_override() {
+        let document = parse_document(&lines(&["# Heading"])).unwrap();
+        let mut standalone = StandaloneOptions::default();
+        standalone.title = Some("Other".to_string());
+        let html = render(&document, &HtmlOptions::default(), &standalone).unwrap();
+        assert!(html.contains("<title>Other</title>"));
+    }
+
+    #[test]
+    fn test_custom_template_and_styles() {
+        let document = parse_document(&lines(&["# A", "", "## B"])).unwrap();
+        let mut standalone = StandaloneOptions::default();
+        standalone.template = Template::new("{{title}}|{{toc}}|{{styles}}".to_string());
+        standalone.stylesheets = vec!["style.css".to_string()];
+        standalone.inline_css = vec!["p > a { color: red; }\n".to_string()];
+        assert_eq!(render(&document, &HtmlOptions::default(), &standalone).unwrap(),
+                   "A|<ul><li><a href=\"#a\">A</a><ul><li><a href=\"#b\">B</a></li>\
+                    </ul></li></ul>|<link rel=\"stylesheet\" href=\"style.css\" />\n\
+                    <style>\np > a { color: red; }\n</style>");
+    }
+
+    #[test]
+    fn test_front_matter_values() {
+        let document = parse_document(&lines(&["---", "title: <Notes>", "author: Ann",
+                                               "tags: [a, b]", "---", "# Heading"]))
+                       .unwrap();
+        let mut standalone = StandaloneOptions::default();
+        standalone.template = Template::new("{{title}} by {{author}} ({{tags}})\
+                                             {{missing}}".to_string());
+        assert_eq!(render(&document, &HtmlOptions::default(), &standalone).unwrap(),
+                   "&lt;Notes&gt; by Ann (a, b)");
+    }
+
+    #[test]
+    fn test_reserved_front_matter_keys() {
+        let document = parse_document(&lines(&["---", "body: x", "---", "Text."])).unwrap();
+        assert_eq!(render(&document, &HtmlOptions::default(), &StandaloneOptions::default()),
+                   Err("front matter key `body` is reserved for the template".to_string()));
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Templates for standalone HTML documents.
+
+use std::collections::HashMap;
+
+/// The template used for standalone documents when none is given.
+pub static DEFAULT_TEMPLATE: &'static str = "\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\" />
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />
+<title>{{title}}</title>
+{{styles}}
+</head>
+<body>
+{{body}}
+</body>
+</html>
+";
+
+/// A text template with `{{name}}` placeholders.
+///
+/// Rendering a template replaces each placeholder with the value of that
+/// name, or with nothing if the name has no value. Whitespace inside the
+/// braces is ignored, so `{{ title }}` works too.
+///
+#[derive(Clone, Debug)]
+pub struct Template {
+    source: String,
+}
+
+impl Template {
+    pub fn new(source: String) -> Template {
+        Template {
+            source: source,
+        }
+    }
+
+    /// The template as it was written.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn render(&self, values: &HashMap<String, String>) -> String {
+        let mut result = String::with_capacity(self.source.len());
+        let mut rest = &self.source[..];
+        while let Some(start) = rest.find("{{") {
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end,
+                None => break,
+            };
+            result.push_str(&rest[..start]);
+            let name = rest[start + 2..end].trim();
+            if let Some(value) = values.get(name) {
+                result.push_str(value);
+            }
+            rest = &rest[end + 2..];
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+impl Default for Template {
+    fn default() -> Template {
+        Template::new(DEFAULT_TEMPLATE.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Template;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render() {
+        let template = Template::new("<h1>{{title}}</h1>{{ body }}".to_string());
+        assert_eq!(template.render(&values(&[("title", "Hi"), ("body", "<p>x</p>")])),
+                   "<h1>Hi</h1><p>x</p>");
+    }
+
+    #[test]
+    fn test_missing_values_are_empty() {
+        let template = Template::new("a{{nothing}}b".to_string());
+        assert_eq!(template.render(&values(&[])), "ab");
+    }
+
+    #[test]
+    fn test_unclosed_placeholder() {
+        let template = Template::new("{{title}} {{oops".to_string());
+        assert_eq!(template.render(&values(&[("title", "T")])), "T {{oops");
+    }
+}
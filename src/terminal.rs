@@ -0,0 +1,429 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rendering documents for reading in a terminal.
+//!
+//! With colour, headings are bold and coloured, emphasis is italic, strong
+//! emphasis bold, code spans dimmed and link text underlined, using ANSI
+//! escape sequences. Without it, the output is the same as `plaintext`'s,
+//! apart from the list bullets, rules, block quotes and tables, which are
+//! drawn with box-drawing characters. Paragraphs and list items are
+//! wrapped to the terminal's width either way. Control characters in the
+//! document are replaced, so it can't send escape sequences of its own.
+
+use std::env;
+use std::os::raw::c_int;
+
+use std::cmp;
+
+use plaintext::{link_text, pad, wrap_measured};
+use types::{Document, MarkdownStructure, Heading, Paragraph, BulletList, Bullet, CodeBlock};
+use types::BlockQuote;
+use types::Table;
+use types::bulletlist::BulletElement;
+use types::inline::Contents;
+
+const BOLD: &'static str = "1";
+const DIM: &'static str = "2";
+const ITALIC: &'static str = "3";
+const UNDERLINE: &'static str = "4";
+
+/// The width to wrap to when `$COLUMNS` isn't set.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Options for rendering to a terminal.
+///
+#[derive(Clone, Debug)]
+pub struct TerminalOptions {
+    /// The column to wrap paragraphs and list items at, if any.
+    pub width: Option<usize>,
+    /// Whether to style the text with escape sequences.
+    pub colour: bool,
+}
+
+impl TerminalOptions {
+    /// Options for the terminal on standard output: as wide as `$COLUMNS`
+    /// says, and in colour unless standard output isn't a terminal or
+    /// `NO_COLOR` is set.
+    pub fn detect() -> TerminalOptions {
+        let width = env::var("COLUMNS").ok()
+            .and_then(|columns| columns.trim().parse().ok())
+            .and_then(|columns| if columns > 0 { Some(columns) } else { None })
+            .unwrap_or(DEFAULT_WIDTH);
+        let no_colour = env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty());
+        TerminalOptions {
+            width: Some(width),
+            colour: stdout_is_tty() && !no_colour,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn stdout_is_tty() -> bool {
+    extern "C" {
+        fn isatty(fd: c_int) -> c_int;
+    }
+    unsafe { isatty(1) != 0 }
+}
+
+#[cfg(not(unix))]
+fn stdout_is_tty() -> bool {
+    false
+}
+
+pub trait ToTerminal {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions);
+
+    /// Render for the terminal on standard output.
+    fn to_terminal(&self) -> String {
+        self.to_terminal_with(&TerminalOptions::detect())
+    }
+
+    fn to_terminal_with(&self, options: &TerminalOptions) -> String {
+        let mut out = String::new();
+        self.write_terminal(&mut out, options);
+        out
+    }
+}
+
+/// Writes the blocks separated by blank lines, with a final newline. Front
+/// matter and HTML blocks are left out.
+impl ToTerminal for Document {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        if write_blocks(self.blocks(), out, options) {
+            out.push('\n');
+        }
+    }
+}
+
+/// Write `blocks` separated by blank lines, leaving out HTML blocks.
+/// Returns whether anything was written.
+fn write_blocks(blocks: &[MarkdownStructure], out: &mut String,
+                options: &TerminalOptions) -> bool {
+    let blocks: Vec<&MarkdownStructure> = blocks.iter().filter(|block| !block.is_html()).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.push_str("\n\n");
+        }
+        block.write_terminal(out, options);
+    }
+    !blocks.is_empty()
+}
+
+impl ToTerminal for MarkdownStructure {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        match self {
+            &MarkdownStructure::MDH(ref heading) => heading.write_terminal(out, options),
+            &MarkdownStructure::MDP(ref paragraph) => paragraph.write_terminal(out, options),
+            &MarkdownStructure::MDB(ref list) => list.write_terminal(out, options),
+            &MarkdownStructure::MDR(_) => {
+                let rule = "─".repeat(options.width.unwrap_or(DEFAULT_WIDTH));
+                out.push_str(&style(DIM, &rule, options.colour));
+            }
+            &MarkdownStructure::MDC(ref code) => code.write_terminal(out, options),
+            &MarkdownStructure::MDX(_) => {}
+            &MarkdownStructure::MDQ(ref quote) => quote.write_terminal(out, options),
+            &MarkdownStructure::MDT(ref table) => table.write_terminal(out, options),
+        }
+    }
+}
+
+/// Quoted lines have a gutter down their left side, and are wrapped to
+/// fit in beside it.
+impl ToTerminal for BlockQuote {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        let width = options.width.unwrap_or(DEFAULT_WIDTH);
+        let inner = TerminalOptions {
+            width: Some(if width > 2 { width - 2 } else { 1 }),
+            colour: options.colour,
+        };
+        let mut text = String::new();
+        write_blocks(self.blocks(), &mut text, &inner);
+        let gutter = style(DIM, "│", options.colour);
+        let lines: Vec<String> = text.split('\n').map(|line| {
+            if line.is_empty() { gutter.clone() } else { format!("{} {}", gutter, line) }
+        }).collect();
+        out.push_str(&lines.join("\n"));
+    }
+}
+
+/// Tables are drawn in boxes, with the head in bold. They aren't wrapped.
+impl ToTerminal for Table {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        let head: Vec<String> = self.head().cells().iter().map(|cell| {
+            let text = inline_text(cell.contents(), false).replace('\n', " ");
+            style(BOLD, &text, options.colour)
+        }).collect();
+        let mut rows = vec![head];
+        for row in self.rows().iter() {
+            rows.push(row.cells().iter().map(|cell| {
+                inline_text(cell.contents(), options.colour).replace('\n', " ")
+            }).collect());
+        }
+        let mut widths = vec![0; self.alignments().len()];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = cmp::max(*width, visible_width(cell));
+            }
+        }
+        let border = |left: &str, middle: &str, right: &str| {
+            let lines: Vec<String> = widths.iter().map(|&n| "─".repeat(n + 2)).collect();
+            style(DIM, &format!("{}{}{}", left, lines.join(middle), right), options.colour)
+        };
+        let bar = style(DIM, "│", options.colour);
+        let mut lines = vec![border("┌", "┬", "┐")];
+        for (i, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = row.iter().enumerate().map(|(column, cell)| {
+                pad(cell, widths[column], self.alignments()[column], &visible_width)
+            }).collect();
+            lines.push(format!("{} {} {}", bar, cells.join(&format!(" {} ", bar)), bar));
+            if i == 0 && rows.len() > 1 {
+                lines.push(border("├", "┼", "┤"));
+            }
+        }
+        lines.push(border("└", "┴", "┘"));
+        out.push_str(&lines.join("\n"));
+    }
+}
+
+/// Code isn't wrapped. It's indented, and dimmed line by line.
+impl ToTerminal for CodeBlock {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        let lines: Vec<String> = self.code().lines().map(|line| {
+            if line.is_empty() {
+                String::new()
+            }
+            else {
+                format!("    {}", style(DIM, &printable(line), options.colour))
+            }
+        }).collect();
+        out.push_str(&lines.join("\n"));
+    }
+}
+
+/// Headings are bold, and coloured by level. Without colour they're
+/// underlined like `plaintext`'s.
+impl ToTerminal for Heading {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        if options.colour {
+            let codes = match self.depth() {
+                1 => "1;35",
+                2 => "1;36",
+                _ => BOLD,
+            };
+            let text = inline_text(self.contents(), false).replace('\n', " ");
+            out.push_str(&style(codes, &text, true));
+        }
+        else {
+            let text = inline_text(self.contents(), false).replace('\n', " ");
+            let underline = if self.depth() == 1 { "=" } else { "-" };
+            out.push_str(&text);
+            out.push('\n');
+            out.push_str(&underline.repeat(text.chars().count()));
+        }
+    }
+}
+
+impl ToTerminal for Paragraph {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        self.contents().write_terminal(out, options)
+    }
+}
+
+impl ToTerminal for Bullet {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        self.contents().write_terminal(out, options)
+    }
+}
+
+impl ToTerminal for BulletList {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        write_list(self, out, options, 0)
+    }
+}
+
+impl ToTerminal for Contents {
+    fn write_terminal(&self, out: &mut String, options: &TerminalOptions) {
+        let text = inline_text(self, options.colour);
+        out.push_str(&wrap_measured(&text, options.width, "", "", &visible_width));
+    }
+}
+
+/// Write a list with its markers indented by `indent` spaces, wrapping
+/// items to line up with their first line's text.
+fn write_list(list: &BulletList, out: &mut String, options: &TerminalOptions, indent: usize) {
+    let mut number = list.start().unwrap_or(1);
+    let mut nested_indent = indent + 2;
+    for (i, element) in list.elements().iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        match element {
+            &BulletElement::Lone(ref bullet) => {
+                let marker = if list.is_ordered() {
+                    number += 1;
+                    format!("{}. ", number - 1)
+                }
+                else {
+                    "• ".to_string()
+                };
+                let width = indent + marker.chars().count();
+                let first = format!("{}{} ", " ".repeat(indent),
+                                    style(BOLD, marker.trim_right(), options.colour));
+                let text = inline_text(bullet.contents(), options.colour);
+                let item = wrap_measured(&text, options.width, &first, &" ".repeat(width),
+                                         &visible_width);
+                out.push_str(item.trim_right());
+                nested_indent = width;
+            }
+            &BulletElement::Nested(ref nested) => write_list(nested, out, options, nested_indent),
+        }
+    }
+}
+
+/// The text of a chain of inline contents, with hard breaks as newlines.
+/// With `colour`, each word is styled on its own, so wrapping between
+/// words never leaves a style running into the indentation.
+fn inline_text(contents: &Contents, colour: bool) -> String {
+    let mut text = String::new();
+    for inline in contents.spans() {
+        match inline {
+            &Contents::Normal(ref s, _, _) => text.push_str(&printable(s)),
+            &Contents::Italic(ref s, _, _) => {
+                text.push_str(&style_words(ITALIC, &printable(s), colour))
+            }
+            &Contents::Bold(ref s, _, _) => text.push_str(&style_words(BOLD, &printable(s), colour)),
+            &Contents::Code(ref s, _, _) => text.push_str(&style_words(DIM, &printable(s), colour)),
+            &Contents::Link(ref s, ref url, _, _, _) => {
+                let (s, url) = (printable(s), printable(url));
+                if colour && !url.is_empty() && !s.is_empty() && s != url {
+                    text.push_str(&style_words(UNDERLINE, &s, true));
+                    text.push(' ');
+                    text.push_str(&style(DIM, &format!("<{}>", url), true));
+                }
+                else {
+                    text.push_str(&link_text(&s, &url));
+                }
+            }
+            &Contents::Image(ref alt, _, _, _) => {
+                text.push_str(&style_words(ITALIC, &printable(alt), colour))
+            }
+            &Contents::SoftBreak(..) => text.push(' '),
+            &Contents::HardBreak(..) => text.push('\n'),
+            &Contents::Nil => {}
+        }
+    }
+    text
+}
+
+/// `s` with the control characters that could move the cursor or start
+/// an escape sequence replaced, so the document can't drive the
+/// terminal. Tabs become spaces, and the rest U+FFFD REPLACEMENT
+/// CHARACTER.
+fn printable(s: &str) -> String {
+    s.chars().map(|c| match c {
+        '\t' => ' ',
+        c if c.is_control() => '\u{fffd}',
+        c => c,
+    }).collect()
+}
+
+/// `text` between the escape sequences setting `codes` and resetting
+/// them, if there's `colour`.
+fn style(codes: &str, text: &str, colour: bool) -> String {
+    if colour && !text.is_empty() {
+        format!("\x1b[{}m{}\x1b[0m", codes, text)
+    }
+    else {
+        text.to_string()
+    }
+}
+
+fn style_words(codes: &str, text: &str, colour: bool) -> String {
+    text.split(' ').map(|word| style(codes, word, colour)).collect::<Vec<_>>().join(" ")
+}
+
+/// How many columns `s` takes up, not counting escape sequences.
+pub fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut escape = false;
+    for c in s.chars() {
+        if escape {
+            escape = c != 'm';
+        }
+        else if c == '\x1b' {
+            escape = true;
+        }
+        else {
+            width += 1;
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ToTerminal, TerminalOptions, visible_width};
+    use convert::parse_document;
+    use testing::lines;
+
+    fn render(ls: &[&str], width: Option<usize>, colour: bool) -> String {
+        let lines = lines(ls);
+        let options = TerminalOptions { width: width, colour: colour };
+        parse_document(&lines).unwrap().to_terminal_with(&options)
+    }
+
+    #[test]
+    fn test_colour() {
+        assert_eq!(render(&["# Usage", "", "Run *it* with `--all`, see [docs](http://x.y).", "",
+                            "- **one** two"], None, true),
+                   "\x1b[1;35mUsage\x1b[0m\n\n\
+                    Run \x1b[3mit\x1b[0m with \x1b[2m--all\x1b[0m, see \
+                    \x1b[4mdocs\x1b[0m \x1b[2m<http://x.y>\x1b[0m.\n\n\
+                    \x1b[1m•\x1b[0m \x1b[1mone\x1b[0m two\n");
+    }
+
+    #[test]
+    fn test_without_colour() {
+        assert_eq!(render(&["## Usage", "", "1. first item here", "   - nested", "", "***"],
+                          Some(12), false),
+                   "Usage\n-----\n\n1. first\n   item here\n   • nested\n\n────────────\n");
+    }
+
+    #[test]
+    fn test_block_quotes() {
+        assert_eq!(render(&["> quoted text", ">", "> > more"], Some(10), false),
+                   "│ quoted\n│ text\n│\n│ │ more\n");
+        assert_eq!(render(&["> a"], None, true), "\x1b[2m│\x1b[0m a\n");
+    }
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(render(&["a | b", ":-: | -", "ccc | d"], None, false),
+                   "┌─────┬───┐\n│  a  │ b │\n├─────┼───┤\n│ ccc │ d │\n└─────┴───┘\n");
+        let out = render(&["a | b", "- | -", "*c* | d"], None, true);
+        assert!(out.contains("\x1b[1ma\x1b[0m"));
+        assert!(out.lines().all(|line| visible_width(line) == 9));
+    }
+
+    #[test]
+    fn test_control_characters() {
+        assert_eq!(render(&["a\x1b[2Jb\x07 *c\x08* [d](e\x1b]0;x\x07)"], None, true),
+                   "a\u{fffd}[2Jb\u{fffd} \x1b[3mc\u{fffd}\x1b[0m \x1b[4md\x1b[0m \
+                    \x1b[2m<e\u{fffd}]0;x\u{fffd}>\x1b[0m\n");
+        assert_eq!(render(&["    \x1bc\u{9b}"], None, false), "    \u{fffd}c\u{fffd}\n");
+    }
+
+    #[test]
+    fn test_wrapping_ignores_escapes() {
+        let out = render(&["**bold words** that wrap"], Some(15), true);
+        assert_eq!(out, "\x1b[1mbold\x1b[0m \x1b[1mwords\x1b[0m that\nwrap\n");
+        assert!(out.lines().all(|line| visible_width(line) <= 15));
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers shared by the unit tests.
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Owned lines, as the parser takes them.
+pub fn lines(ls: &[&str]) -> Vec<String> {
+    ls.iter().map(|s| s.to_string()).collect()
+}
+
+/// Write `contents` to the file at `path`, making its directory first.
+pub fn write(path: &Path, contents: &str) {
+    write_bytes(path, contents.as_bytes());
+}
+
+/// Like `write`, for contents that needn't be UTF-8.
+pub fn write_bytes(path: &Path, contents: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::File::create(path).unwrap().write_all(contents).unwrap();
+}
+
+/// The contents of the file at `path`.
+pub fn read(path: &Path) -> String {
+    let mut s = String::new();
+    fs::File::open(path).unwrap().read_to_string(&mut s).unwrap();
+    s
+}
+
+/// An empty directory for the test `name` to work in. It's unique to
+/// this process, so runs of the tests at the same time don't share it.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rustdown-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
@@ -7,14 +7,16 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::char;
 
+use entities;
 
 /// If string `s` starts with `c`, then return the remaining characters
 /// after `c` has been trimmed from the beginning, along with the number
 /// of occurrences of `c` in the beginning of string `s`.
 pub fn starting_chars(s: &str, c: char) -> Option<(String, u32)> {
     let mut result = None;
-    if s.trim_matches(c) != s {
+    if s.starts_with(c) {
         let mut count = 0u32;
         let mut found = false;
         let words: String = s.chars().filter_map(
@@ -38,10 +40,91 @@ pub fn all_chars_are(c: char, s: &str) -> bool {
     !s.is_empty() && s.chars().all(|x| x == c)
 }
 
+/// Resolve the backslash escapes and character references in `s`.
+///
+/// A backslash before any ASCII punctuation character yields that
+/// character literally, so `\\*` is a plain `*`. Named (`&copy;`),
+/// decimal (`&#169;`) and hexadecimal (`&#xA9;`) references are replaced
+/// with the characters they stand for. Anything that isn't a valid escape
+/// or reference is kept as written.
+pub fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find(|c| c == '\\' || c == '&') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with('\\') {
+            match rest[1..].chars().next() {
+                Some(c) if c.is_ascii_punctuation() => {
+                    result.push(c);
+                    rest = &rest[2..];
+                }
+                _ => {
+                    result.push('\\');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        else {
+            match entity_reference(rest) {
+                Some((decoded, len)) => {
+                    result.push_str(&decoded);
+                    rest = &rest[len..];
+                }
+                None => {
+                    result.push('&');
+                    rest = &rest[1..];
+                }
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Decode the character reference at the start of `s`, which must begin
+/// with `&`. On success, return the decoded text along with the number of
+/// bytes of `s` the reference occupied.
+fn entity_reference(s: &str) -> Option<(String, usize)> {
+    let end = match s[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '#') {
+        Some(i) if s[i + 1..].starts_with(';') => i + 1,
+        _ => return None,
+    };
+    let body = &s[1..end];
+    let decoded = if body.starts_with("#x") || body.starts_with("#X") {
+        numeric_reference(&body[2..], 16, 6)
+    }
+    else if body.starts_with('#') {
+        numeric_reference(&body[1..], 10, 7)
+    }
+    else if !body.is_empty() && body.chars().all(|c| c.is_ascii_alphanumeric()) {
+        entities::lookup(body).map(|e| e.to_string())
+    }
+    else {
+        None
+    };
+    decoded.map(|d| (d, end + 1))
+}
+
+/// Decode the digits of a numeric character reference. Code points that
+/// aren't valid characters (including NUL) become U+FFFD, as CommonMark
+/// requires.
+fn numeric_reference(digits: &str, radix: u32, max_len: usize) -> Option<String> {
+    if digits.is_empty() || digits.len() > max_len
+        || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let c = u32::from_str_radix(digits, radix).ok()
+        .and_then(|n| if n == 0 { None } else { char::from_u32(n) })
+        .unwrap_or('\u{fffd}');
+    Some(c.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::starting_chars;
     use super::all_chars_are;
+    use super::unescape;
 
     #[test]
     fn test_all_chars_are_fail() {
@@ -63,4 +146,34 @@ mod tests {
         assert_eq!(starting_chars("### haha", '#'), Some(("haha".to_string(), 3)));
         assert_eq!(starting_chars("    - bullet", ' '), Some(("- bullet".to_string(), 4)));
     }
+
+    #[test]
+    fn test_starting_chars_only_at_start() {
+        assert_eq!(starting_chars("haha #", '#'), None);
+        assert_eq!(starting_chars("\\# haha", '#'), None);
+    }
+
+    #[test]
+    fn test_unescape_backslashes() {
+        assert_eq!(unescape("\\* not a bullet"), "* not a bullet");
+        assert_eq!(unescape("\\# not a heading"), "# not a heading");
+        assert_eq!(unescape("\\\\"), "\\");
+        assert_eq!(unescape("\\a\\"), "\\a\\");
+        assert_eq!(unescape("\\&copy;"), "&copy;");
+    }
+
+    #[test]
+    fn test_unescape_named_entities() {
+        assert_eq!(unescape("&copy; 2014"), "\u{a9} 2014");
+        assert_eq!(unescape("&amp; &lt;&gt;"), "& <>");
+        assert_eq!(unescape("&nosuchentity; &copy"), "&nosuchentity; &copy");
+        assert_eq!(unescape("AT&T; & more"), "AT&T; & more");
+    }
+
+    #[test]
+    fn test_unescape_numeric_entities() {
+        assert_eq!(unescape("&#169;&#xA9;&#Xa9;"), "\u{a9}\u{a9}\u{a9}");
+        assert_eq!(unescape("&#0;&#xD800;&#1114112;"), "\u{fffd}\u{fffd}\u{fffd}");
+        assert_eq!(unescape("&#12345678; &#x; &#abc;"), "&#12345678; &#x; &#abc;");
+    }
 }
@@ -0,0 +1,57 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use parser::{Event, Tag, ToEvents};
+use span::Span;
+use super::MarkdownStructure;
+
+/// A block quote, and the blocks quoted in it.
+///
+#[derive(Clone, Debug)]
+pub struct BlockQuote {
+    blocks: Vec<MarkdownStructure>,
+    span: Span,
+}
+
+impl BlockQuote {
+    pub fn new(blocks: Vec<MarkdownStructure>) -> BlockQuote {
+        BlockQuote {
+            blocks: blocks,
+            span: Span::default(),
+        }
+    }
+
+    pub fn blocks(&self) -> &[MarkdownStructure] {
+        &self.blocks
+    }
+
+    pub fn blocks_mut(&mut self) -> &mut Vec<MarkdownStructure> {
+        &mut self.blocks
+    }
+
+    /// The part of the source the quote was parsed from, markers and all.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl ToEvents for BlockQuote {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::Start(Tag::BlockQuote), self.span));
+        for block in self.blocks.iter() {
+            block.to_events(events);
+        }
+        events.push((Event::End(Tag::BlockQuote), self.span));
+    }
+}
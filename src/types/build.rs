@@ -0,0 +1,188 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Building the document tree from the parser's events.
+
+use parser;
+use parser::Event;
+use parser::Parser;
+use parser::Tag;
+use parser::Alignment;
+use span::Span;
+use super::MarkdownStructure;
+use super::MarkdownStructure::{MDH, MDP, MDB, MDR, MDC, MDX, MDQ, MDT};
+use super::Heading;
+use super::Paragraph;
+use super::BulletList;
+use super::BlockQuote;
+use super::CodeBlock;
+use super::HtmlBlock;
+use super::{Table, TableRow, TableCell};
+use super::bulletlist::Bullet;
+use super::bulletlist::BulletElement;
+use super::inline::Contents;
+
+/// Parse a single block of lines. A block with no text in it is an
+/// empty paragraph.
+pub fn parse_block(block: &Vec<String>) -> MarkdownStructure {
+    let mut blocks = build_blocks(Parser::new(block).spanned());
+    if blocks.is_empty() {
+        return MDP(Paragraph::with_contents(Contents::Nil));
+    }
+    blocks.remove(0)
+}
+
+/// Build the document's structures from a stream of parse events and
+/// their spans.
+pub fn build_blocks<I: Iterator<Item=(Event, Span)>>(mut events: I) -> Vec<MarkdownStructure> {
+    let mut blocks = vec![];
+    while let Some((event, span)) = events.next() {
+        match event {
+            Event::Start(Tag::Heading(depth)) => {
+                let mut heading = Heading::with_contents(build_inline(&mut events), depth);
+                heading.set_span(span);
+                blocks.push(MDH(heading))
+            }
+            Event::Start(Tag::Paragraph) => {
+                let mut paragraph = Paragraph::with_contents(build_inline(&mut events));
+                paragraph.set_span(span);
+                blocks.push(MDP(paragraph))
+            }
+            Event::Start(Tag::List(start)) => {
+                blocks.push(MDB(build_list(&mut events, start, span)))
+            }
+            Event::Start(Tag::BlockQuote) => {
+                let mut quote = BlockQuote::new(build_blocks(quoted_events(&mut events).into_iter()));
+                quote.set_span(span);
+                blocks.push(MDQ(quote))
+            }
+            Event::Start(Tag::Table(alignments)) => {
+                blocks.push(MDT(build_table(&mut events, alignments, span)))
+            }
+            Event::Rule => blocks.push(MDR(span)),
+            Event::CodeBlock(info, code) => {
+                let mut code = CodeBlock::new(info, code);
+                code.set_span(span);
+                blocks.push(MDC(code))
+            }
+            Event::Html(html) => {
+                let mut html = HtmlBlock::new(html);
+                html.set_span(span);
+                blocks.push(MDX(html))
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// Take the events inside a block quote, up to the end of the quote.
+fn quoted_events<I: Iterator<Item=(Event, Span)>>(events: &mut I) -> Vec<(Event, Span)> {
+    let mut quoted = vec![];
+    let mut depth = 0;
+    while let Some((event, span)) = events.next() {
+        match event {
+            Event::Start(Tag::BlockQuote) => depth += 1,
+            Event::End(Tag::BlockQuote) if depth == 0 => break,
+            Event::End(Tag::BlockQuote) => depth -= 1,
+            _ => {}
+        }
+        quoted.push((event, span));
+    }
+    quoted
+}
+
+/// Build inline contents from events, up to the end of the enclosing
+/// block.
+pub fn build_inline<I: Iterator<Item=(Event, Span)>>(events: &mut I) -> Contents {
+    let mut spans = vec![];
+    while let Some((event, span)) = events.next() {
+        let nil = Box::new(Contents::Nil);
+        spans.push(match event {
+            Event::Text(s) => Contents::Normal(s, span, nil),
+            Event::Code(s) => Contents::Code(s, span, nil),
+            Event::SoftBreak => Contents::SoftBreak(span, nil),
+            Event::HardBreak => Contents::HardBreak(span, nil),
+            Event::Start(Tag::Emphasis) => {
+                Contents::Italic(parser::collect_text(events), span, nil)
+            }
+            Event::Start(Tag::Strong) => Contents::Bold(parser::collect_text(events), span, nil),
+            Event::Start(Tag::Link(url, title)) => {
+                Contents::Link(parser::collect_text(events), url, title, span, nil)
+            }
+            Event::Start(Tag::Image(src)) => {
+                Contents::Image(parser::collect_text(events), src, span, nil)
+            }
+            _ => break,
+        });
+    }
+    Contents::from_spans(spans)
+}
+
+fn build_table<I>(events: &mut I, alignments: Vec<Alignment>, span: Span) -> Table
+    where I: Iterator<Item=(Event, Span)>
+{
+    let mut head = None;
+    let mut rows = vec![];
+    while let Some((event, span)) = events.next() {
+        match event {
+            Event::Start(Tag::TableRow) => rows.push(build_row(events, span)),
+            Event::End(Tag::TableHead) => head = rows.pop(),
+            Event::End(Tag::Table(_)) => break,
+            _ => {}
+        }
+    }
+    let mut table = Table::new(alignments, head.unwrap_or(TableRow::new(vec![])));
+    table.set_span(span);
+    for row in rows {
+        table.push(row);
+    }
+    table
+}
+
+fn build_row<I: Iterator<Item=(Event, Span)>>(events: &mut I, span: Span) -> TableRow {
+    let mut cells = vec![];
+    while let Some((event, span)) = events.next() {
+        match event {
+            Event::Start(Tag::TableCell) => {
+                let mut cell = TableCell::with_contents(build_inline(events));
+                cell.set_span(span);
+                cells.push(cell)
+            }
+            _ => break,
+        }
+    }
+    let mut row = TableRow::new(cells);
+    row.set_span(span);
+    row
+}
+
+fn build_list<I>(events: &mut I, start: Option<u64>, span: Span) -> BulletList
+    where I: Iterator<Item=(Event, Span)>
+{
+    let mut list = match start {
+        Some(start) => BulletList::new_ordered(start),
+        None => BulletList::new_unordered(),
+    };
+    list.set_span(span);
+    while let Some((event, span)) = events.next() {
+        match event {
+            Event::Start(Tag::Item) => {
+                let mut bullet = Bullet::with_contents(build_inline(events));
+                bullet.set_span(span);
+                list.push(BulletElement::Lone(bullet))
+            }
+            Event::Start(Tag::List(start)) => {
+                list.push(BulletElement::Nested(build_list(events, start, span)))
+            }
+            _ => break,
+        }
+    }
+    list
+}
@@ -7,9 +7,11 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use html::ToHtml;
-use html::Html;
-use text;
+use parser::{Event, Tag, ToEvents};
+use span::Span;
+use types::MarkdownStructure;
+use super::build;
+use super::inline::Contents;
 
 /// An element in a bulleted list.
 ///
@@ -24,11 +26,11 @@ pub enum BulletElement {
 
 impl BulletElement {}
 
-impl ToHtml for BulletElement {
-    fn to_html(&self) -> Html {
+impl ToEvents for BulletElement {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
         match self {
-            &BulletElement::Nested(ref bullet_list) => bullet_list.to_html(),
-            &BulletElement::Lone(ref bullet) => bullet.to_html(),
+            &BulletElement::Nested(ref bullet_list) => bullet_list.to_events(events),
+            &BulletElement::Lone(ref bullet) => bullet.to_events(events),
         }
     }
 }
@@ -42,8 +44,9 @@ impl ToHtml for BulletElement {
 ///
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct Bullet {
-    contents: String,
+    contents: Contents,
     tag: String,
+    span: Span,
 }
 
 /// A markdown bullet.
@@ -54,17 +57,40 @@ pub struct Bullet {
 ///
 impl Bullet {
     pub fn new(contents: String) -> Bullet {
+        Bullet::with_contents(Contents::new(contents))
+    }
+
+    pub fn with_contents(contents: Contents) -> Bullet {
         Bullet {
             contents: contents,
             tag: "li".to_string(),
+            span: Span::default(),
         }
     }
+
+    pub fn contents(&self) -> &Contents {
+        &self.contents
+    }
+
+    pub fn contents_mut(&mut self) -> &mut Contents {
+        &mut self.contents
+    }
+
+    /// The part of the source the item was parsed from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
-impl ToHtml for Bullet {
-    fn to_html(&self) -> Html {
-        let name: String = self.tag.clone();
-        Html::new_simple(name, self.contents.clone())
+impl ToEvents for Bullet {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::Start(Tag::Item), self.span));
+        self.contents.to_events(events);
+        events.push((Event::End(Tag::Item), self.span));
     }
 }
 
@@ -80,97 +106,87 @@ impl ToHtml for Bullet {
 pub struct BulletList {
     contents: Vec<BulletElement>,
     tag: String,
+    start: u64,
+    span: Span,
 }
 
 impl BulletList {
-    /// Create a new empty ordered list (`ol` is the tag).
-    fn new_ordered() -> BulletList {
+    /// Create a new empty ordered list (`ol` is the tag), numbered
+    /// from `start`.
+    pub fn new_ordered(start: u64) -> BulletList {
         BulletList {
             contents: vec![],
             tag: "ol".to_string(),
+            start: start,
+            span: Span::default(),
         }
     }
 
     /// Create a new empty unordered list (`ul` is the tag).
-    fn new_unordered() -> BulletList {
+    pub fn new_unordered() -> BulletList {
         BulletList {
             contents: vec![],
             tag: "ul".to_string(),
+            start: 1,
+            span: Span::default(),
         }
     }
 
     /// Add a `BulletElement` to this list.
-    fn push(&mut self, elem: BulletElement) {
+    pub fn push(&mut self, elem: BulletElement) {
         self.contents.push(elem);
     }
 
-    /// Add a string bullet to this list, with depth same as
-    /// this list.
-    fn push_string(&mut self, s: String) {
-        self.push(BulletElement::Lone(Bullet::new(s)))
+    pub fn elements(&self) -> &[BulletElement] {
+        &self.contents
+    }
+
+    pub fn elements_mut(&mut self) -> &mut Vec<BulletElement> {
+        &mut self.contents
+    }
+
+    pub fn is_ordered(&self) -> bool {
+        self.tag == "ol"
     }
 
-    fn is_numeric_bullet<'a>(s: String) -> Option<(String, u32)> {
-        text::starting_chars(&s, ' ')
-        .and_then(|(s, i)| {
-            if i % 4 == 0 {
-                Some((s, i))
-            }
-            else {
-                None
-            }
-        })
+    /// The number of the first item, for ordered lists.
+    pub fn start(&self) -> Option<u64> {
+        if self.is_ordered() { Some(self.start) } else { None }
+    }
+
+    /// The part of the source the list was parsed from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
     }
 }
 
-impl ToHtml for BulletList {
-    fn to_html(&self) -> Html {
-        let name: String = "ul".to_string();
-        let mut html = Html::new_empty(name);
+impl ToEvents for BulletList {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        let tag = Tag::List(self.start());
+        events.push((Event::Start(tag.clone()), self.span));
         for bullet in self.contents.iter() {
-            let tag = bullet.to_html();
-            html.add_tag(tag);
+            bullet.to_events(events);
         }
-        html
+        events.push((Event::End(tag), self.span));
     }
 }
 
+/// Parse a block as a list, returning `Some(list)` if it is one.
 pub fn parse_bulletlist(b: &Vec<String>) -> Option<BulletList> {
-    // TODO: Doesn't support nested lists!
-    let mut c: Option<&str> = None;
-    let mut bullets = BulletList::new_unordered();
-    for s in b.iter() {
-        let st = s.trim_left();
-        if st.is_empty() { continue }
-        match &st.as_bytes()[..1] {
-            b"-" => {
-                match c {
-                    None => c = Some("-"),
-                    Some("-") => (),
-                    _ => return None
-                }
-                let s = st[1..].trim_left().to_string();
-                bullets.push_string(s)
-            }
-            b"*" => {
-                match c {
-                    None => c = Some("-"),
-                    Some("-") => (),
-                    _ => return None
-                }
-                let s = st[1..].trim_left().to_string();
-                bullets.push_string(s)
-            }
-            _ => return None,
-        }
+    match build::parse_block(b) {
+        MarkdownStructure::MDB(bullets) => Some(bullets),
+        _ => None,
     }
-    Some(bullets)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use html::ToHtml;
+    use html::WriteHtml;
 
     #[test]
     fn test_bullet_fmt() {
@@ -191,10 +207,19 @@ mod tests {
     #[test]
     fn test_parse_bullet_list() {
         let s = vec!["- One".to_string(), "- Two".to_string()];
-        let parsed = parse_bulletlist(&s);
+        let parsed = parse_bulletlist(&s).unwrap();
         let mut bullets = BulletList::new_unordered();
         bullets.push(BulletElement::Lone(Bullet::new("One".to_string())));
         bullets.push(BulletElement::Lone(Bullet::new("Two".to_string())));
-        assert_eq!(parsed, Some(bullets));
+        assert_eq!(format!("{}", parsed.to_html()), format!("{}", bullets.to_html()));
+    }
+
+    #[test]
+    fn test_parse_nested_list() {
+        let s = vec!["3. One".to_string(), "   + Two".to_string(), "4) Three".to_string()];
+        let parsed = parse_bulletlist(&s).unwrap();
+        assert_eq!(format!("{}", parsed.to_html()),
+                   "<ol start=\"3\"><li>One</li><ul><li>Two</li></ul><li>Three</li></ol>");
+        assert_eq!(parse_bulletlist(&vec!["- One".to_string(), "1. Two".to_string()]), None);
     }
 }
@@ -0,0 +1,63 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use parser::{Event, ToEvents};
+use span::Span;
+
+/// A fenced or indented code block. Its code is kept exactly as it was
+/// written, with a newline at the end of every line.
+///
+#[derive(Clone, Debug)]
+pub struct CodeBlock {
+    info: String,
+    code: String,
+    span: Span,
+}
+
+impl CodeBlock {
+    pub fn new(info: String, code: String) -> CodeBlock {
+        CodeBlock {
+            info: info,
+            code: code,
+            span: Span::default(),
+        }
+    }
+
+    /// The info string after the opening fence, which is empty for
+    /// indented code.
+    pub fn info(&self) -> &str {
+        &self.info
+    }
+
+    /// The first word of the info string, which names the language of
+    /// the code, if there is one.
+    pub fn language(&self) -> Option<&str> {
+        self.info.split_whitespace().next()
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The part of the source the code block was parsed from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl ToEvents for CodeBlock {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::CodeBlock(self.info.clone(), self.code.clone()), self.span));
+    }
+}
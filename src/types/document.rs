@@ -0,0 +1,262 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::slice;
+use std::vec;
+
+use html::Html;
+use html::HtmlAttribute;
+use html::HtmlOptions;
+use html::WriteHtml;
+use html::write_events;
+use metadata::{Metadata, MetaValue};
+use parser::{Event, ToEvents};
+use span::Span;
+use slug::Slugger;
+use super::Heading;
+use super::MarkdownStructure;
+
+/// A whole markdown document, as the sequence of blocks it is made of,
+/// along with the metadata from its front matter.
+///
+#[derive(Clone, Debug)]
+pub struct Document {
+    blocks: Vec<MarkdownStructure>,
+    metadata: Metadata,
+    front_matter: Option<Vec<String>>,
+}
+
+impl Document {
+    pub fn new(blocks: Vec<MarkdownStructure>) -> Document {
+        Document::with_metadata(blocks, Metadata::new())
+    }
+
+    pub fn with_metadata(blocks: Vec<MarkdownStructure>, metadata: Metadata) -> Document {
+        Document {
+            blocks: blocks,
+            metadata: metadata,
+            front_matter: None,
+        }
+    }
+
+    pub fn blocks<'a>(&'a self) -> &'a [MarkdownStructure] {
+        &self.blocks
+    }
+
+    pub fn blocks_mut<'a>(&'a mut self) -> &'a mut Vec<MarkdownStructure> {
+        &mut self.blocks
+    }
+
+    pub fn metadata<'a>(&'a self) -> &'a Metadata {
+        &self.metadata
+    }
+
+    /// The lines of front matter the metadata was read from, fences and
+    /// all, if the document was parsed from markdown that had any.
+    pub fn front_matter<'a>(&'a self) -> Option<&'a [String]> {
+        self.front_matter.as_ref().map(|lines| &lines[..])
+    }
+
+    pub fn set_front_matter(&mut self, lines: Vec<String>) {
+        self.front_matter = Some(lines);
+    }
+
+    /// Look up a single metadata value.
+    pub fn meta<'a>(&'a self, key: &str) -> Option<&'a MetaValue> {
+        self.metadata.get(key)
+    }
+
+    /// All the headings in the document, in order.
+    pub fn headings<'a>(&'a self) -> Vec<&'a Heading> {
+        self.blocks.iter().filter_map(|block| {
+            match block {
+                &MarkdownStructure::MDH(ref heading) => Some(heading),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// The document's title. This is the `title` from the front matter if
+    /// there is one, or else the text of the first heading.
+    pub fn title(&self) -> Option<String> {
+        self.meta("title").and_then(|title| title.as_str()).map(|title| title.to_string())
+            .or(self.headings().first().map(|heading| heading.text()))
+    }
+
+    /// The anchor names of the headings, in the same order as
+    /// `headings()`.
+    pub fn heading_ids(&self) -> Vec<String> {
+        let mut slugger = Slugger::new();
+        self.headings().iter().map(|heading| slugger.slug(&heading.text())).collect()
+    }
+
+    /// The events of the document's blocks, as the parser would produce
+    /// them. Each block's events are only made once the ones before have
+    /// been consumed.
+    pub fn events<'a>(&'a self) -> Events<'a> {
+        Events {
+            blocks: self.blocks.iter(),
+            pending: vec![].into_iter(),
+        }
+    }
+
+    /// Render the document's blocks, one per line, or pretty-printed if
+    /// `options.pretty` is set.
+    pub fn body_html(&self, options: &HtmlOptions) -> String {
+        self.to_html_with(options)
+    }
+
+    /// A table of contents, as nested lists of links to the headings.
+    /// Returns `None` if the document has no headings.
+    pub fn toc(&self) -> Option<Html> {
+        let entries: Vec<(u32, String, String)> =
+            self.headings().iter().zip(self.heading_ids().into_iter())
+                .map(|(heading, id)| (heading.depth(), id, heading.text()))
+                .collect();
+        if entries.is_empty() {
+            None
+        }
+        else {
+            Some(toc_list(&entries))
+        }
+    }
+}
+
+/// Writes the same thing as `body_html`, a block at a time.
+impl WriteHtml for Document {
+    fn write_html<W: fmt::Write>(&self, w: &mut W, options: &HtmlOptions) -> fmt::Result {
+        write_events(self.events(), w, options)
+    }
+}
+
+/// An iterator over the events of a document. See `Document::events`.
+pub struct Events<'a> {
+    blocks: slice::Iter<'a, MarkdownStructure>,
+    pending: vec::IntoIter<(Event, Span)>,
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = (Event, Span);
+
+    fn next(&mut self) -> Option<(Event, Span)> {
+        loop {
+            if let Some(event) = self.pending.next() {
+                return Some(event);
+            }
+            let mut events = vec![];
+            match self.blocks.next() {
+                Some(block) => block.to_events(&mut events),
+                None => return None,
+            }
+            self.pending = events.into_iter();
+        }
+    }
+}
+
+/// Build a list out of `(depth, id, text)` entries. Entries deeper than
+/// the one before them are nested inside its list item.
+fn toc_list(entries: &[(u32, String, String)]) -> Html {
+    let mut list = Html::new_empty("ul".to_string());
+    let mut i = 0;
+    while i < entries.len() {
+        let (depth, ref id, ref text) = entries[i];
+        let end = entries[i + 1..].iter().position(|entry| entry.0 <= depth)
+                                  .map_or(entries.len(), |n| i + 1 + n);
+        let mut link = Html::new_simple("a".to_string(), text.clone());
+        link.add_attribute(HtmlAttribute::new("href".to_string(), format!("#{}", id)));
+        let mut item = Html::new_empty("li".to_string());
+        item.add_tag(link);
+        if end > i + 1 {
+            item.add_tag(toc_list(&entries[i + 1..end]));
+        }
+        list.add_tag(item);
+        i = end;
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use convert::parse_document;
+    use html::HtmlOptions;
+    use parser::{Event, Parser};
+    use testing::lines;
+
+    #[test]
+    fn test_title() {
+        let document = parse_document(&lines(&["Intro text.", "", "# Title", "", "## Sub"])).unwrap();
+        assert_eq!(document.title(), Some("Title".to_string()));
+        let document = parse_document(&lines(&["No headings here."])).unwrap();
+        assert_eq!(document.title(), None);
+        let document = parse_document(&lines(&["---", "title: Front", "---", "# Title"]))
+                       .unwrap();
+        assert_eq!(document.title(), Some("Front".to_string()));
+    }
+
+    #[test]
+    fn test_body_html() {
+        let document = parse_document(&lines(&["# Title", "", "Some text.", "", "# Title"]))
+                       .unwrap();
+        let mut options = HtmlOptions::default();
+        assert_eq!(document.body_html(&options),
+                   "<h1>Title</h1>\n<p>Some text.</p>\n<h1>Title</h1>");
+        options.heading_ids = true;
+        assert_eq!(document.body_html(&options),
+                   "<h1 id=\"title\">Title</h1>\n<p>Some text.</p>\n\
+                    <h1 id=\"title-1\">Title</h1>");
+    }
+
+    #[test]
+    fn test_events_match_parser() {
+        let input = lines(&["# A <b>", "", "Some  ", "*text* &amp;", "[more](m.md)", "",
+                            "- x", "  1. y", "", "Setext", "---", "", "# A <b>"]);
+        let document = parse_document(&input).unwrap();
+        let events: Vec<Event> = document.events().map(|(event, _)| event).collect();
+        let parsed: Vec<Event> = Parser::new(&input).collect();
+        assert_eq!(events, parsed);
+    }
+
+    #[test]
+    fn test_sourcepos() {
+        let document = parse_document(&lines(&["---", "title: T", "---", "# A", "",
+                                               "b *c*", "", "- d"])).unwrap();
+        let mut options = HtmlOptions::default();
+        options.sourcepos = true;
+        options.heading_ids = true;
+        assert_eq!(document.body_html(&options),
+                   "<h1 data-sourcepos=\"4:1-4:3\" id=\"a\">A</h1>\n\
+                    <p data-sourcepos=\"6:1-6:5\">b <em>c</em></p>\n\
+                    <ul data-sourcepos=\"8:1-8:3\"><li data-sourcepos=\"8:1-8:3\">d</li></ul>");
+        let spans: Vec<String> = document.blocks().iter()
+                                         .map(|block| format!("{}", block.span())).collect();
+        assert_eq!(spans, vec!["4:1-4:3", "6:1-6:5", "8:1-8:3"]);
+    }
+
+    #[test]
+    fn test_pretty_body_html() {
+        let document = parse_document(&lines(&["# Title", "", "- a", "- b"])).unwrap();
+        let mut options = HtmlOptions::default();
+        options.pretty = Some(2);
+        assert_eq!(document.body_html(&options),
+                   "<h1>Title</h1>\n<ul>\n  <li>a</li>\n  <li>b</li>\n</ul>");
+    }
+
+    #[test]
+    fn test_toc() {
+        let document = parse_document(&lines(&["# A", "", "## B", "", "### C", "",
+                                               "## D", "", "# E"])).unwrap();
+        assert_eq!(format!("{}", document.toc().unwrap()),
+                   "<ul><li><a href=\"#a\">A</a><ul>\
+                    <li><a href=\"#b\">B</a><ul><li><a href=\"#c\">C</a></li></ul></li>\
+                    <li><a href=\"#d\">D</a></li></ul></li>\
+                    <li><a href=\"#e\">E</a></li></ul>");
+        assert!(parse_document(&lines(&["text"])).unwrap().toc().is_none());
+    }
+}
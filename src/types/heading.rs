@@ -9,30 +9,64 @@
 
 use std::cmp::PartialEq;
 
-use html::ToHtml;
-use html::Html;
-use text;
-
-macro_rules! parse (
-    ($e:expr) => (match $e { Some(e) => return Some(e), None => () })
-);
+use parser::{Event, Tag, ToEvents};
+use span::Span;
+use types::MarkdownStructure;
+use super::build;
+use super::inline::Contents;
 
 /// A markdown text heading. `depth` signifies the level of the heading, e.g.,
 /// `# head` is depth 1, `## head` is depth 2, and so on.
 ///
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Heading {
-    contents: String,
+    contents: Contents,
     depth: u32,
+    span: Span,
 }
 
 impl Heading {
     pub fn new(title: String, depth: u32) -> Heading {
+        Heading::with_contents(Contents::new(title), depth)
+    }
+
+    pub fn with_contents(contents: Contents, depth: u32) -> Heading {
         Heading {
-            contents: title,
+            contents: contents,
             depth: depth,
+            span: Span::default(),
         }
     }
+
+    pub fn contents(&self) -> &Contents {
+        &self.contents
+    }
+
+    pub fn contents_mut(&mut self) -> &mut Contents {
+        &mut self.contents
+    }
+
+    /// The text of the heading, without any inline markup.
+    pub fn text(&self) -> String {
+        self.contents.plain_text()
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: u32) {
+        self.depth = depth;
+    }
+
+    /// The part of the source the heading was parsed from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl PartialEq for Heading {
@@ -45,62 +79,30 @@ impl PartialEq for Heading {
     }
 }
 
-impl ToHtml for Heading {
-    fn to_html(&self) -> Html {
-        let name: String = format!("h{}", self.depth);
-        Html::new_simple(name, self.contents.clone())
+impl ToEvents for Heading {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::Start(Tag::Heading(self.depth)), self.span));
+        self.contents.to_events(events);
+        events.push((Event::End(Tag::Heading(self.depth)), self.span));
     }
 }
 
-/// Parse all the different types of headings, regardless
-/// of how they're formatted. Return `Some(heading)` if one was found
-/// in this block.
+/// Parse a block as a heading, returning `Some(heading)` if it is one.
 pub fn parse_heading(block: &Vec<String>) -> Option<Heading> {
-    parse!(pound_heading(block));
-    parse!(line_heading(block));
-    None
-}
-
-/// A heading created with a pound sign, like:
-///
-///     # Heading
-///
-fn pound_heading(b: &Vec<String>) -> Option<Heading> {
-    if b.len() != 1 { return None }
-    let s = &b[0];
-    match text::starting_chars(s, '#') {
-        Some((title, count)) => Some(Heading::new(title.to_string(), count)),
-        None => None,
-    }
-}
-
-/// A heading created with an underline, like:
-///
-///     Heading
-///     =======
-///
-fn line_heading(b: &Vec<String>) -> Option<Heading> {
-    if b.len() != 2 { return None }
-    let mut depth = 0usize;
-    if text::all_chars_are('=', &b[1]) {
-        return Some(Heading::new(b[0].to_string(), 1));
-    }
-    if text::all_chars_are('-', &b[1]) {
-        return Some(Heading::new(b[0].to_string(), 2));
+    match build::parse_block(block) {
+        MarkdownStructure::MDH(heading) => Some(heading),
+        _ => None,
     }
-    return None;
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Heading;
-    use super::pound_heading;
-    use super::line_heading;
-    use html::ToHtml;
+    use super::parse_heading;
+    use html::WriteHtml;
 
     fn pound_heading_equals(input: &str, result: &str) {
         let block = &vec![input.to_string()];
-        match pound_heading(block) {
+        match parse_heading(block) {
             Some(heading) => {
                 assert_eq!(format!("{}", heading.to_html()), result.to_string());
             }
@@ -110,32 +112,10 @@ mod tests {
 
     #[test]
     fn test_pound_heads() {
-        assert_eq!(pound_heading(&vec!["# Hello, world".to_string()]),
-                   Some(Heading {
-                       contents: "Hello, world".to_string(),
-                       depth: 1,
-                   })
-                   );
-        assert_eq!(pound_heading(&vec!("## Hello again, world!".to_string())),
-                   Some(Heading {
-                       contents: "Hello again, world!".to_string(),
-                       depth: 2,
-                   }));
-    }
-
-    fn test_line_heads() {
-        assert_eq!(line_heading(&vec!("Hello\n=====".to_string())),
-                   Some(Heading {
-                       contents: "Hello".to_string(),
-                       depth: 1,
-                   }));
-        assert_eq!(line_heading(&vec!("Hello\n-----".to_string())),
-                   Some(Heading {
-                       contents: "Hello".to_string(),
-                       depth: 2,
-                   }));
-        assert_eq!(line_heading(&vec!("Hello\n =====".to_string())),
-                   None);
+        let heading = parse_heading(&vec!["# Hello, world".to_string()]).unwrap();
+        assert_eq!((heading.text(), heading.depth()), ("Hello, world".to_string(), 1));
+        let heading = parse_heading(&vec!("## Hello again, world!".to_string())).unwrap();
+        assert_eq!((heading.text(), heading.depth()), ("Hello again, world!".to_string(), 2));
     }
 
     #[test]
@@ -145,11 +125,12 @@ mod tests {
                              "<h2>Hello again, world!</h2>");
         pound_heading_equals("## <h2>Hello again, world!</h2>",
                              "<h2>&lt;h2&gt;Hello again, world!&lt;/h2&gt;</h2>");
+        pound_heading_equals("# A *fine* `day`", "<h1>A <em>fine</em> <code>day</code></h1>");
     }
 
     #[test]
-    fn test_non_pound_heads() {
-        assert_eq!(pound_heading(&vec!("Hello, world".to_string())), None);
-        assert_eq!(pound_heading(&vec!(" ## Hello, world".to_string())), None);
+    fn test_pound_heads_escapes() {
+        pound_heading_equals("# &copy; 2014 &amp; \\#1", "<h1>\u{a9} 2014 &amp; #1</h1>");
+        pound_heading_equals("# &amp;copy;", "<h1>&amp;copy;</h1>");
     }
 }
@@ -0,0 +1,62 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use parser::{Event, ToEvents};
+use span::Span;
+
+/// A block of raw HTML, such as a `<div>` or a comment. It's passed
+/// through to HTML as it was written, and left out of other formats.
+///
+#[derive(Clone, Debug)]
+pub struct HtmlBlock {
+    html: String,
+    span: Span,
+}
+
+impl HtmlBlock {
+    pub fn new(html: String) -> HtmlBlock {
+        HtmlBlock {
+            html: html,
+            span: Span::default(),
+        }
+    }
+
+    /// The lines of the block, joined with newlines.
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    /// The text of the block if it's nothing but a single comment, like
+    /// `<!-- more -->`, without the comment markers or the whitespace
+    /// around it.
+    pub fn comment(&self) -> Option<&str> {
+        let html = self.html.trim();
+        if html.len() < 7 || !html.starts_with("<!--") || !html.ends_with("-->") {
+            return None;
+        }
+        let text = &html[4..html.len() - 3];
+        if text.contains("-->") { None } else { Some(text.trim()) }
+    }
+
+    /// The part of the source the block was parsed from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl ToEvents for HtmlBlock {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::Html(self.html.clone()), self.span));
+    }
+}
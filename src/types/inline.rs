@@ -7,29 +7,275 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use url::Url;
-
-enum Contents {
-    Italic(String, Box<Contents>),
-    Bold(String, Box<Contents>),
-    Code(String, Box<Contents>),
-    Normal(String, Box<Contents>),
-    Link(String, Url, Box<Contents>),
+use parser;
+use parser::{Event, Tag, ToEvents};
+use parser::inline::paragraph_inline_events;
+use super::build;
+use span;
+use span::{Position, Span};
+
+/// Inline markdown contents, as a chain of spans. Each span holds the
+/// part of the source it was parsed from, and the rest of the chain,
+/// which ends in `Nil`.
+///
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Contents {
+    Italic(String, Span, Box<Contents>),
+    Bold(String, Span, Box<Contents>),
+    Code(String, Span, Box<Contents>),
+    Normal(String, Span, Box<Contents>),
+    /// A link's text, destination and title. The destination is kept as
+    /// it was written, since relative links aren't valid `Url`s on their
+    /// own.
+    Link(String, String, Option<String>, Span, Box<Contents>),
+    /// An image's alt text and source.
+    Image(String, String, Span, Box<Contents>),
+    /// A line ending inside a paragraph. How it is rendered is
+    /// controlled by `HtmlOptions::soft_break`.
+    SoftBreak(Span, Box<Contents>),
+    /// A line ending that was explicitly marked as a break, with either
+    /// two trailing spaces or a trailing backslash.
+    HardBreak(Span, Box<Contents>),
     Nil
 }
 
 impl Contents {
-    fn new(s: String) -> Contents {
-        Contents::Normal(s, Box::new(Contents::Nil))
+    pub fn new(s: String) -> Contents {
+        Contents::Normal(s, Span::default(), Box::new(Contents::Nil))
+    }
+
+    /// Chain single spans together, in order. The rest of each span in
+    /// `spans` is replaced.
+    pub fn from_spans(spans: Vec<Contents>) -> Contents {
+        spans.into_iter().rev().fold(Contents::Nil, |next, span| span.with_next(next))
+    }
+
+    /// This span, followed by `next` instead of the rest of its chain.
+    pub fn with_next(self, next: Contents) -> Contents {
+        let next = Box::new(next);
+        match self {
+            Contents::Italic(s, span, _) => Contents::Italic(s, span, next),
+            Contents::Bold(s, span, _) => Contents::Bold(s, span, next),
+            Contents::Code(s, span, _) => Contents::Code(s, span, next),
+            Contents::Normal(s, span, _) => Contents::Normal(s, span, next),
+            Contents::Link(s, url, title, span, _) => Contents::Link(s, url, title, span, next),
+            Contents::Image(alt, src, span, _) => Contents::Image(alt, src, span, next),
+            Contents::SoftBreak(span, _) => Contents::SoftBreak(span, next),
+            Contents::HardBreak(span, _) => Contents::HardBreak(span, next),
+            Contents::Nil => *next,
+        }
+    }
+
+    /// The rest of the chain after this span, if any.
+    pub fn next<'a>(&'a self) -> Option<&'a Contents> {
+        match self {
+            &Contents::Italic(_, _, ref next) => Some(next),
+            &Contents::Bold(_, _, ref next) => Some(next),
+            &Contents::Code(_, _, ref next) => Some(next),
+            &Contents::Normal(_, _, ref next) => Some(next),
+            &Contents::Link(_, _, _, _, ref next) => Some(next),
+            &Contents::Image(_, _, _, ref next) => Some(next),
+            &Contents::SoftBreak(_, ref next) => Some(next),
+            &Contents::HardBreak(_, ref next) => Some(next),
+            &Contents::Nil => None,
+        }
+    }
+
+    /// The rest of the chain after this span, mutably.
+    pub fn next_mut<'a>(&'a mut self) -> Option<&'a mut Contents> {
+        match self {
+            &mut Contents::Italic(_, _, ref mut next) => Some(next),
+            &mut Contents::Bold(_, _, ref mut next) => Some(next),
+            &mut Contents::Code(_, _, ref mut next) => Some(next),
+            &mut Contents::Normal(_, _, ref mut next) => Some(next),
+            &mut Contents::Link(_, _, _, _, ref mut next) => Some(next),
+            &mut Contents::Image(_, _, _, ref mut next) => Some(next),
+            &mut Contents::SoftBreak(_, ref mut next) => Some(next),
+            &mut Contents::HardBreak(_, ref mut next) => Some(next),
+            &mut Contents::Nil => None,
+        }
+    }
+
+    /// The part of the source this span was parsed from, or `None` for
+    /// the end of the chain.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            &Contents::Italic(_, span, _) | &Contents::Bold(_, span, _) |
+            &Contents::Code(_, span, _) | &Contents::Normal(_, span, _) |
+            &Contents::Link(_, _, _, span, _) | &Contents::Image(_, _, span, _) |
+            &Contents::SoftBreak(span, _) | &Contents::HardBreak(span, _) => Some(span),
+            &Contents::Nil => None,
+        }
+    }
+
+    /// The part of the source this span was parsed from, mutably.
+    pub fn span_mut<'a>(&'a mut self) -> Option<&'a mut Span> {
+        match self {
+            &mut Contents::Italic(_, ref mut span, _) | &mut Contents::Bold(_, ref mut span, _) |
+            &mut Contents::Code(_, ref mut span, _) | &mut Contents::Normal(_, ref mut span, _) |
+            &mut Contents::Link(_, _, _, ref mut span, _) |
+            &mut Contents::Image(_, _, ref mut span, _) |
+            &mut Contents::SoftBreak(ref mut span, _) |
+            &mut Contents::HardBreak(ref mut span, _) => Some(span),
+            &mut Contents::Nil => None,
+        }
+    }
+
+    /// Iterate over the spans of the chain, not including the final `Nil`.
+    pub fn spans<'a>(&'a self) -> Spans<'a> {
+        Spans { span: self }
+    }
+
+    /// The text of the chain with all markup dropped. Line breaks become
+    /// spaces.
+    pub fn plain_text(&self) -> String {
+        let mut text = String::new();
+        for span in self.spans() {
+            match span {
+                &Contents::Italic(ref s, _, _) | &Contents::Bold(ref s, _, _) |
+                &Contents::Code(ref s, _, _) | &Contents::Normal(ref s, _, _) |
+                &Contents::Link(ref s, _, _, _, _) | &Contents::Image(ref s, _, _, _) => {
+                    text.push_str(s)
+                }
+                &Contents::SoftBreak(..) | &Contents::HardBreak(..) => text.push(' '),
+                &Contents::Nil => {}
+            }
+        }
+        text
+    }
+}
+
+/// An iterator over the spans of a `Contents` chain.
+pub struct Spans<'a> {
+    span: &'a Contents,
+}
+
+impl<'a> Iterator for Spans<'a> {
+    type Item = &'a Contents;
+
+    fn next(&mut self) -> Option<&'a Contents> {
+        match self.span.next() {
+            Some(next) => {
+                let span = self.span;
+                self.span = next;
+                Some(span)
+            }
+            None => None,
+        }
+    }
+}
+
+/// The events for the spans of the chain, without any enclosing tag.
+/// Markup holds a single `Text` event, since its text is plain.
+impl ToEvents for Contents {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        for span in self.spans() {
+            match span {
+                &Contents::Italic(ref s, span, _) => push_tagged(Tag::Emphasis, s, span, events),
+                &Contents::Bold(ref s, span, _) => push_tagged(Tag::Strong, s, span, events),
+                &Contents::Code(ref s, span, _) => events.push((Event::Code(s.clone()), span)),
+                &Contents::Normal(ref s, span, _) => events.push((Event::Text(s.clone()), span)),
+                &Contents::Link(ref s, ref url, ref title, span, _) => {
+                    push_tagged(Tag::Link(url.clone(), title.clone()), s, span, events)
+                }
+                &Contents::Image(ref alt, ref src, span, _) => {
+                    push_tagged(Tag::Image(src.clone()), alt, span, events)
+                }
+                &Contents::SoftBreak(span, _) => events.push((Event::SoftBreak, span)),
+                &Contents::HardBreak(span, _) => events.push((Event::HardBreak, span)),
+                &Contents::Nil => {}
+            }
+        }
     }
 }
 
+fn push_tagged(tag: Tag, text: &str, span: Span, events: &mut Vec<(Event, Span)>) {
+    events.push((Event::Start(tag.clone()), span));
+    if !text.is_empty() {
+        events.push((Event::Text(text.to_string()), span));
+    }
+    events.push((Event::End(tag), span));
+}
+
+/// Parse the lines of a paragraph into inline contents, keeping track of
+/// where the line breaks fall.
+///
+/// A line ending in two or more spaces, or in a backslash, is followed
+/// by a hard break. Any other line ending is a soft break. Neither kind
+/// of break is produced after the last line.
+pub fn parse_lines(lines: &[String]) -> Contents {
+    let mut events = vec![];
+    let starts = span::line_starts(lines, Position::start());
+    paragraph_inline_events(lines, &starts, &parser::References::new(), &mut events);
+    build::build_inline(&mut events.into_iter())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use html::{HtmlOptions, SoftBreakStyle, WriteHtml};
+    use span::{Position, Span};
+    use testing::lines;
+    use types::Paragraph;
+
+    fn render(contents: &Contents, soft_break: SoftBreakStyle) -> String {
+        let mut options = HtmlOptions::default();
+        options.soft_break = soft_break;
+        Paragraph::with_contents(contents.clone()).to_html_with(&options)
+    }
 
     #[test]
-    fn test() {
+    fn test_soft_breaks() {
+        let contents = parse_lines(&lines(&["Hello,", "  World "]));
+        let span = |line, start, end, offset| {
+            Span::new(Position::new(line, start, offset),
+                      Position::new(line, end, offset + end - start))
+        };
+        assert_eq!(contents,
+                   Contents::Normal("Hello,".to_string(), span(1, 1, 7, 0), Box::new(
+                       Contents::SoftBreak(span(1, 7, 7, 6), Box::new(
+                           Contents::Normal("World".to_string(), span(2, 3, 8, 9),
+                                            Box::new(Contents::Nil)))))));
+        assert_eq!(render(&contents, SoftBreakStyle::Newline),
+                   "<p>Hello,\nWorld</p>");
+        assert_eq!(render(&contents, SoftBreakStyle::Space),
+                   "<p>Hello, World</p>");
+        assert_eq!(render(&contents, SoftBreakStyle::LineBreak),
+                   "<p>Hello,<br />\nWorld</p>");
+    }
+
+    #[test]
+    fn test_hard_breaks() {
+        let contents = parse_lines(&lines(&["Two spaces  ", "Backslash\\", "End\\"]));
+        assert_eq!(render(&contents, SoftBreakStyle::Space),
+                   "<p>Two spaces<br />\nBackslash<br />\nEnd\\</p>");
+    }
 
+    #[test]
+    fn test_escaped_backslash_is_not_a_break() {
+        let contents = parse_lines(&lines(&["One \\\\", "Two"]));
+        assert_eq!(render(&contents, SoftBreakStyle::Newline),
+                   "<p>One \\\nTwo</p>");
+    }
+
+    #[test]
+    fn test_inline_markup() {
+        let contents = parse_lines(&lines(&["A *b* **c** `d` [e](f.html) ![g](h.png)"]));
+        assert_eq!(contents.spans().count(), 10);
+        assert_eq!(render(&contents, SoftBreakStyle::Newline),
+                   "<p>A <em>b</em> <strong>c</strong> <code>d</code> \
+                    <a href=\"f.html\">e</a> <img src=\"h.png\" alt=\"g\" /></p>");
+        assert_eq!(contents.plain_text(), "A b c d e g");
+    }
+
+    #[test]
+    fn test_from_spans() {
+        let contents = Contents::from_spans(vec![Contents::new("a".to_string()),
+                                                 Contents::SoftBreak(Span::default(),
+                                                                     Box::new(Contents::Nil)),
+                                                 Contents::new("b".to_string())]);
+        assert_eq!(render(&contents, SoftBreakStyle::Space), "<p>a b</p>");
+        assert_eq!(contents.spans().count(), 3);
+        assert_eq!(Contents::from_spans(vec![]), Contents::Nil);
     }
 }
@@ -9,36 +9,83 @@
 
 pub use self::bulletlist::Bullet;
 pub use self::bulletlist::BulletList;
+pub use self::blockquote::BlockQuote;
+pub use self::codeblock::CodeBlock;
+pub use self::htmlblock::HtmlBlock;
+pub use self::document::Document;
 pub use self::heading::Heading;
 pub use self::paragraph::Paragraph;
+pub use self::table::{Table, TableRow, TableCell};
 pub use self::heading::parse_heading;
 pub use self::paragraph::parse_paragraph;
 pub use self::bulletlist::parse_bulletlist;
-use html::Html;
-use html::ToHtml;
+pub use self::visit::Visitor;
+pub use self::visit::VisitorMut;
+use parser::{Event, ToEvents};
+use span::Span;
 
+pub mod build;
 pub mod inline;
 pub mod heading;
 pub mod paragraph;
 pub mod bulletlist;
+pub mod blockquote;
+pub mod codeblock;
+pub mod htmlblock;
+pub mod table;
+pub mod document;
+pub mod visit;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum MarkdownStructure {
     MDH(Heading),
     MDP(Paragraph),
     MDB(BulletList),
+    /// A thematic break, such as `---`.
+    MDR(Span),
+    MDC(CodeBlock),
+    /// A block of raw HTML.
+    MDX(HtmlBlock),
+    MDQ(BlockQuote),
+    MDT(Table),
 }
 
 impl MarkdownStructure {
+    /// Whether this is a block of raw HTML, which only HTML and markdown
+    /// output have any use for.
+    pub fn is_html(&self) -> bool {
+        match self {
+            &MarkdownStructure::MDX(_) => true,
+            _ => false,
+        }
+    }
 
+    /// The part of the source the block was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            &MarkdownStructure::MDH(ref heading) => heading.span(),
+            &MarkdownStructure::MDP(ref paragraph) => paragraph.span(),
+            &MarkdownStructure::MDB(ref bulletlist) => bulletlist.span(),
+            &MarkdownStructure::MDR(span) => span,
+            &MarkdownStructure::MDC(ref code) => code.span(),
+            &MarkdownStructure::MDX(ref html) => html.span(),
+            &MarkdownStructure::MDQ(ref quote) => quote.span(),
+            &MarkdownStructure::MDT(ref table) => table.span(),
+        }
+    }
 }
 
-impl ToHtml for MarkdownStructure {
-    fn to_html(&self) -> Html {
+impl ToEvents for MarkdownStructure {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
         match self {
-            &MarkdownStructure::MDH(ref heading) => heading.to_html().clone(),
-            &MarkdownStructure::MDP(ref paragraph) => paragraph.to_html().clone(),
-            &MarkdownStructure::MDB(ref bulletlist) => bulletlist.to_html().clone(),
+            &MarkdownStructure::MDH(ref heading) => heading.to_events(events),
+            &MarkdownStructure::MDP(ref paragraph) => paragraph.to_events(events),
+            &MarkdownStructure::MDB(ref bulletlist) => bulletlist.to_events(events),
+            &MarkdownStructure::MDR(span) => events.push((Event::Rule, span)),
+            &MarkdownStructure::MDC(ref code) => code.to_events(events),
+            &MarkdownStructure::MDX(ref html) => html.to_events(events),
+            &MarkdownStructure::MDQ(ref quote) => quote.to_events(events),
+            &MarkdownStructure::MDT(ref table) => table.to_events(events),
         }
     }
 }
@@ -7,41 +7,69 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use html::ToHtml;
-use html::Html;
+use parser::{Event, Tag, ToEvents};
+use span::Span;
+use super::inline;
+use super::inline::Contents;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Paragraph {
-    contents: String,
+    contents: Contents,
+    span: Span,
 }
 
 impl Paragraph {
     pub fn new(contents: String) -> Paragraph {
+        Paragraph::with_contents(Contents::new(contents))
+    }
+
+    pub fn with_contents(contents: Contents) -> Paragraph {
         Paragraph {
             contents: contents,
+            span: Span::default(),
         }
     }
+
+    pub fn contents(&self) -> &Contents {
+        &self.contents
+    }
+
+    pub fn contents_mut(&mut self) -> &mut Contents {
+        &mut self.contents
+    }
+
+    /// The part of the source the paragraph was parsed from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
-impl ToHtml for Paragraph {
-    fn to_html(&self) -> Html {
-        Html::new_simple("p".to_string(), self.contents.clone())
+impl ToEvents for Paragraph {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::Start(Tag::Paragraph), self.span));
+        self.contents.to_events(events);
+        events.push((Event::End(Tag::Paragraph), self.span));
     }
 }
 
 pub fn parse_paragraph(block: &Vec<String>) -> Paragraph {
-    Paragraph::new(
-        block.iter().fold(
-            "".to_string(),
-            |res, line| res + "\n" + line
-        ).trim().to_string()
-    )
+    let start = block.iter().position(|line| !line.trim().is_empty())
+                            .unwrap_or(block.len());
+    let end = block.iter().rposition(|line| !line.trim().is_empty())
+                          .map_or(start, |i| i + 1);
+    Paragraph::with_contents(inline::parse_lines(&block[start..end]))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Paragraph};
-    use html::ToHtml;
+    use super::{Paragraph, parse_paragraph};
+    use html::{WriteHtml, HtmlOptions, SoftBreakStyle};
+    use types::parse_heading;
+    use types::parse_bulletlist;
 
     fn paragraph_equals(input: &str, result: &str) {
         assert_eq!(format!("{}", Paragraph::new(input.to_string()).to_html()),
@@ -53,4 +81,40 @@ mod tests {
         paragraph_equals("Hello, World", "<p>Hello, World</p>");
         paragraph_equals("Hello,\nWorld", "<p>Hello,\nWorld</p>");
     }
+
+    #[test]
+    fn test_escaped_block_markers() {
+        let block = vec!["\\# Not a heading".to_string()];
+        assert!(parse_heading(&block).is_none());
+        assert_eq!(format!("{}", parse_paragraph(&block).to_html()),
+                   "<p># Not a heading</p>".to_string());
+        let block = vec!["\\* Not a bullet".to_string()];
+        assert!(parse_bulletlist(&block).is_none());
+        assert_eq!(format!("{}", parse_paragraph(&block).to_html()),
+                   "<p>* Not a bullet</p>".to_string());
+    }
+
+    #[test]
+    fn test_paragraph_entities() {
+        let block = vec!["&copy; &#169; &#xA9;".to_string()];
+        assert_eq!(format!("{}", parse_paragraph(&block).to_html()),
+                   "<p>\u{a9} \u{a9} \u{a9}</p>".to_string());
+    }
+
+    #[test]
+    fn test_paragraph_line_breaks() {
+        let block = vec!["Roses are red,  ".to_string(),
+                         "violets are blue\\".to_string(),
+                         "this line is".to_string(),
+                         "soft-wrapped.".to_string()];
+        let paragraph = parse_paragraph(&block);
+        assert_eq!(format!("{}", paragraph.to_html()),
+                   "<p>Roses are red,<br />\nviolets are blue<br />\n\
+                    this line is\nsoft-wrapped.</p>".to_string());
+        let mut options = HtmlOptions::default();
+        options.soft_break = SoftBreakStyle::Space;
+        assert_eq!(format!("{}", paragraph.to_html_with(&options)),
+                   "<p>Roses are red,<br />\nviolets are blue<br />\n\
+                    this line is soft-wrapped.</p>".to_string());
+    }
 }
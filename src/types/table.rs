@@ -0,0 +1,167 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+
+use parser::{Alignment, Event, Tag, ToEvents};
+use span::Span;
+use super::inline::Contents;
+
+/// A table: a row of column headings, then the rows of its body.
+///
+#[derive(Clone, Debug)]
+pub struct Table {
+    alignments: Vec<Alignment>,
+    head: TableRow,
+    rows: Vec<TableRow>,
+    span: Span,
+}
+
+impl Table {
+    /// A table with `head` as its header row, and a column for each of
+    /// `alignments`.
+    pub fn new(alignments: Vec<Alignment>, head: TableRow) -> Table {
+        Table {
+            alignments: alignments,
+            head: head,
+            rows: vec![],
+            span: Span::default(),
+        }
+    }
+
+    pub fn alignments(&self) -> &[Alignment] {
+        &self.alignments
+    }
+
+    pub fn head(&self) -> &TableRow {
+        &self.head
+    }
+
+    pub fn head_mut(&mut self) -> &mut TableRow {
+        &mut self.head
+    }
+
+    /// The rows of the table's body.
+    pub fn rows(&self) -> &[TableRow] {
+        &self.rows
+    }
+
+    pub fn rows_mut(&mut self) -> &mut Vec<TableRow> {
+        &mut self.rows
+    }
+
+    pub fn push(&mut self, row: TableRow) {
+        self.rows.push(row);
+    }
+
+    /// The part of the source the table was parsed from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+/// A row of a table, with one cell for each column.
+///
+#[derive(Clone, Debug)]
+pub struct TableRow {
+    cells: Vec<TableCell>,
+    span: Span,
+}
+
+impl TableRow {
+    pub fn new(cells: Vec<TableCell>) -> TableRow {
+        TableRow {
+            cells: cells,
+            span: Span::default(),
+        }
+    }
+
+    pub fn cells(&self) -> &[TableCell] {
+        &self.cells
+    }
+
+    pub fn cells_mut(&mut self) -> &mut Vec<TableCell> {
+        &mut self.cells
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+/// A cell of a table, which holds inline contents.
+///
+#[derive(Clone, Debug)]
+pub struct TableCell {
+    contents: Contents,
+    span: Span,
+}
+
+impl TableCell {
+    pub fn with_contents(contents: Contents) -> TableCell {
+        TableCell {
+            contents: contents,
+            span: Span::default(),
+        }
+    }
+
+    pub fn contents(&self) -> &Contents {
+        &self.contents
+    }
+
+    pub fn contents_mut(&mut self) -> &mut Contents {
+        &mut self.contents
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl ToEvents for Table {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::Start(Tag::Table(self.alignments.clone())), self.span));
+        events.push((Event::Start(Tag::TableHead), self.head.span));
+        self.head.to_events(events);
+        events.push((Event::End(Tag::TableHead), self.head.span));
+        if !self.rows.is_empty() {
+            let body = Span::new(self.rows[0].span.start, self.rows[self.rows.len() - 1].span.end);
+            events.push((Event::Start(Tag::TableBody), body));
+            for row in self.rows.iter() {
+                row.to_events(events);
+            }
+            events.push((Event::End(Tag::TableBody), body));
+        }
+        events.push((Event::End(Tag::Table(self.alignments.clone())), self.span));
+    }
+}
+
+impl ToEvents for TableRow {
+    fn to_events(&self, events: &mut Vec<(Event, Span)>) {
+        events.push((Event::Start(Tag::TableRow), self.span));
+        for cell in self.cells.iter() {
+            events.push((Event::Start(Tag::TableCell), cell.span));
+            cell.contents.to_events(events);
+            events.push((Event::End(Tag::TableCell), cell.span));
+        }
+        events.push((Event::End(Tag::TableRow), self.span));
+    }
+}
@@ -0,0 +1,433 @@
+// Copyright 2014 The Rustdown Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Walking and rewriting parsed documents.
+//!
+//! Each method of `Visitor` and `VisitorMut` walks into the children of
+//! the node it's given by default, by calling the matching `walk_`
+//! function. Override a method to look at or change a kind of node, and
+//! call the `walk_` function from it to keep going into its children.
+
+use span::{Span, SourceMap};
+use super::{Document, MarkdownStructure, Heading, Paragraph, BulletList, Bullet, CodeBlock};
+use super::{BlockQuote, HtmlBlock, Table, TableRow};
+use super::bulletlist::BulletElement;
+use super::inline::Contents;
+
+/// Walks a document without changing it.
+pub trait Visitor {
+    fn visit_document(&mut self, document: &Document) {
+        walk_document(self, document)
+    }
+
+    fn visit_block(&mut self, block: &MarkdownStructure) {
+        walk_block(self, block)
+    }
+
+    fn visit_heading(&mut self, heading: &Heading) {
+        walk_heading(self, heading)
+    }
+
+    fn visit_paragraph(&mut self, paragraph: &Paragraph) {
+        walk_paragraph(self, paragraph)
+    }
+
+    fn visit_list(&mut self, list: &BulletList) {
+        walk_list(self, list)
+    }
+
+    fn visit_list_element(&mut self, element: &BulletElement) {
+        walk_list_element(self, element)
+    }
+
+    fn visit_bullet(&mut self, bullet: &Bullet) {
+        walk_bullet(self, bullet)
+    }
+
+    fn visit_rule(&mut self, _span: Span) {}
+
+    fn visit_code_block(&mut self, _code: &CodeBlock) {}
+
+    fn visit_html_block(&mut self, _html: &HtmlBlock) {}
+
+    fn visit_block_quote(&mut self, quote: &BlockQuote) {
+        walk_block_quote(self, quote)
+    }
+
+    fn visit_table(&mut self, table: &Table) {
+        walk_table(self, table)
+    }
+
+    /// Visit a chain of inline contents, such as a paragraph's.
+    fn visit_contents(&mut self, contents: &Contents) {
+        walk_contents(self, contents)
+    }
+
+    /// Visit a single inline span. This is never `Contents::Nil`.
+    fn visit_inline(&mut self, _inline: &Contents) {}
+}
+
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, document: &Document) {
+    for block in document.blocks().iter() {
+        visitor.visit_block(block);
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &MarkdownStructure) {
+    match block {
+        &MarkdownStructure::MDH(ref heading) => visitor.visit_heading(heading),
+        &MarkdownStructure::MDP(ref paragraph) => visitor.visit_paragraph(paragraph),
+        &MarkdownStructure::MDB(ref list) => visitor.visit_list(list),
+        &MarkdownStructure::MDR(span) => visitor.visit_rule(span),
+        &MarkdownStructure::MDC(ref code) => visitor.visit_code_block(code),
+        &MarkdownStructure::MDX(ref html) => visitor.visit_html_block(html),
+        &MarkdownStructure::MDQ(ref quote) => visitor.visit_block_quote(quote),
+        &MarkdownStructure::MDT(ref table) => visitor.visit_table(table),
+    }
+}
+
+/// Visits the contents of every cell, the head's first.
+pub fn walk_table<V: Visitor + ?Sized>(visitor: &mut V, table: &Table) {
+    for row in Some(table.head()).into_iter().chain(table.rows().iter()) {
+        for cell in row.cells().iter() {
+            visitor.visit_contents(cell.contents());
+        }
+    }
+}
+
+pub fn walk_block_quote<V: Visitor + ?Sized>(visitor: &mut V, quote: &BlockQuote) {
+    for block in quote.blocks().iter() {
+        visitor.visit_block(block);
+    }
+}
+
+pub fn walk_heading<V: Visitor + ?Sized>(visitor: &mut V, heading: &Heading) {
+    visitor.visit_contents(heading.contents())
+}
+
+pub fn walk_paragraph<V: Visitor + ?Sized>(visitor: &mut V, paragraph: &Paragraph) {
+    visitor.visit_contents(paragraph.contents())
+}
+
+pub fn walk_list<V: Visitor + ?Sized>(visitor: &mut V, list: &BulletList) {
+    for element in list.elements().iter() {
+        visitor.visit_list_element(element);
+    }
+}
+
+pub fn walk_list_element<V: Visitor + ?Sized>(visitor: &mut V, element: &BulletElement) {
+    match element {
+        &BulletElement::Nested(ref list) => visitor.visit_list(list),
+        &BulletElement::Lone(ref bullet) => visitor.visit_bullet(bullet),
+    }
+}
+
+pub fn walk_bullet<V: Visitor + ?Sized>(visitor: &mut V, bullet: &Bullet) {
+    visitor.visit_contents(bullet.contents())
+}
+
+pub fn walk_contents<V: Visitor + ?Sized>(visitor: &mut V, contents: &Contents) {
+    for inline in contents.spans() {
+        visitor.visit_inline(inline);
+    }
+}
+
+/// Walks a document, with the chance to change it along the way.
+///
+/// Nodes can be added or removed through the `Vec`s of blocks and list
+/// elements, and inline spans by replacing the chain given to
+/// `visit_contents_mut`.
+pub trait VisitorMut {
+    fn visit_document_mut(&mut self, document: &mut Document) {
+        walk_document_mut(self, document)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut MarkdownStructure) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_heading_mut(&mut self, heading: &mut Heading) {
+        walk_heading_mut(self, heading)
+    }
+
+    fn visit_paragraph_mut(&mut self, paragraph: &mut Paragraph) {
+        walk_paragraph_mut(self, paragraph)
+    }
+
+    fn visit_list_mut(&mut self, list: &mut BulletList) {
+        walk_list_mut(self, list)
+    }
+
+    fn visit_list_element_mut(&mut self, element: &mut BulletElement) {
+        walk_list_element_mut(self, element)
+    }
+
+    fn visit_bullet_mut(&mut self, bullet: &mut Bullet) {
+        walk_bullet_mut(self, bullet)
+    }
+
+    fn visit_rule_mut(&mut self, _span: &mut Span) {}
+
+    fn visit_code_block_mut(&mut self, _code: &mut CodeBlock) {}
+
+    fn visit_html_block_mut(&mut self, _html: &mut HtmlBlock) {}
+
+    fn visit_block_quote_mut(&mut self, quote: &mut BlockQuote) {
+        walk_block_quote_mut(self, quote)
+    }
+
+    fn visit_table_mut(&mut self, table: &mut Table) {
+        walk_table_mut(self, table)
+    }
+
+    fn visit_contents_mut(&mut self, contents: &mut Contents) {
+        walk_contents_mut(self, contents)
+    }
+
+    /// Visit a single inline span. This is never `Contents::Nil`.
+    fn visit_inline_mut(&mut self, _inline: &mut Contents) {}
+}
+
+pub fn walk_document_mut<V: VisitorMut + ?Sized>(visitor: &mut V, document: &mut Document) {
+    for block in document.blocks_mut().iter_mut() {
+        visitor.visit_block_mut(block);
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut MarkdownStructure) {
+    match block {
+        &mut MarkdownStructure::MDH(ref mut heading) => visitor.visit_heading_mut(heading),
+        &mut MarkdownStructure::MDP(ref mut paragraph) => visitor.visit_paragraph_mut(paragraph),
+        &mut MarkdownStructure::MDB(ref mut list) => visitor.visit_list_mut(list),
+        &mut MarkdownStructure::MDR(ref mut span) => visitor.visit_rule_mut(span),
+        &mut MarkdownStructure::MDC(ref mut code) => visitor.visit_code_block_mut(code),
+        &mut MarkdownStructure::MDX(ref mut html) => visitor.visit_html_block_mut(html),
+        &mut MarkdownStructure::MDQ(ref mut quote) => visitor.visit_block_quote_mut(quote),
+        &mut MarkdownStructure::MDT(ref mut table) => visitor.visit_table_mut(table),
+    }
+}
+
+pub fn walk_table_mut<V: VisitorMut + ?Sized>(visitor: &mut V, table: &mut Table) {
+    for cell in table.head_mut().cells_mut().iter_mut() {
+        visitor.visit_contents_mut(cell.contents_mut());
+    }
+    for row in table.rows_mut().iter_mut() {
+        for cell in row.cells_mut().iter_mut() {
+            visitor.visit_contents_mut(cell.contents_mut());
+        }
+    }
+}
+
+pub fn walk_block_quote_mut<V: VisitorMut + ?Sized>(visitor: &mut V, quote: &mut BlockQuote) {
+    for block in quote.blocks_mut().iter_mut() {
+        visitor.visit_block_mut(block);
+    }
+}
+
+pub fn walk_heading_mut<V: VisitorMut + ?Sized>(visitor: &mut V, heading: &mut Heading) {
+    visitor.visit_contents_mut(heading.contents_mut())
+}
+
+pub fn walk_paragraph_mut<V: VisitorMut + ?Sized>(visitor: &mut V, paragraph: &mut Paragraph) {
+    visitor.visit_contents_mut(paragraph.contents_mut())
+}
+
+pub fn walk_list_mut<V: VisitorMut + ?Sized>(visitor: &mut V, list: &mut BulletList) {
+    for element in list.elements_mut().iter_mut() {
+        visitor.visit_list_element_mut(element);
+    }
+}
+
+pub fn walk_list_element_mut<V: VisitorMut + ?Sized>(visitor: &mut V,
+                                                     element: &mut BulletElement) {
+    match element {
+        &mut BulletElement::Nested(ref mut list) => visitor.visit_list_mut(list),
+        &mut BulletElement::Lone(ref mut bullet) => visitor.visit_bullet_mut(bullet),
+    }
+}
+
+pub fn walk_bullet_mut<V: VisitorMut + ?Sized>(visitor: &mut V, bullet: &mut Bullet) {
+    visitor.visit_contents_mut(bullet.contents_mut())
+}
+
+pub fn walk_contents_mut<V: VisitorMut + ?Sized>(visitor: &mut V, contents: &mut Contents) {
+    let mut inline = contents;
+    loop {
+        if let &mut Contents::Nil = inline {
+            break;
+        }
+        visitor.visit_inline_mut(inline);
+        inline = match { inline }.next_mut() {
+            Some(next) => next,
+            None => break,
+        };
+    }
+}
+
+/// Moves every heading down (or up, if negative) by a number of levels,
+/// keeping the depth between 1 and 6.
+pub struct ShiftHeadings(pub i32);
+
+impl VisitorMut for ShiftHeadings {
+    fn visit_heading_mut(&mut self, heading: &mut Heading) {
+        let depth = heading.depth() as i32 + self.0;
+        heading.set_depth(if depth < 1 { 1 } else if depth > 6 { 6 } else { depth as u32 });
+        walk_heading_mut(self, heading)
+    }
+}
+
+/// Maps every span in a document back to the source the lines it was
+/// parsed from were read from.
+pub struct MapSpans(pub SourceMap);
+
+impl MapSpans {
+    fn map_row(&self, row: &mut TableRow) {
+        row.set_span(self.0.span(row.span()));
+        for cell in row.cells_mut().iter_mut() {
+            cell.set_span(self.0.span(cell.span()));
+        }
+    }
+}
+
+impl VisitorMut for MapSpans {
+    fn visit_heading_mut(&mut self, heading: &mut Heading) {
+        heading.set_span(self.0.span(heading.span()));
+        walk_heading_mut(self, heading)
+    }
+
+    fn visit_paragraph_mut(&mut self, paragraph: &mut Paragraph) {
+        paragraph.set_span(self.0.span(paragraph.span()));
+        walk_paragraph_mut(self, paragraph)
+    }
+
+    fn visit_list_mut(&mut self, list: &mut BulletList) {
+        list.set_span(self.0.span(list.span()));
+        walk_list_mut(self, list)
+    }
+
+    fn visit_bullet_mut(&mut self, bullet: &mut Bullet) {
+        bullet.set_span(self.0.span(bullet.span()));
+        walk_bullet_mut(self, bullet)
+    }
+
+    fn visit_rule_mut(&mut self, span: &mut Span) {
+        *span = self.0.span(*span);
+    }
+
+    fn visit_code_block_mut(&mut self, code: &mut CodeBlock) {
+        code.set_span(self.0.span(code.span()));
+    }
+
+    fn visit_html_block_mut(&mut self, html: &mut HtmlBlock) {
+        html.set_span(self.0.span(html.span()));
+    }
+
+    fn visit_block_quote_mut(&mut self, quote: &mut BlockQuote) {
+        quote.set_span(self.0.span(quote.span()));
+        walk_block_quote_mut(self, quote)
+    }
+
+    fn visit_table_mut(&mut self, table: &mut Table) {
+        table.set_span(self.0.span(table.span()));
+        self.map_row(table.head_mut());
+        for row in table.rows_mut().iter_mut() {
+            self.map_row(row);
+        }
+        walk_table_mut(self, table)
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Contents) {
+        if let Some(span) = inline.span_mut() {
+            *span = self.0.span(*span);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use convert::parse_document;
+    use html::HtmlOptions;
+    use span::Span;
+    use types::{Heading, MarkdownStructure};
+    use types::inline::Contents;
+    use testing::lines;
+
+    struct Counter {
+        headings: usize,
+        bullets: usize,
+        links: Vec<String>,
+    }
+
+    impl Visitor for Counter {
+        fn visit_heading(&mut self, heading: &Heading) {
+            self.headings += 1;
+            walk_heading(self, heading)
+        }
+
+        fn visit_bullet(&mut self, bullet: &Bullet) {
+            self.bullets += 1;
+            walk_bullet(self, bullet)
+        }
+
+        fn visit_inline(&mut self, inline: &Contents) {
+            if let &Contents::Link(_, ref url, _, _, _) = inline {
+                self.links.push(url.clone());
+            }
+        }
+    }
+
+    struct RewriteLinks;
+
+    impl VisitorMut for RewriteLinks {
+        fn visit_inline_mut(&mut self, inline: &mut Contents) {
+            if let &mut Contents::Link(_, ref mut url, _, _, _) = inline {
+                *url = url.replace(".md", ".html");
+            }
+        }
+    }
+
+    struct RuleAfterHeadings;
+
+    impl VisitorMut for RuleAfterHeadings {
+        fn visit_document_mut(&mut self, document: &mut Document) {
+            let blocks = document.blocks_mut();
+            let mut i = 0;
+            while i < blocks.len() {
+                if let MarkdownStructure::MDH(_) = blocks[i] {
+                    blocks.insert(i + 1, MarkdownStructure::MDR(Span::default()));
+                }
+                i += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let document = parse_document(&lines(&["# [A](a.md)", "", "- [b](b.md)", "  - c",
+                                               "", "## D"])).unwrap();
+        let mut counter = Counter { headings: 0, bullets: 0, links: vec![] };
+        counter.visit_document(&document);
+        assert_eq!((counter.headings, counter.bullets), (2, 2));
+        assert_eq!(counter.links, vec!["a.md", "b.md"]);
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut document = parse_document(&lines(&["# A", "", "See [b](b.md) and [c](c.md)", "",
+                                                   "###### E"])).unwrap();
+        RewriteLinks.visit_document_mut(&mut document);
+        ShiftHeadings(1).visit_document_mut(&mut document);
+        RuleAfterHeadings.visit_document_mut(&mut document);
+        assert_eq!(document.body_html(&HtmlOptions::default()),
+                   "<h2>A</h2>\n<hr />\n\
+                    <p>See <a href=\"b.html\">b</a> and <a href=\"c.html\">c</a></p>\n\
+                    <h6>E</h6>\n<hr />");
+    }
+}